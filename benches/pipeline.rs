@@ -0,0 +1,71 @@
+//! Raster pipeline: PNG round trips between stages versus image buffers encoded once
+//!
+//! Run with `cargo bench --bench pipeline`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pay_by_square_generator::{
+    add_caption, add_frame, caption_lines, generate_pay_by_square_code,
+    generate_pay_by_square_qr_with_profile, generate_qr_image_with_options, FrameTemplate,
+    PaymentRequest, QrOptions, ValidationProfile,
+};
+
+const FRAME: &[u8] = include_bytes!("../frames/classic.png");
+
+fn payment() -> PaymentRequest {
+    serde_json::from_value(serde_json::json!({
+        "amount": 1234.5,
+        "iban": "SK9611000000002918599669",
+        "variable_symbol": "1234567890",
+        "beneficiary_name": "John Doe",
+        "note": "Payment for invoice"
+    }))
+    .unwrap()
+}
+
+fn pipeline(c: &mut Criterion) {
+    let payment = payment();
+    let profile = ValidationProfile::standard();
+    let opts = QrOptions {
+        qr_size: 600,
+        caption: true,
+        ..Default::default()
+    };
+    let lines = caption_lines(&payment, &opts.caption_fields, Default::default());
+
+    let mut group = c.benchmark_group("framed_png_with_caption");
+
+    // Every stage decodes the previous PNG, the frame is decoded per request
+    group.bench_function("png_round_trip", |b| {
+        b.iter(|| {
+            let code = generate_pay_by_square_code(&payment).unwrap();
+            let unframed = QrOptions {
+                with_frame: false,
+                ..opts.clone()
+            };
+            let png = generate_qr_image_with_options(&code, &unframed).unwrap();
+            let png = add_frame(png, Some(FRAME)).unwrap();
+            black_box(add_caption(png, &lines, &opts).unwrap())
+        })
+    });
+
+    // Frame decoded once, stages pass image buffers and PNG is encoded once
+    let template = FrameTemplate::from_image("frame", FRAME).unwrap();
+    group.bench_function("in_memory", |b| {
+        b.iter(|| {
+            black_box(
+                generate_pay_by_square_qr_with_profile(
+                    &payment,
+                    opts.clone(),
+                    Some(&template),
+                    &profile,
+                )
+                .unwrap(),
+            )
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, pipeline);
+criterion_main!(benches);
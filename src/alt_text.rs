@@ -0,0 +1,85 @@
+use crate::caption::{format_amount, format_date, group_iban, payment_iban};
+use crate::i18n::{translate, Locale};
+use crate::models::PaymentRequest;
+
+/// Describes what a code pays, as a text alternative for screen readers
+///
+/// The description names the amount, the beneficiary and the IBAN, followed by
+/// the symbols, due date and note the payment has, e.g. "Payment of 100.50 EUR
+/// to John Doe, IBAN SK96 1100 0000 0029 1859 9669, variable symbol 1234567890".
+/// Wording, numbers and dates follow the locale.
+pub fn alt_text(payment: &PaymentRequest, locale: Locale) -> String {
+    let amount = format!(
+        "{} {}",
+        format_amount(payment.amount, locale),
+        payment.currency
+    );
+    let payment_text = match payment.beneficiary_name.as_deref() {
+        Some(name) if !name.trim().is_empty() => translate(locale, "alt.payment_to")
+            .replace("{amount}", &amount)
+            .replace("{beneficiary}", name.trim()),
+        _ => translate(locale, "alt.payment").replace("{amount}", &amount),
+    };
+
+    let details = [
+        ("alt.iban", payment_iban(payment).map(group_iban)),
+        ("alt.vs", payment.variable_symbol.clone()),
+        ("alt.ks", payment.constant_symbol.clone()),
+        ("alt.ss", payment.specific_symbol.clone()),
+        (
+            "alt.due_date",
+            payment
+                .payment_due_date
+                .map(|date| format_date(date, locale)),
+        ),
+        ("alt.note", payment.note.clone()),
+    ];
+
+    let mut parts = vec![payment_text];
+    parts.extend(details.into_iter().filter_map(|(key, value)| {
+        value
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| translate(locale, key).replace("{0}", value.trim()))
+    }));
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn payment() -> PaymentRequest {
+        serde_json::from_value(serde_json::json!({
+            "amount": 100.50,
+            "iban": "SK9611000000002918599669",
+            "beneficiary_name": "John Doe",
+            "variable_symbol": "1234567890"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_alt_text() {
+        assert_eq!(
+            alt_text(&payment(), Locale::En),
+            "Payment of 100.50 EUR to John Doe, IBAN SK96 1100 0000 0029 1859 9669, \
+             variable symbol 1234567890"
+        );
+
+        let payment = PaymentRequest {
+            amount: 1234.5,
+            beneficiary_name: None,
+            specific_symbol: Some("42".to_string()),
+            payment_due_date: NaiveDate::from_ymd_opt(2026, 3, 31),
+            note: Some("Faktúra 7".to_string()),
+            ..payment()
+        };
+        assert_eq!(
+            alt_text(&payment, Locale::Sk),
+            "Platba 1\u{a0}234,50 EUR, IBAN SK96 1100 0000 0029 1859 9669, \
+             variabilný symbol 1234567890, špecifický symbol 42, \
+             splatnosť 31.\u{a0}3.\u{a0}2026, poznámka: Faktúra 7"
+        );
+    }
+}
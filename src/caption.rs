@@ -0,0 +1,251 @@
+use crate::errors::PayBySquareError;
+use crate::i18n::{translate, Locale};
+use crate::models::PaymentRequest;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Payment detail printed in the caption under the code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptionField {
+    Amount,
+    Iban,
+    Vs,
+    Ks,
+    Ss,
+    DueDate,
+}
+
+impl CaptionField {
+    pub const ALL: [CaptionField; 6] = [
+        CaptionField::Amount,
+        CaptionField::Iban,
+        CaptionField::Vs,
+        CaptionField::Ks,
+        CaptionField::Ss,
+        CaptionField::DueDate,
+    ];
+
+    /// Returns the name used in the `caption_fields` option
+    pub fn name(&self) -> &'static str {
+        match self {
+            CaptionField::Amount => "amount",
+            CaptionField::Iban => "iban",
+            CaptionField::Vs => "vs",
+            CaptionField::Ks => "ks",
+            CaptionField::Ss => "ss",
+            CaptionField::DueDate => "due_date",
+        }
+    }
+
+    fn label_key(&self) -> &'static str {
+        match self {
+            CaptionField::Amount => "caption.amount",
+            CaptionField::Iban => "caption.iban",
+            CaptionField::Vs => "caption.vs",
+            CaptionField::Ks => "caption.ks",
+            CaptionField::Ss => "caption.ss",
+            CaptionField::DueDate => "caption.due_date",
+        }
+    }
+}
+
+/// Ordered selection of caption fields, written as a comma-separated list (e.g. `amount,iban,vs`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(try_from = "String", into = "String")]
+#[schema(value_type = String, example = "amount,iban,vs")]
+pub struct CaptionFields(pub Vec<CaptionField>);
+
+impl Default for CaptionFields {
+    fn default() -> Self {
+        Self(CaptionField::ALL.to_vec())
+    }
+}
+
+impl fmt::Display for CaptionFields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.0.iter().map(CaptionField::name).collect();
+        f.write_str(&names.join(","))
+    }
+}
+
+impl FromStr for CaptionFields {
+    type Err = PayBySquareError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                CaptionField::ALL
+                    .into_iter()
+                    .find(|field| field.name() == name)
+                    .ok_or(PayBySquareError::ValidationError {
+                        key: "validation.caption_field",
+                    })
+            })
+            .collect::<Result<_, _>>()
+            .map(CaptionFields)
+    }
+}
+
+impl TryFrom<String> for CaptionFields {
+    type Error = PayBySquareError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<CaptionFields> for String {
+    fn from(fields: CaptionFields) -> Self {
+        fields.to_string()
+    }
+}
+
+/// Builds the caption lines for the selected fields of a payment
+///
+/// Fields the payment doesn't have are skipped. Labels, numbers and dates
+/// follow the locale.
+pub fn caption_lines(
+    payment: &PaymentRequest,
+    fields: &CaptionFields,
+    locale: Locale,
+) -> Vec<String> {
+    fields
+        .0
+        .iter()
+        .filter_map(|field| {
+            let value = match field {
+                CaptionField::Amount => Some(format!(
+                    "{} {}",
+                    format_amount(payment.amount, locale),
+                    payment.currency
+                )),
+                CaptionField::Iban => payment_iban(payment).map(group_iban),
+                CaptionField::Vs => payment.variable_symbol.clone(),
+                CaptionField::Ks => payment.constant_symbol.clone(),
+                CaptionField::Ss => payment.specific_symbol.clone(),
+                CaptionField::DueDate => payment
+                    .payment_due_date
+                    .map(|date| format_date(date, locale)),
+            }?;
+            Some(format!(
+                "{}: {}",
+                translate(locale, field.label_key()),
+                value
+            ))
+        })
+        .collect()
+}
+
+/// Formats an amount with two decimals and grouped thousands
+///
+/// English uses `1,234.50`; Slovak and Czech use `1 234,50` with no-break spaces.
+pub fn format_amount(amount: f64, locale: Locale) -> String {
+    let (group, decimal) = match locale {
+        Locale::En => (',', '.'),
+        Locale::Sk | Locale::Cs => ('\u{a0}', ','),
+    };
+
+    let formatted = format!("{:.2}", amount.abs());
+    let (whole, fraction) = formatted.split_once('.').unwrap_or((&formatted, "00"));
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(group);
+        }
+        grouped.push(digit);
+    }
+
+    let sign = if amount < 0.0 { "-" } else { "" };
+    format!("{}{}{}{}", sign, grouped, decimal, fraction)
+}
+
+/// Formats a date as `2024-12-31` (English) or `31. 12. 2024` (Slovak, Czech)
+pub fn format_date(date: NaiveDate, locale: Locale) -> String {
+    match locale {
+        Locale::En => date.format("%Y-%m-%d").to_string(),
+        Locale::Sk | Locale::Cs => date.format("%-d.\u{a0}%-m.\u{a0}%Y").to_string(),
+    }
+}
+
+/// Returns the IBAN of the payment, or of its first bank account
+pub(crate) fn payment_iban(payment: &PaymentRequest) -> Option<&str> {
+    payment.iban.as_deref().or_else(|| {
+        payment
+            .bank_accounts
+            .as_ref()
+            .and_then(|accounts| accounts.first())
+            .map(|account| account.iban.as_str())
+    })
+}
+
+/// Formats an IBAN in groups of four characters
+pub(crate) fn group_iban(iban: &str) -> String {
+    let chars: Vec<char> = iban.chars().filter(|c| !c.is_whitespace()).collect();
+    chars
+        .chunks(4)
+        .map(|group| group.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment() -> PaymentRequest {
+        serde_json::from_value(serde_json::json!({
+            "amount": 1234.5,
+            "iban": "SK9611000000002918599669",
+            "variable_symbol": "1234567890",
+            "constant_symbol": "0308",
+            "payment_due_date": "2024-12-31"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_caption_lines() {
+        let payment: PaymentRequest = serde_json::from_value(serde_json::json!({
+            "amount": 100.5,
+            "iban": "SK9611000000002918599669",
+            "variable_symbol": "1234567890"
+        }))
+        .unwrap();
+        assert_eq!(
+            caption_lines(&payment, &CaptionFields::default(), Locale::En),
+            [
+                "Amount: 100.50 EUR",
+                "IBAN: SK96 1100 0000 0029 1859 9669",
+                "VS: 1234567890"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_caption_lines_localized_selection() {
+        let fields: CaptionFields = "due_date, amount,ks".parse().unwrap();
+        assert_eq!(
+            caption_lines(&payment(), &fields, Locale::Sk),
+            [
+                "Splatnosť: 31.\u{a0}12.\u{a0}2024",
+                "Suma: 1\u{a0}234,50 EUR",
+                "KS: 0308"
+            ]
+        );
+        assert!("amount,name".parse::<CaptionFields>().is_err());
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(0.5, Locale::En), "0.50");
+        assert_eq!(format_amount(1234567.891, Locale::En), "1,234,567.89");
+        assert_eq!(format_amount(999.999, Locale::Cs), "1\u{a0}000,00");
+        assert_eq!(format_amount(-1500.0, Locale::Sk), "-1\u{a0}500,00");
+    }
+}
@@ -1,17 +1,20 @@
+use crate::i18n::{translate, Locale};
+use crate::models::OutputFormat;
+use actix_web::http::{header, StatusCode};
 use actix_web::{HttpResponse, ResponseError};
-use std::fmt;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum PayBySquareError {
-    #[error("Invalid IBAN format: {0}")]
-    InvalidIban(String),
+    /// `key` is the catalog key of the detail
+    #[error("Invalid IBAN format: {}", translate(Locale::En, key))]
+    InvalidIban { key: &'static str },
 
-    #[error("Invalid SWIFT/BIC format: {0}")]
-    InvalidSwift(String),
+    #[error("Invalid SWIFT/BIC format: {}", translate(Locale::En, key))]
+    InvalidSwift { key: &'static str },
 
-    #[error("Validation error: {0}")]
-    ValidationError(String),
+    #[error("Validation error: {}", translate(Locale::En, key))]
+    ValidationError { key: &'static str },
 
     #[error("Missing required field: either 'iban' or 'bank_accounts' must be provided")]
     MissingBankAccount,
@@ -26,6 +29,24 @@ pub enum PayBySquareError {
         actual: usize,
     },
 
+    #[error("Option out of range: {option} (min: {min}, max: {max})")]
+    OptionOutOfRange { option: String, min: f32, max: f32 },
+
+    #[error("Contrast ratio {ratio:.2} between foreground and background is below {min}")]
+    LowContrast { ratio: f32, min: f32 },
+
+    #[error("Foreground must be darker than background")]
+    InvertedColors,
+
+    #[error("QR version {version} exceeds the maximum of {max}")]
+    VersionTooHigh { version: i16, max: u8 },
+
+    #[error("Print size needs {pixels} pixels, more than the maximum of {max}")]
+    PrintSizeTooLarge { pixels: u32, max: u32 },
+
+    #[error("Symbol needs {pixels} pixels, more than the {max} of the frame template placement")]
+    SymbolTooLarge { pixels: u32, max: u32 },
+
     #[error("Compression failed: {0}")]
     CompressionError(String),
 
@@ -35,6 +56,12 @@ pub enum PayBySquareError {
     #[error("Image processing failed: {0}")]
     ImageError(String),
 
+    #[error("PDF generation failed: {0}")]
+    PdfError(String),
+
+    #[error("Invalid PDF document: {}", translate(Locale::En, key))]
+    InvalidPdf { key: &'static str },
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -43,26 +70,287 @@ pub enum PayBySquareError {
 
     #[error("Internal error: {0}")]
     InternalError(String),
+
+    #[error("None of the accepted formats is supported: {0}")]
+    NotAcceptable(String),
+
+    #[error("Unknown validation profile: {0}")]
+    UnknownProfile(String),
+
+    #[error("Validation profile {0} is not allowed for this API key")]
+    ProfileNotAllowed(String),
+
+    #[error("Unknown frame template: {0}")]
+    UnknownFrameTemplate(String),
+
+    #[error("Unknown logo: {0}")]
+    UnknownLogo(String),
+
+    #[error("Unknown slip template: {0}")]
+    UnknownSlipTemplate(String),
+
+    #[error("Generated code failed verification: {}", translate(Locale::En, key))]
+    VerificationFailed { key: &'static str },
+
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+
+    /// Error of one payment in a batch, numbered from 1
+    #[error("Payment {number}: {error}")]
+    BatchItem {
+        number: usize,
+        error: Box<PayBySquareError>,
+    },
+}
+
+impl PayBySquareError {
+    /// Returns the catalog key of the error message
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            PayBySquareError::InvalidIban { .. } => "error.invalid_iban",
+            PayBySquareError::InvalidSwift { .. } => "error.invalid_swift",
+            PayBySquareError::ValidationError { .. } => "error.validation",
+            PayBySquareError::MissingBankAccount => "error.missing_bank_account",
+            PayBySquareError::InvalidAmount => "error.invalid_amount",
+            PayBySquareError::FieldTooLong { .. } => "error.field_too_long",
+            PayBySquareError::OptionOutOfRange { .. } => "error.option_out_of_range",
+            PayBySquareError::LowContrast { .. } => "error.low_contrast",
+            PayBySquareError::InvertedColors => "error.inverted_colors",
+            PayBySquareError::VersionTooHigh { .. } => "error.version_too_high",
+            PayBySquareError::PrintSizeTooLarge { .. } => "error.print_size_too_large",
+            PayBySquareError::SymbolTooLarge { .. } => "error.symbol_too_large",
+            PayBySquareError::CompressionError(_) => "error.compression",
+            PayBySquareError::QrError(_) => "error.qr",
+            PayBySquareError::ImageError(_) => "error.image",
+            PayBySquareError::PdfError(_) => "error.pdf",
+            PayBySquareError::InvalidPdf { .. } => "error.invalid_pdf",
+            PayBySquareError::IoError(_) => "error.io",
+            PayBySquareError::SerializationError(_) => "error.serialization",
+            PayBySquareError::InternalError(_) => "error.internal",
+            PayBySquareError::NotAcceptable(_) => "error.not_acceptable",
+            PayBySquareError::UnknownProfile(_) => "error.unknown_profile",
+            PayBySquareError::ProfileNotAllowed(_) => "error.profile_not_allowed",
+            PayBySquareError::UnknownFrameTemplate(_) => "error.unknown_frame_template",
+            PayBySquareError::UnknownLogo(_) => "error.unknown_logo",
+            PayBySquareError::UnknownSlipTemplate(_) => "error.unknown_slip_template",
+            PayBySquareError::VerificationFailed { .. } => "error.verification_failed",
+            PayBySquareError::ConfigError(_) => "error.config",
+            PayBySquareError::BatchItem { .. } => "error.batch_item",
+        }
+    }
+
+    /// Formats the error message in the given locale
+    pub fn localized(&self, locale: Locale) -> String {
+        let template = translate(locale, self.message_key());
+        match self {
+            PayBySquareError::InvalidIban { key }
+            | PayBySquareError::InvalidSwift { key }
+            | PayBySquareError::ValidationError { key }
+            | PayBySquareError::VerificationFailed { key }
+            | PayBySquareError::InvalidPdf { key } => {
+                template.replace("{0}", translate(locale, key))
+            }
+            PayBySquareError::CompressionError(detail)
+            | PayBySquareError::QrError(detail)
+            | PayBySquareError::ImageError(detail)
+            | PayBySquareError::PdfError(detail)
+            | PayBySquareError::InternalError(detail)
+            | PayBySquareError::NotAcceptable(detail)
+            | PayBySquareError::UnknownProfile(detail)
+            | PayBySquareError::ProfileNotAllowed(detail)
+            | PayBySquareError::UnknownFrameTemplate(detail)
+            | PayBySquareError::UnknownLogo(detail)
+            | PayBySquareError::UnknownSlipTemplate(detail)
+            | PayBySquareError::ConfigError(detail) => template.replace("{0}", detail),
+            PayBySquareError::IoError(e) => template.replace("{0}", &e.to_string()),
+            PayBySquareError::SerializationError(e) => template.replace("{0}", &e.to_string()),
+            PayBySquareError::FieldTooLong { field, max, actual } => template
+                .replace("{field}", field)
+                .replace("{max}", &max.to_string())
+                .replace("{actual}", &actual.to_string()),
+            PayBySquareError::OptionOutOfRange { option, min, max } => template
+                .replace("{option}", option)
+                .replace("{min}", &min.to_string())
+                .replace("{max}", &max.to_string()),
+            PayBySquareError::LowContrast { ratio, min } => template
+                .replace("{ratio}", &format!("{:.2}", ratio))
+                .replace("{min}", &min.to_string()),
+            PayBySquareError::VersionTooHigh { version, max } => template
+                .replace("{version}", &version.to_string())
+                .replace("{max}", &max.to_string()),
+            PayBySquareError::PrintSizeTooLarge { pixels, max }
+            | PayBySquareError::SymbolTooLarge { pixels, max } => template
+                .replace("{pixels}", &pixels.to_string())
+                .replace("{max}", &max.to_string()),
+            PayBySquareError::BatchItem { number, error } => template
+                .replace("{number}", &number.to_string())
+                .replace("{error}", &error.localized(locale)),
+            PayBySquareError::MissingBankAccount
+            | PayBySquareError::InvalidAmount
+            | PayBySquareError::InvertedColors => template.to_string(),
+        }
+    }
+
+    /// Builds the HTTP error response with messages in the given locale
+    pub fn localized_response(&self, locale: Locale) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        response.insert_header((header::CONTENT_LANGUAGE, locale.code()));
+        match self {
+            PayBySquareError::NotAcceptable(_) => response.json(serde_json::json!({
+                "error": self.localized(locale),
+                "supported": OutputFormat::ALL.map(|format| format.content_type())
+            })),
+            _ if self.status_code().is_client_error() => response.json(serde_json::json!({
+                "error": self.localized(locale)
+            })),
+            _ => response.json(serde_json::json!({
+                "error": translate(locale, "error.internal_server")
+            })),
+        }
+    }
 }
 
 impl ResponseError for PayBySquareError {
-    fn error_response(&self) -> HttpResponse {
+    fn status_code(&self) -> StatusCode {
         match self {
-            PayBySquareError::ValidationError(_)
-            | PayBySquareError::InvalidIban(_)
-            | PayBySquareError::InvalidSwift(_)
+            PayBySquareError::ValidationError { .. }
+            | PayBySquareError::InvalidIban { .. }
+            | PayBySquareError::InvalidSwift { .. }
             | PayBySquareError::MissingBankAccount
             | PayBySquareError::InvalidAmount
-            | PayBySquareError::FieldTooLong { .. } => {
-                HttpResponse::BadRequest().json(serde_json::json!({
-                    "error": self.to_string()
-                }))
-            }
-            _ => HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Internal server error"
-            })),
+            | PayBySquareError::FieldTooLong { .. }
+            | PayBySquareError::OptionOutOfRange { .. }
+            | PayBySquareError::LowContrast { .. }
+            | PayBySquareError::InvertedColors
+            | PayBySquareError::VersionTooHigh { .. }
+            | PayBySquareError::PrintSizeTooLarge { .. }
+            | PayBySquareError::SymbolTooLarge { .. }
+            | PayBySquareError::InvalidPdf { .. }
+            | PayBySquareError::UnknownProfile(_)
+            | PayBySquareError::UnknownFrameTemplate(_)
+            | PayBySquareError::UnknownLogo(_)
+            | PayBySquareError::UnknownSlipTemplate(_) => StatusCode::BAD_REQUEST,
+            PayBySquareError::ProfileNotAllowed(_) => StatusCode::FORBIDDEN,
+            PayBySquareError::NotAcceptable(_) => StatusCode::NOT_ACCEPTABLE,
+            PayBySquareError::VerificationFailed { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            PayBySquareError::BatchItem { error, .. } => error.status_code(),
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
+
+    fn error_response(&self) -> HttpResponse {
+        self.localized_response(Locale::En)
+    }
 }
 
 pub type Result<T> = std::result::Result<T, PayBySquareError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_variants() -> Vec<PayBySquareError> {
+        vec![
+            PayBySquareError::InvalidIban {
+                key: "validation.iban_characters",
+            },
+            PayBySquareError::InvalidSwift {
+                key: "validation.swift_length",
+            },
+            PayBySquareError::ValidationError {
+                key: "validation.color_format",
+            },
+            PayBySquareError::MissingBankAccount,
+            PayBySquareError::InvalidAmount,
+            PayBySquareError::FieldTooLong {
+                field: "note".to_string(),
+                max: 140,
+                actual: 150,
+            },
+            PayBySquareError::OptionOutOfRange {
+                option: "qr_size".to_string(),
+                min: 100.0,
+                max: 2000.0,
+            },
+            PayBySquareError::LowContrast {
+                ratio: 1.234,
+                min: 4.0,
+            },
+            PayBySquareError::InvertedColors,
+            PayBySquareError::VersionTooHigh {
+                version: 12,
+                max: 10,
+            },
+            PayBySquareError::PrintSizeTooLarge {
+                pixels: 2362,
+                max: 2000,
+            },
+            PayBySquareError::SymbolTooLarge {
+                pixels: 53,
+                max: 40,
+            },
+            PayBySquareError::CompressionError("x".to_string()),
+            PayBySquareError::QrError("x".to_string()),
+            PayBySquareError::ImageError("x".to_string()),
+            PayBySquareError::PdfError("x".to_string()),
+            PayBySquareError::InvalidPdf {
+                key: "pdf.encrypted",
+            },
+            PayBySquareError::IoError(std::io::Error::other("x")),
+            PayBySquareError::SerializationError(serde_json::from_str::<u8>("x").unwrap_err()),
+            PayBySquareError::InternalError("x".to_string()),
+            PayBySquareError::NotAcceptable("x".to_string()),
+            PayBySquareError::UnknownProfile("x".to_string()),
+            PayBySquareError::ProfileNotAllowed("x".to_string()),
+            PayBySquareError::UnknownFrameTemplate("x".to_string()),
+            PayBySquareError::UnknownLogo("x".to_string()),
+            PayBySquareError::UnknownSlipTemplate("x".to_string()),
+            PayBySquareError::VerificationFailed {
+                key: "verify.amount",
+            },
+            PayBySquareError::ConfigError("x".to_string()),
+            PayBySquareError::BatchItem {
+                number: 3,
+                error: Box::new(PayBySquareError::InvalidAmount),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_english_catalog_matches_display() {
+        for error in all_variants() {
+            assert_eq!(error.localized(Locale::En), error.to_string());
+        }
+    }
+
+    #[test]
+    fn test_localized_messages() {
+        let error = PayBySquareError::FieldTooLong {
+            field: "note".to_string(),
+            max: 140,
+            actual: 150,
+        };
+        assert_eq!(
+            error.localized(Locale::Sk),
+            "Pole je príliš dlhé: note (max: 140, zadané: 150)"
+        );
+
+        let error = PayBySquareError::InvalidIban {
+            key: "validation.iban_characters",
+        };
+        assert_eq!(
+            error.localized(Locale::Cs),
+            "Neplatný formát IBAN: IBAN obsahuje neplatné znaky"
+        );
+
+        let error = PayBySquareError::BatchItem {
+            number: 2,
+            error: Box::new(PayBySquareError::InvalidAmount),
+        };
+        assert_eq!(
+            error.localized(Locale::Sk),
+            "Platba 2: Suma musí byť väčšia ako 0"
+        );
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+    }
+}
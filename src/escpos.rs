@@ -0,0 +1,276 @@
+use crate::errors::Result;
+use crate::logo::PlacedLogo;
+use crate::models::{QrOptions, RgbaColor};
+use crate::qr::{build_symbol, print_options, render_symbol};
+use crate::templates::FrameTemplate;
+use crate::zpl::is_dark;
+use image::RgbaImage;
+use qrcode::{EcLevel, QrCode};
+
+/// Print resolution of ESC/POS output when `opts.dpi` is not set (8 dots/mm)
+pub const ESCPOS_DEFAULT_DPI: u32 = 203;
+
+/// Largest module size of the native QR command, in dots
+const MAX_MODULE_SIZE: u32 = 16;
+
+/// Largest data of the native QR command; longer codes are sent as a bit image
+const MAX_NATIVE_DATA: usize = 7089;
+
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+
+/// Code page PC852 (Latin 2), selected with `ESC t`
+const CODE_PAGE_PC852: u8 = 18;
+
+/// Characters of Slovak and Czech text outside ASCII and their PC852 codes
+const PC852: [(char, u8); 41] = [
+    ('\u{a0}', 0xFF),
+    ('á', 0xA0),
+    ('ä', 0x84),
+    ('č', 0x9F),
+    ('ď', 0xD4),
+    ('é', 0x82),
+    ('ě', 0xD8),
+    ('í', 0xA1),
+    ('ĺ', 0x92),
+    ('ľ', 0x96),
+    ('ň', 0xE5),
+    ('ó', 0xA2),
+    ('ô', 0x93),
+    ('ŕ', 0xEA),
+    ('ř', 0xFD),
+    ('š', 0xE7),
+    ('ť', 0x9C),
+    ('ú', 0xA3),
+    ('ů', 0x85),
+    ('ý', 0xEC),
+    ('ž', 0xA7),
+    ('Á', 0xB5),
+    ('Ä', 0x8E),
+    ('Č', 0xAC),
+    ('Ď', 0xD2),
+    ('É', 0x90),
+    ('Ě', 0xB7),
+    ('Í', 0xD6),
+    ('Ĺ', 0x91),
+    ('Ľ', 0x95),
+    ('Ň', 0xD5),
+    ('Ó', 0xE0),
+    ('Ô', 0xE2),
+    ('Ŕ', 0xE8),
+    ('Ř', 0xFC),
+    ('Š', 0xE6),
+    ('Ť', 0x9B),
+    ('Ú', 0xE9),
+    ('Ů', 0xDE),
+    ('Ý', 0xED),
+    ('Ž', 0xA6),
+];
+
+/// Generates ESC/POS commands printing the code on a receipt
+///
+/// `opts.receipt_header`, the code, `caption` and `opts.receipt_footer` are
+/// printed centred, with text in code page PC852. Sizes are in printer dots:
+/// `opts.qr_size`, or `opts.size_mm` at `opts.dpi` (default 203). The code is
+/// sent as the printer's own QR code (model 2) when the symbol needs nothing
+/// the command can't express: no frame, no logo and a module of at most 16
+/// dots. Otherwise the image that PNG output would contain is sent as a raster
+/// bit image, with colours printed black when darker than mid-grey.
+///
+/// The paper is not cut, so the commands can be part of a longer receipt.
+pub fn generate_qr_escpos(
+    code: &str,
+    opts: &QrOptions,
+    caption: &[String],
+    template: Option<&FrameTemplate>,
+) -> Result<Vec<u8>> {
+    let (symbol, logo) = build_symbol(code, opts)?;
+    render_escpos(code, &symbol, logo.as_ref(), opts, caption, template)
+}
+
+/// Renders the encoded symbol of `code`, see [`generate_qr_escpos`]
+pub(crate) fn render_escpos(
+    code: &str,
+    symbol: &QrCode,
+    logo: Option<&PlacedLogo>,
+    opts: &QrOptions,
+    caption: &[String],
+    template: Option<&FrameTemplate>,
+) -> Result<Vec<u8>> {
+    let opts = QrOptions {
+        dpi: Some(opts.dpi.unwrap_or(ESCPOS_DEFAULT_DPI)),
+        ..opts.clone()
+    };
+    let (opts, _) = print_options(symbol, &opts, template)?;
+
+    // Module size in dots, as `render_qr` sizes modules
+    let dimension = symbol.width() as u32 + 2 * opts.quiet_zone;
+    let module = (opts.qr_size / dimension).max(1);
+
+    let native = !opts.with_frame
+        && logo.is_none()
+        && code.len() <= MAX_NATIVE_DATA
+        && module <= MAX_MODULE_SIZE;
+
+    // Initialize, select the code page and centre
+    let mut out = vec![ESC, b'@', ESC, b't', CODE_PAGE_PC852, ESC, b'a', 1];
+    if let Some(ref header) = opts.receipt_header {
+        text_lines(&mut out, header.lines());
+    }
+
+    if native {
+        // The printer leaves no quiet zone above and below the symbol
+        let quiet_zone = (opts.quiet_zone * module).min(255) as u8;
+        out.extend([ESC, b'J', quiet_zone]);
+        qr_command(&mut out, b'A', &[50, 0]);
+        qr_command(&mut out, b'C', &[module as u8]);
+        qr_command(&mut out, b'E', &[ec_level(symbol.error_correction_level())]);
+        qr_command(&mut out, b'P', &[&[b'0'][..], code.as_bytes()].concat());
+        qr_command(&mut out, b'Q', b"0");
+        out.extend([ESC, b'J', quiet_zone]);
+    } else {
+        bit_image(&mut out, &render_symbol(symbol, logo, &opts, template)?);
+    }
+
+    text_lines(&mut out, caption.iter().map(String::as_str));
+    if let Some(ref footer) = opts.receipt_footer {
+        text_lines(&mut out, footer.lines());
+    }
+
+    // Feed past the tear bar and restore left alignment
+    out.extend([ESC, b'd', 3, ESC, b'a', 0]);
+    Ok(out)
+}
+
+/// Appends a `GS ( k` command of the QR symbol (cn 49) with function `fn_code`
+fn qr_command(out: &mut Vec<u8>, fn_code: u8, params: &[u8]) {
+    let len = (params.len() + 2) as u16;
+    out.extend([GS, b'(', b'k']);
+    out.extend(len.to_le_bytes());
+    out.extend([49, fn_code]);
+    out.extend(params);
+}
+
+fn ec_level(level: EcLevel) -> u8 {
+    match level {
+        EcLevel::L => 48,
+        EcLevel::M => 49,
+        EcLevel::Q => 50,
+        EcLevel::H => 51,
+    }
+}
+
+/// Appends lines of text, each ended by a line feed
+fn text_lines<'a>(out: &mut Vec<u8>, lines: impl Iterator<Item = &'a str>) {
+    for line in lines {
+        out.extend(encode_text(line));
+        out.push(b'\n');
+    }
+}
+
+/// Encodes text in code page PC852
+///
+/// Control characters and characters missing from the code page become `?`,
+/// so text can't smuggle in printer commands.
+fn encode_text(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' => c as u8,
+            c => PC852
+                .iter()
+                .find(|&&(known, _)| known == c)
+                .map_or(b'?', |&(_, byte)| byte),
+        })
+        .collect()
+}
+
+/// Appends an image as a `GS v 0` raster bit image
+fn bit_image(out: &mut Vec<u8>, img: &RgbaImage) {
+    let bytes_per_row = img.width().div_ceil(8) as u16;
+    out.extend([GS, b'v', b'0', 0]);
+    out.extend(bytes_per_row.to_le_bytes());
+    out.extend((img.height() as u16).to_le_bytes());
+    for y in 0..img.height() {
+        let mut row = vec![0u8; bytes_per_row as usize];
+        for x in 0..img.width() {
+            if is_dark(RgbaColor(img.get_pixel(x, y).0)) {
+                row[x as usize / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        out.extend(row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "0004A00090IFU3P5R8G9E0N4FD1K5U";
+
+    #[test]
+    fn test_native_qr() {
+        let opts = QrOptions {
+            with_frame: false,
+            receipt_header: Some("Obchod s.r.o.\nBlok č. 42".to_string()),
+            receipt_footer: Some("Ďakujeme".to_string()),
+            ..Default::default()
+        };
+        let caption = ["Suma: 10,00\u{a0}EUR".to_string()];
+        let escpos = generate_qr_escpos(CODE, &opts, &caption, None).unwrap();
+
+        // 33 modules with the quiet zone, 9 dots each in 300 dots
+        let expected = [
+            &b"\x1b@\x1bt\x12\x1ba\x01"[..],
+            b"Obchod s.r.o.\nBlok \x9f. 42\n",
+            b"\x1bJ\x24",
+            b"\x1d(k\x04\x001A2\x00",
+            b"\x1d(k\x03\x001C\x09",
+            b"\x1d(k\x03\x001E1",
+            b"\x1d(k\x21\x001P0",
+            CODE.as_bytes(),
+            b"\x1d(k\x03\x001Q0",
+            b"\x1bJ\x24",
+            b"Suma: 10,00\xffEUR\n",
+            b"\xd2akujeme\n",
+            b"\x1bd\x03\x1ba\x00",
+        ]
+        .concat();
+        assert_eq!(escpos, expected);
+    }
+
+    #[test]
+    fn test_bit_image() {
+        let opts = QrOptions {
+            qr_size: 200,
+            ..Default::default()
+        };
+        let escpos = generate_qr_escpos(CODE, &opts, &[], None).unwrap();
+        assert!(!escpos.windows(3).any(|window| window == b"\x1d(k"));
+
+        // 25 bytes by 200 rows after the set-up commands
+        let image = escpos
+            .strip_prefix(&b"\x1b@\x1bt\x12\x1ba\x01\x1dv0\x00\x19\x00\xc8\x00"[..])
+            .and_then(|rest| rest.strip_suffix(&b"\x1bd\x03\x1ba\x00"[..]))
+            .unwrap();
+        assert_eq!(image.len(), 25 * 200);
+
+        // Same pixels as the framed PNG output
+        let (qr, logo) = build_symbol(CODE, &opts).unwrap();
+        let img = render_symbol(&qr, logo.as_ref(), &opts, None).unwrap();
+        for (y, row) in image.chunks(25).enumerate() {
+            for x in 0..200u32 {
+                let dark = row[x as usize / 8] & (0x80 >> (x % 8)) != 0;
+                assert_eq!(dark, is_dark(RgbaColor(img.get_pixel(x, y as u32).0)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_text() {
+        assert_eq!(
+            encode_text("Splatnosť: 1.\u{a0}2."),
+            b"Splatnos\x9c: 1.\xff2."
+        );
+        assert_eq!(encode_text("a\x1bb€"), b"a?b?");
+    }
+}
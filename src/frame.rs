@@ -0,0 +1,412 @@
+use crate::errors::Result;
+use crate::logo::PlacedLogo;
+use crate::models::{FrameVariant, QrOptions, RgbaColor};
+use crate::qr::{build_symbol, encode_image, render_qr};
+use crate::text::{outline_text, TextOutline, TextRun};
+use ab_glyph::{point, Point};
+use ab_glyph_rasterizer::Rasterizer;
+use image::{Rgba, RgbaImage};
+use qrcode::QrCode;
+
+/// Border width relative to the frame width
+const STROKE_RATIO: f32 = 0.025;
+
+/// Outer corner radius relative to the frame width
+const RADIUS_RATIO: f32 = 0.08;
+
+/// Space between the border (or caption) and the symbol relative to the frame width
+const PADDING_RATIO: f32 = 0.03;
+
+/// Caption em size relative to the frame width
+const CAPTION_RATIO: f32 = 0.075;
+
+/// Space between the caption and the interrupted border relative to the em size
+const CAPTION_GAP_RATIO: f32 = 0.35;
+
+/// Control point distance approximating a quarter circle with a cubic curve
+const KAPPA: f32 = 0.552_284_8;
+
+/// Path segment ending at its last point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Segment {
+    Line(Point),
+    Quad(Point, Point),
+    Cubic(Point, Point, Point),
+}
+
+impl Segment {
+    pub fn end(&self) -> Point {
+        match *self {
+            Segment::Line(p) | Segment::Quad(_, p) | Segment::Cubic(_, _, p) => p,
+        }
+    }
+
+    fn map(&self, f: impl Fn(Point) -> Point) -> Self {
+        match *self {
+            Segment::Line(p) => Segment::Line(f(p)),
+            Segment::Quad(c, p) => Segment::Quad(f(c), f(p)),
+            Segment::Cubic(c0, c1, p) => Segment::Cubic(f(c0), f(c1), f(p)),
+        }
+    }
+}
+
+/// Closed outline filled with the non-zero winding rule
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Contour {
+    pub start: Point,
+    pub segments: Vec<Segment>,
+}
+
+impl Contour {
+    /// Last point of the contour
+    pub fn end(&self) -> Point {
+        self.segments.last().map_or(self.start, Segment::end)
+    }
+
+    /// Applies a point transformation to the whole contour
+    pub fn map(&self, f: impl Fn(Point) -> Point) -> Self {
+        Self {
+            start: f(self.start),
+            segments: self.segments.iter().map(|s| s.map(&f)).collect(),
+        }
+    }
+}
+
+/// Geometry of the "by square" frame for a given width
+///
+/// The frame is a rounded border whose bottom edge is interrupted by the
+/// caption ("PAY by square" or "INVOICE by square"). All proportions scale
+/// with `size`; coordinates grow to the right and downwards.
+#[derive(Debug, Clone)]
+pub(crate) struct FrameLayout {
+    pub size: f32,
+    pub qr_x: f32,
+    pub qr_y: f32,
+    pub qr_size: f32,
+    /// Border and caption glyphs, filled with the frame colour
+    pub contours: Vec<Contour>,
+}
+
+impl FrameLayout {
+    pub fn new(size: f32, variant: FrameVariant) -> Self {
+        let stroke = size * STROKE_RATIO;
+        let radius = size * RADIUS_RATIO;
+        let padding = size * PADDING_RATIO;
+
+        // Shrink long captions so the gap stays on the straight part of the border
+        let mut em = size * CAPTION_RATIO;
+        let mut caption = outline_caption(variant, em);
+        let max_width = size - 2.0 * (radius + em * CAPTION_GAP_RATIO);
+        if caption.width > max_width {
+            em *= max_width / caption.width;
+            caption = outline_caption(variant, em);
+        }
+        let gap = em * CAPTION_GAP_RATIO;
+
+        // Caption is centered on the bottom border line
+        let border_line = size - caption.height().max(stroke) / 2.0;
+        let baseline = border_line - (caption.top + caption.bottom) / 2.0;
+        let caption_x = (size - caption.width) / 2.0;
+
+        let mut contours = vec![border(
+            size,
+            border_line + stroke / 2.0,
+            stroke,
+            radius,
+            (caption_x - gap, caption_x + caption.width + gap),
+        )];
+        contours.extend(
+            caption
+                .contours
+                .iter()
+                .map(|c| c.map(|p| point(p.x + caption_x, p.y + baseline))),
+        );
+
+        // Symbol takes the inner area above the caption, centered horizontally
+        let qr_y = stroke + padding;
+        let qr_bottom = baseline + caption.top - padding;
+        let qr_size = (qr_bottom - qr_y).min(size - 2.0 * qr_y);
+
+        Self {
+            size,
+            qr_x: (size - qr_size) / 2.0,
+            qr_y,
+            qr_size,
+            contours,
+        }
+    }
+}
+
+fn outline_caption(variant: FrameVariant, em: f32) -> TextOutline {
+    outline_text(
+        &[
+            TextRun::bold(variant.label()),
+            TextRun::regular(" by square"),
+        ],
+        em,
+    )
+}
+
+/// Fits the frame caption centered into a box
+pub(crate) fn caption_contours(
+    variant: FrameVariant,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+) -> Vec<Contour> {
+    let unit = outline_caption(variant, 1.0);
+    let caption = outline_caption(variant, (width / unit.width).min(height / unit.height()));
+    let left = x + (width - caption.width) / 2.0;
+    let baseline = y + (height - caption.height()) / 2.0 - caption.top;
+    caption
+        .contours
+        .iter()
+        .map(|c| c.map(|p| point(p.x + left, p.y + baseline)))
+        .collect()
+}
+
+/// Builds the rounded border as a single contour open at the bottom between `gap.0` and `gap.1`
+fn border(width: f32, height: f32, stroke: f32, radius: f32, gap: (f32, f32)) -> Contour {
+    let (left, top, right, bottom) = (0.0, 0.0, width, height);
+    let mut contour = Contour {
+        start: point(gap.0, bottom),
+        segments: Vec::new(),
+    };
+
+    // Outer edge clockwise from the left side of the gap
+    let r = radius;
+    line_to(&mut contour, point(left + r, bottom));
+    corner(&mut contour, point(left, bottom), point(left, bottom - r));
+    line_to(&mut contour, point(left, top + r));
+    corner(&mut contour, point(left, top), point(left + r, top));
+    line_to(&mut contour, point(right - r, top));
+    corner(&mut contour, point(right, top), point(right, top + r));
+    line_to(&mut contour, point(right, bottom - r));
+    corner(&mut contour, point(right, bottom), point(right - r, bottom));
+    line_to(&mut contour, point(gap.1, bottom));
+
+    // Inner edge counter-clockwise back to the gap
+    let (left, top, right, bottom) = (stroke, stroke, width - stroke, height - stroke);
+    let r = (radius - stroke).max(0.0);
+    line_to(&mut contour, point(gap.1, bottom));
+    line_to(&mut contour, point(right - r, bottom));
+    corner(&mut contour, point(right, bottom), point(right, bottom - r));
+    line_to(&mut contour, point(right, top + r));
+    corner(&mut contour, point(right, top), point(right - r, top));
+    line_to(&mut contour, point(left + r, top));
+    corner(&mut contour, point(left, top), point(left, top + r));
+    line_to(&mut contour, point(left, bottom - r));
+    corner(&mut contour, point(left, bottom), point(left + r, bottom));
+    line_to(&mut contour, point(gap.0, bottom));
+
+    contour
+}
+
+fn line_to(contour: &mut Contour, p: Point) {
+    contour.segments.push(Segment::Line(p));
+}
+
+/// Appends a quarter circle from the current point around `corner` to `to`
+fn corner(contour: &mut Contour, corner: Point, to: Point) {
+    let from = contour.end();
+    let towards = |a: Point| {
+        point(
+            a.x + (corner.x - a.x) * KAPPA,
+            a.y + (corner.y - a.y) * KAPPA,
+        )
+    };
+    contour
+        .segments
+        .push(Segment::Cubic(towards(from), towards(to), to));
+}
+
+/// Generates a QR code image inside the drawn "by square" frame as PNG
+///
+/// See [`render_framed_qr`].
+pub fn generate_framed_qr_image(code: &str, opts: &QrOptions) -> Result<Vec<u8>> {
+    encode_image(&render_framed_qr(code, opts)?, image::ImageFormat::Png)
+}
+
+/// Renders a QR code image inside the drawn "by square" frame
+///
+/// The image is `opts.qr_size` pixels wide and tall; the symbol is rendered
+/// with whole-pixel modules into the area inside the border above the caption.
+/// When that area is too small for one pixel per module, the frame grows until
+/// it fits. The frame area outside the symbol is filled with the background colour.
+pub fn render_framed_qr(code: &str, opts: &QrOptions) -> Result<RgbaImage> {
+    let (qr, logo) = build_symbol(code, opts)?;
+    Ok(render_framed_symbol(&qr, logo.as_ref(), opts))
+}
+
+/// Renders an encoded symbol inside the drawn frame, see [`render_framed_qr`]
+pub(crate) fn render_framed_symbol(
+    qr: &QrCode,
+    logo: Option<&PlacedLogo>,
+    opts: &QrOptions,
+) -> RgbaImage {
+    let dimension = qr.width() as u32 + 2 * opts.quiet_zone;
+    let size = opts
+        .qr_size
+        .max(framed_size_for(dimension, opts.frame_variant));
+    let layout = FrameLayout::new(size as f32, opts.frame_variant);
+
+    let qr_img = render_qr(qr, layout.qr_size.floor() as u32, opts, logo);
+    let qr_size = qr_img.width();
+
+    let mut result = RgbaImage::from_pixel(size, size, Rgba(opts.background.0));
+    image::imageops::replace(
+        &mut result,
+        &qr_img,
+        ((size - qr_size) / 2) as i64,
+        layout.qr_y.round() as i64,
+    );
+    fill_contours(&mut result, &layout.contours, opts.frame_color);
+    result
+}
+
+/// Returns the smallest frame size at which the symbol area holds
+/// `symbol_pixels` pixels
+pub(crate) fn framed_size_for(symbol_pixels: u32, variant: FrameVariant) -> u32 {
+    let ratio = FrameLayout::new(1000.0, variant).qr_size / 1000.0;
+    let mut size = (symbol_pixels as f32 / ratio).floor() as u32;
+    while (FrameLayout::new(size as f32, variant).qr_size.floor() as u32) < symbol_pixels {
+        size += 1;
+    }
+    size
+}
+
+/// Fills contours with an anti-aliased colour composited over the image
+pub(crate) fn fill_contours(img: &mut RgbaImage, contours: &[Contour], color: RgbaColor) {
+    let mut rasterizer = Rasterizer::new(img.width() as usize, img.height() as usize);
+    for contour in contours {
+        let mut from = contour.start;
+        for segment in &contour.segments {
+            match *segment {
+                Segment::Line(p) => rasterizer.draw_line(from, p),
+                Segment::Quad(c, p) => rasterizer.draw_quad(from, c, p),
+                Segment::Cubic(c0, c1, p) => rasterizer.draw_cubic(from, c0, c1, p),
+            }
+            from = segment.end();
+        }
+        if from != contour.start {
+            rasterizer.draw_line(from, contour.start);
+        }
+    }
+
+    rasterizer.for_each_pixel_2d(|x, y, coverage| {
+        if coverage <= 0.0 {
+            return;
+        }
+        let pixel = img.get_pixel_mut(x, y);
+        let source = color.opacity() * coverage.min(1.0);
+        let backdrop = pixel.0[3] as f32 / 255.0 * (1.0 - source);
+        let alpha = source + backdrop;
+        if alpha <= 0.0 {
+            return;
+        }
+        for (channel, &target) in pixel.0.iter_mut().zip(&color.0).take(3) {
+            *channel =
+                ((target as f32 * source + *channel as f32 * backdrop) / alpha).round() as u8;
+        }
+        pixel.0[3] = (alpha * 255.0).round() as u8;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_scales_with_size() {
+        let small = FrameLayout::new(300.0, FrameVariant::Pay);
+        let large = FrameLayout::new(1200.0, FrameVariant::Pay);
+        assert!((large.qr_size - 4.0 * small.qr_size).abs() < 0.1);
+        assert!((large.qr_y - 4.0 * small.qr_y).abs() < 0.1);
+
+        // Symbol stays inside the border, above the caption
+        assert!(small.qr_x > 300.0 * STROKE_RATIO);
+        assert!(small.qr_size > 0.8 * 300.0);
+    }
+
+    #[test]
+    fn test_invoice_caption_fits_border() {
+        let layout = FrameLayout::new(300.0, FrameVariant::Invoice);
+        let radius = 300.0 * RADIUS_RATIO;
+        let border = &layout.contours[0];
+        // The gap edges are the first and last points of the border contour
+        assert!(border.start.x > radius);
+        assert!(border.segments[8].end().x < 300.0 - radius);
+    }
+
+    #[test]
+    fn test_dense_symbol_grows_frame() {
+        let payment: crate::PaymentRequest = serde_json::from_value(serde_json::json!({
+            "amount": 100.5,
+            "iban": "SK9611000000002918599669",
+            "variable_symbol": "1234567890",
+            "note": "Faktúra za služby poskytnuté v mesiaci marec, zmluva č. 2024/117"
+        }))
+        .unwrap();
+        let code = crate::generate_pay_by_square_code(&payment).unwrap();
+        let mut logo = Vec::new();
+        RgbaImage::from_pixel(20, 20, Rgba([20, 20, 20, 255]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut logo),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+
+        // Smallest allowed size, with a logo raising the level to H
+        let opts = QrOptions {
+            qr_size: 100,
+            logo: Some(crate::models::Logo::Image(logo)),
+            ..Default::default()
+        };
+        let img = render_framed_qr(&code, &opts).unwrap();
+        assert!(img.width() > 100);
+        assert_eq!(img.width(), img.height());
+        assert_eq!(
+            crate::read_qr(&image::DynamicImage::ImageRgba8(img)).as_deref(),
+            Some(code.as_str())
+        );
+    }
+
+    #[test]
+    fn test_generate_framed_qr_image_transparent() {
+        let opts = QrOptions {
+            qr_size: 400,
+            background: "#FFFFFF00".parse().unwrap(),
+            frame_color: "#CC000080".parse().unwrap(),
+            ..Default::default()
+        };
+        let png = generate_framed_qr_image("TEST", &opts).unwrap();
+        let img = image::load_from_memory(&png).unwrap().to_rgba8();
+
+        // Half-transparent border over a transparent background, nothing between
+        assert_eq!(img.get_pixel(2, 200).0, [0xCC, 0x00, 0x00, 0x80]);
+        assert_eq!(img.get_pixel(14, 200).0[3], 0);
+    }
+
+    #[test]
+    fn test_generate_framed_qr_image() {
+        let opts = QrOptions {
+            qr_size: 400,
+            ..Default::default()
+        };
+        let png = generate_framed_qr_image("TEST", &opts).unwrap();
+        let img = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(img.dimensions(), (400, 400));
+
+        // Border on the left edge, white padding inside, dark finder pattern in the symbol
+        let layout = FrameLayout::new(400.0, FrameVariant::Pay);
+        assert_eq!(img.get_pixel(2, 200).0, [0x00, 0x66, 0xCC, 0xFF]);
+        assert_eq!(img.get_pixel(14, 200).0, [0xFF, 0xFF, 0xFF, 0xFF]);
+        let module = layout.qr_size / 29.0;
+        let finder = (layout.qr_x + 7.5 * module) as u32;
+        assert_eq!(
+            img.get_pixel(finder, (layout.qr_y + 7.5 * module) as u32).0[0],
+            0
+        );
+    }
+}
@@ -21,11 +21,12 @@ pub fn generate_pay_by_square_code(payment: &PaymentRequest) -> Result<String> {
     let compressed = compress_lzma(&data_with_crc)?;
 
     // 5. Add header (4 bytes: type, version, document type, reserved)
-    let mut final_data = Vec::new();
-    final_data.push(0x00); // By square type
-    final_data.push(0x00); // Version
-    final_data.push(0x00); // Document type
-    final_data.push(0x00); // Reserved
+    let mut final_data = vec![
+        0x00, // By square type
+        0x00, // Version
+        0x00, // Document type
+        0x00, // Reserved
+    ];
     final_data.extend_from_slice(&compressed);
 
     // 6. Base32hex encode
@@ -34,6 +35,30 @@ pub fn generate_pay_by_square_code(payment: &PaymentRequest) -> Result<String> {
     Ok(encoded)
 }
 
+/// Decodes a PayBySquare code string back to its tab-separated data
+///
+/// Returns `None` when the code is malformed or its checksum doesn't match.
+pub fn decode_pay_by_square_data(code: &str) -> Option<String> {
+    use std::io::Read;
+
+    // 1. Base32hex decode and strip the header
+    let data = base32hex_decode(code)?;
+    let compressed = data.get(4..)?;
+
+    // 2. LZMA decompression
+    let mut data_with_crc = Vec::new();
+    xz2::read::XzDecoder::new(compressed)
+        .read_to_end(&mut data_with_crc)
+        .ok()?;
+
+    // 3. Check the CRC32 checksum
+    let (crc_bytes, data) = data_with_crc.split_at_checked(4)?;
+    if crc32fast::hash(data).to_le_bytes() != crc_bytes {
+        return None;
+    }
+    String::from_utf8(data.to_vec()).ok()
+}
+
 /// Builds the tab-separated data structure according to PayBySquare specification
 fn build_data_structure(payment: &PaymentRequest) -> Result<String> {
     let mut fields = Vec::new();
@@ -60,7 +85,7 @@ fn build_data_structure(payment: &PaymentRequest) -> Result<String> {
     fields.push(payment.currency.clone());
 
     // Field 4: Payment date (YYYYMMDD)
-    fields.push(payment.date.map(|d| format_date(d)).unwrap_or_default());
+    fields.push(payment.date.map(format_date).unwrap_or_default());
 
     // Field 5: Variable symbol
     fields.push(payment.variable_symbol.clone().unwrap_or_default());
@@ -119,7 +144,7 @@ fn build_data_structure(payment: &PaymentRequest) -> Result<String> {
     fields.push(
         payment
             .payment_due_date
-            .map(|d| format_date(d))
+            .map(format_date)
             .unwrap_or_default(),
     );
 
@@ -233,6 +258,26 @@ fn base32hex_encode(data: &[u8]) -> String {
     result
 }
 
+/// Decodes Base32hex (RFC 4648) without padding, dropping incomplete trailing bits
+fn base32hex_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut result = Vec::new();
+    let mut bits = 0u32;
+    let mut bit_count = 0u32;
+
+    for c in encoded.chars() {
+        let value = c.to_digit(32).filter(|_| !c.is_ascii_lowercase())?;
+        bits = (bits << 5) | value;
+        bit_count += 5;
+
+        if bit_count >= 8 {
+            bit_count -= 8;
+            result.push((bits >> bit_count) as u8);
+        }
+    }
+
+    Some(result)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -247,6 +292,26 @@ mod tests {
             .all(|c| "0123456789ABCDEFGHIJKLMNOPQRSTUV".contains(c)));
     }
 
+    #[test]
+    fn test_decode_round_trip() {
+        let payment: PaymentRequest = serde_json::from_value(serde_json::json!({
+            "amount": 100.5,
+            "iban": "SK9611000000002918599669",
+            "variable_symbol": "1234567890",
+            "note": "Faktúra č. 1"
+        }))
+        .unwrap();
+        let code = generate_pay_by_square_code(&payment).unwrap();
+        assert_eq!(
+            decode_pay_by_square_data(&code),
+            Some(build_data_structure(&payment).unwrap())
+        );
+
+        // Corrupted codes and other alphabets are rejected
+        assert_eq!(decode_pay_by_square_data(&code[..code.len() - 8]), None);
+        assert_eq!(decode_pay_by_square_data(&code.to_lowercase()), None);
+    }
+
     #[test]
     fn test_format_date() {
         let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
@@ -0,0 +1,699 @@
+use actix_web::{dev::Payload, http::header, FromRequest, HttpRequest};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Supported message locales
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Sk,
+    Cs,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Locale::En, Locale::Sk, Locale::Cs];
+
+    /// Returns the language tag of the locale
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Sk => "sk",
+            Locale::Cs => "cs",
+        }
+    }
+
+    /// Picks the best supported locale from an `Accept-Language` header value
+    pub fn from_accept_language(header: &str) -> Locale {
+        let mut candidates: Vec<(f32, Locale)> = quality_values(header)
+            .into_iter()
+            .filter_map(|(tag, quality)| {
+                let locale = tag.parse::<Locale>().ok()?;
+                (quality > 0.0).then_some((quality, locale))
+            })
+            .collect();
+
+        // Stable sort keeps header order for equal weights
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        candidates
+            .first()
+            .map(|(_, locale)| *locale)
+            .unwrap_or_default()
+    }
+}
+
+/// Splits an `Accept`-style header value into its values and their `q` weights
+///
+/// Values keep the header order. A missing weight is 1, an unreadable one 0.
+pub(crate) fn quality_values(header: &str) -> Vec<(&str, f32)> {
+    header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.trim().split(';');
+            let value = parts.next()?.trim();
+            let quality = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .map(|q| q.trim().parse::<f32>().unwrap_or(0.0))
+                .unwrap_or(1.0);
+            (!value.is_empty()).then_some((value, quality))
+        })
+        .collect()
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Parses a language tag, ignoring the region subtag (e.g. "sk-SK")
+    fn from_str(tag: &str) -> std::result::Result<Self, Self::Err> {
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Ok(Locale::En),
+            "sk" => Ok(Locale::Sk),
+            "cs" => Ok(Locale::Cs),
+            _ => Err(format!("Unsupported locale: {}", tag)),
+        }
+    }
+}
+
+/// Extracts the locale from the `Accept-Language` request header
+impl FromRequest for Locale {
+    type Error = Infallible;
+    type Future = Ready<std::result::Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let locale = req
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(Locale::from_accept_language)
+            .unwrap_or_default();
+        ready(Ok(locale))
+    }
+}
+
+/// All message keys; every catalog must define each of them
+pub const KEYS: &[&str] = &[
+    "error.invalid_iban",
+    "error.invalid_swift",
+    "error.validation",
+    "error.missing_bank_account",
+    "error.invalid_amount",
+    "error.field_too_long",
+    "error.option_out_of_range",
+    "error.low_contrast",
+    "error.inverted_colors",
+    "error.version_too_high",
+    "error.print_size_too_large",
+    "error.symbol_too_large",
+    "error.compression",
+    "error.qr",
+    "error.image",
+    "error.pdf",
+    "error.invalid_pdf",
+    "error.io",
+    "error.serialization",
+    "error.internal",
+    "error.internal_server",
+    "error.not_acceptable",
+    "error.unknown_profile",
+    "error.profile_not_allowed",
+    "error.unknown_frame_template",
+    "error.unknown_logo",
+    "error.unknown_slip_template",
+    "error.verification_failed",
+    "error.config",
+    "error.batch_item",
+    "validation.color_format",
+    "validation.logo_format",
+    "validation.caption_field",
+    "validation.iban_length",
+    "validation.iban_country_code",
+    "validation.iban_check_digits",
+    "validation.iban_characters",
+    "validation.swift_length",
+    "validation.swift_characters",
+    "validation.due_date_missing",
+    "validation.due_date_past",
+    "validation.variable_symbol_numeric",
+    "validation.constant_symbol_numeric",
+    "validation.specific_symbol_numeric",
+    "verify.unreadable",
+    "verify.text_differs",
+    "verify.undecodable",
+    "verify.amount",
+    "verify.currency",
+    "verify.account",
+    "verify.symbols",
+    "pdf.unreadable",
+    "pdf.encrypted",
+    "pdf.base64",
+    "pdf.no_media_box",
+    "pdf.page_box",
+    "caption.amount",
+    "caption.iban",
+    "caption.vs",
+    "caption.ks",
+    "caption.ss",
+    "caption.due_date",
+    "slip.title",
+    "slip.beneficiary",
+    "slip.swift",
+    "slip.date",
+    "slip.invoice_id",
+    "slip.note",
+    "slip.scan",
+    "alt.payment",
+    "alt.payment_to",
+    "alt.iban",
+    "alt.vs",
+    "alt.ks",
+    "alt.ss",
+    "alt.due_date",
+    "alt.note",
+];
+
+const EN: &[(&str, &str)] = &[
+    ("error.invalid_iban", "Invalid IBAN format: {0}"),
+    ("error.invalid_swift", "Invalid SWIFT/BIC format: {0}"),
+    ("error.validation", "Validation error: {0}"),
+    (
+        "error.missing_bank_account",
+        "Missing required field: either 'iban' or 'bank_accounts' must be provided",
+    ),
+    ("error.invalid_amount", "Amount must be greater than 0"),
+    (
+        "error.field_too_long",
+        "Field too long: {field} (max: {max}, got: {actual})",
+    ),
+    (
+        "error.option_out_of_range",
+        "Option out of range: {option} (min: {min}, max: {max})",
+    ),
+    (
+        "error.low_contrast",
+        "Contrast ratio {ratio} between foreground and background is below {min}",
+    ),
+    (
+        "error.inverted_colors",
+        "Foreground must be darker than background",
+    ),
+    (
+        "error.version_too_high",
+        "QR version {version} exceeds the maximum of {max}",
+    ),
+    (
+        "error.print_size_too_large",
+        "Print size needs {pixels} pixels, more than the maximum of {max}",
+    ),
+    (
+        "error.symbol_too_large",
+        "Symbol needs {pixels} pixels, more than the {max} of the frame template placement",
+    ),
+    ("error.compression", "Compression failed: {0}"),
+    ("error.qr", "QR generation failed: {0}"),
+    ("error.image", "Image processing failed: {0}"),
+    ("error.pdf", "PDF generation failed: {0}"),
+    ("error.invalid_pdf", "Invalid PDF document: {0}"),
+    ("error.io", "IO error: {0}"),
+    ("error.serialization", "Serialization error: {0}"),
+    ("error.internal", "Internal error: {0}"),
+    ("error.internal_server", "Internal server error"),
+    (
+        "error.not_acceptable",
+        "None of the accepted formats is supported: {0}",
+    ),
+    ("error.unknown_profile", "Unknown validation profile: {0}"),
+    (
+        "error.profile_not_allowed",
+        "Validation profile {0} is not allowed for this API key",
+    ),
+    (
+        "error.unknown_frame_template",
+        "Unknown frame template: {0}",
+    ),
+    ("error.unknown_logo", "Unknown logo: {0}"),
+    ("error.unknown_slip_template", "Unknown slip template: {0}"),
+    (
+        "error.verification_failed",
+        "Generated code failed verification: {0}",
+    ),
+    ("error.config", "Configuration error: {0}"),
+    ("error.batch_item", "Payment {number}: {error}"),
+    (
+        "validation.color_format",
+        "Colour must be in the form #RRGGBB or #RRGGBBAA",
+    ),
+    (
+        "validation.logo_format",
+        "Logo must be a registered name or a base64 data URI",
+    ),
+    (
+        "validation.caption_field",
+        "Caption fields must be a comma-separated list of amount, iban, vs, ks, ss, due_date",
+    ),
+    (
+        "validation.iban_length",
+        "IBAN must be between 15 and 34 characters",
+    ),
+    (
+        "validation.iban_country_code",
+        "IBAN must start with a 2-letter country code",
+    ),
+    (
+        "validation.iban_check_digits",
+        "IBAN check digits must be numeric",
+    ),
+    (
+        "validation.iban_characters",
+        "IBAN contains invalid characters",
+    ),
+    (
+        "validation.swift_length",
+        "SWIFT/BIC must be 8 or 11 characters",
+    ),
+    (
+        "validation.swift_characters",
+        "SWIFT/BIC contains invalid characters",
+    ),
+    (
+        "validation.due_date_missing",
+        "Payment due date is required",
+    ),
+    (
+        "validation.due_date_past",
+        "Payment due date must not be in the past",
+    ),
+    (
+        "validation.variable_symbol_numeric",
+        "Variable symbol must contain only digits",
+    ),
+    (
+        "validation.constant_symbol_numeric",
+        "Constant symbol must contain only digits",
+    ),
+    (
+        "validation.specific_symbol_numeric",
+        "Specific symbol must contain only digits",
+    ),
+    ("verify.unreadable", "no readable QR code in the image"),
+    (
+        "verify.text_differs",
+        "scanned text differs from the generated code",
+    ),
+    ("verify.undecodable", "code cannot be decoded"),
+    ("verify.amount", "code decodes to a different amount"),
+    ("verify.currency", "code decodes to a different currency"),
+    ("verify.account", "code decodes to a different bank account"),
+    (
+        "verify.symbols",
+        "code decodes to different payment symbols",
+    ),
+    ("pdf.unreadable", "the document cannot be read"),
+    ("pdf.encrypted", "encrypted documents are not supported"),
+    ("pdf.base64", "the document is not valid base64"),
+    ("pdf.no_media_box", "page has no media box"),
+    ("pdf.page_box", "page box must have 4 numbers"),
+    ("caption.amount", "Amount"),
+    ("caption.iban", "IBAN"),
+    ("caption.vs", "VS"),
+    ("caption.ks", "KS"),
+    ("caption.ss", "SS"),
+    ("caption.due_date", "Due date"),
+    ("slip.title", "Payment slip"),
+    ("slip.beneficiary", "Beneficiary"),
+    ("slip.swift", "SWIFT/BIC"),
+    ("slip.date", "Date"),
+    ("slip.invoice_id", "Invoice"),
+    ("slip.note", "Note"),
+    ("slip.scan", "Scan the code in your banking app"),
+    ("alt.payment", "Payment of {amount}"),
+    ("alt.payment_to", "Payment of {amount} to {beneficiary}"),
+    ("alt.iban", "IBAN {0}"),
+    ("alt.vs", "variable symbol {0}"),
+    ("alt.ks", "constant symbol {0}"),
+    ("alt.ss", "specific symbol {0}"),
+    ("alt.due_date", "due {0}"),
+    ("alt.note", "note: {0}"),
+];
+
+const SK: &[(&str, &str)] = &[
+    ("error.invalid_iban", "Neplatný formát IBAN: {0}"),
+    ("error.invalid_swift", "Neplatný formát SWIFT/BIC: {0}"),
+    ("error.validation", "Chyba validácie: {0}"),
+    (
+        "error.missing_bank_account",
+        "Chýba povinné pole: je potrebné zadať 'iban' alebo 'bank_accounts'",
+    ),
+    ("error.invalid_amount", "Suma musí byť väčšia ako 0"),
+    (
+        "error.field_too_long",
+        "Pole je príliš dlhé: {field} (max: {max}, zadané: {actual})",
+    ),
+    (
+        "error.option_out_of_range",
+        "Voľba je mimo rozsahu: {option} (min: {min}, max: {max})",
+    ),
+    (
+        "error.low_contrast",
+        "Kontrastný pomer {ratio} medzi popredím a pozadím je nižší ako {min}",
+    ),
+    (
+        "error.inverted_colors",
+        "Popredie musí byť tmavšie ako pozadie",
+    ),
+    (
+        "error.version_too_high",
+        "Verzia QR kódu {version} prekračuje maximum {max}",
+    ),
+    (
+        "error.print_size_too_large",
+        "Tlačový rozmer vyžaduje {pixels} pixelov, viac ako maximum {max}",
+    ),
+    (
+        "error.symbol_too_large",
+        "Symbol vyžaduje {pixels} pixelov, viac ako {max} miesta v šablóne rámčeka",
+    ),
+    ("error.compression", "Kompresia zlyhala: {0}"),
+    ("error.qr", "Generovanie QR kódu zlyhalo: {0}"),
+    ("error.image", "Spracovanie obrázka zlyhalo: {0}"),
+    ("error.pdf", "Generovanie PDF zlyhalo: {0}"),
+    ("error.invalid_pdf", "Neplatný PDF dokument: {0}"),
+    ("error.io", "Chyba vstupu/výstupu: {0}"),
+    ("error.serialization", "Chyba serializácie: {0}"),
+    ("error.internal", "Interná chyba: {0}"),
+    ("error.internal_server", "Interná chyba servera"),
+    (
+        "error.not_acceptable",
+        "Žiadny z akceptovaných formátov nie je podporovaný: {0}",
+    ),
+    ("error.unknown_profile", "Neznámy validačný profil: {0}"),
+    (
+        "error.profile_not_allowed",
+        "Validačný profil {0} nie je pre tento API kľúč povolený",
+    ),
+    ("error.unknown_frame_template", "Neznáma šablóna rámu: {0}"),
+    ("error.unknown_logo", "Neznáme logo: {0}"),
+    ("error.unknown_slip_template", "Neznáma šablóna príkazu: {0}"),
+    ("error.verification_failed", "Vygenerovaný kód neprešiel overením: {0}"),
+    ("error.config", "Chyba konfigurácie: {0}"),
+    ("error.batch_item", "Platba {number}: {error}"),
+    (
+        "validation.color_format",
+        "Farba musí byť v tvare #RRGGBB alebo #RRGGBBAA",
+    ),
+    (
+        "validation.logo_format",
+        "Logo musí byť registrovaný názov alebo base64 data URI",
+    ),
+    (
+        "validation.caption_field",
+        "Polia popisu musia byť zoznam hodnôt amount, iban, vs, ks, ss, due_date oddelených čiarkou",
+    ),
+    ("validation.iban_length", "IBAN musí mať 15 až 34 znakov"),
+    (
+        "validation.iban_country_code",
+        "IBAN musí začínať 2-písmenovým kódom krajiny",
+    ),
+    (
+        "validation.iban_check_digits",
+        "Kontrolné číslice IBAN musia byť číselné",
+    ),
+    ("validation.iban_characters", "IBAN obsahuje neplatné znaky"),
+    (
+        "validation.swift_length",
+        "SWIFT/BIC musí mať 8 alebo 11 znakov",
+    ),
+    (
+        "validation.swift_characters",
+        "SWIFT/BIC obsahuje neplatné znaky",
+    ),
+    ("validation.due_date_missing", "Dátum splatnosti je povinný"),
+    (
+        "validation.due_date_past",
+        "Dátum splatnosti nesmie byť v minulosti",
+    ),
+    (
+        "validation.variable_symbol_numeric",
+        "Variabilný symbol môže obsahovať iba číslice",
+    ),
+    (
+        "validation.constant_symbol_numeric",
+        "Konštantný symbol môže obsahovať iba číslice",
+    ),
+    (
+        "validation.specific_symbol_numeric",
+        "Špecifický symbol môže obsahovať iba číslice",
+    ),
+    ("verify.unreadable", "obrázok neobsahuje čitateľný QR kód"),
+    (
+        "verify.text_differs",
+        "naskenovaný text sa líši od vygenerovaného kódu",
+    ),
+    ("verify.undecodable", "kód sa nedá dekódovať"),
+    ("verify.amount", "kód obsahuje inú sumu"),
+    ("verify.currency", "kód obsahuje inú menu"),
+    ("verify.account", "kód obsahuje iný bankový účet"),
+    ("verify.symbols", "kód obsahuje iné platobné symboly"),
+    ("pdf.unreadable", "dokument sa nedá prečítať"),
+    ("pdf.encrypted", "šifrované dokumenty nie sú podporované"),
+    ("pdf.base64", "dokument nie je platný base64"),
+    ("pdf.no_media_box", "strana nemá rozmer (MediaBox)"),
+    ("pdf.page_box", "rozmer strany musí mať 4 čísla"),
+    ("caption.amount", "Suma"),
+    ("caption.iban", "IBAN"),
+    ("caption.vs", "VS"),
+    ("caption.ks", "KS"),
+    ("caption.ss", "ŠS"),
+    ("caption.due_date", "Splatnosť"),
+    ("slip.title", "Platobný príkaz"),
+    ("slip.beneficiary", "Príjemca"),
+    ("slip.swift", "SWIFT/BIC"),
+    ("slip.date", "Dátum"),
+    ("slip.invoice_id", "Faktúra"),
+    ("slip.note", "Poznámka"),
+    ("slip.scan", "Naskenujte kód v aplikácii svojej banky"),
+    ("alt.payment", "Platba {amount}"),
+    ("alt.payment_to", "Platba {amount} príjemcovi {beneficiary}"),
+    ("alt.iban", "IBAN {0}"),
+    ("alt.vs", "variabilný symbol {0}"),
+    ("alt.ks", "konštantný symbol {0}"),
+    ("alt.ss", "špecifický symbol {0}"),
+    ("alt.due_date", "splatnosť {0}"),
+    ("alt.note", "poznámka: {0}"),
+];
+
+const CS: &[(&str, &str)] = &[
+    ("error.invalid_iban", "Neplatný formát IBAN: {0}"),
+    ("error.invalid_swift", "Neplatný formát SWIFT/BIC: {0}"),
+    ("error.validation", "Chyba validace: {0}"),
+    (
+        "error.missing_bank_account",
+        "Chybí povinné pole: je nutné zadat 'iban' nebo 'bank_accounts'",
+    ),
+    ("error.invalid_amount", "Částka musí být větší než 0"),
+    (
+        "error.field_too_long",
+        "Pole je příliš dlouhé: {field} (max: {max}, zadáno: {actual})",
+    ),
+    (
+        "error.option_out_of_range",
+        "Volba je mimo rozsah: {option} (min: {min}, max: {max})",
+    ),
+    (
+        "error.low_contrast",
+        "Kontrastní poměr {ratio} mezi popředím a pozadím je nižší než {min}",
+    ),
+    (
+        "error.inverted_colors",
+        "Popředí musí být tmavší než pozadí",
+    ),
+    (
+        "error.version_too_high",
+        "Verze QR kódu {version} překračuje maximum {max}",
+    ),
+    (
+        "error.print_size_too_large",
+        "Tiskový rozměr vyžaduje {pixels} pixelů, více než maximum {max}",
+    ),
+    (
+        "error.symbol_too_large",
+        "Symbol vyžaduje {pixels} pixelů, více než {max} místa v šabloně rámečku",
+    ),
+    ("error.compression", "Komprese selhala: {0}"),
+    ("error.qr", "Generování QR kódu selhalo: {0}"),
+    ("error.image", "Zpracování obrázku selhalo: {0}"),
+    ("error.pdf", "Generování PDF selhalo: {0}"),
+    ("error.invalid_pdf", "Neplatný PDF dokument: {0}"),
+    ("error.io", "Chyba vstupu/výstupu: {0}"),
+    ("error.serialization", "Chyba serializace: {0}"),
+    ("error.internal", "Interní chyba: {0}"),
+    ("error.internal_server", "Interní chyba serveru"),
+    (
+        "error.not_acceptable",
+        "Žádný z akceptovaných formátů není podporován: {0}",
+    ),
+    ("error.unknown_profile", "Neznámý validační profil: {0}"),
+    (
+        "error.profile_not_allowed",
+        "Validační profil {0} není pro tento API klíč povolen",
+    ),
+    ("error.unknown_frame_template", "Neznámá šablona rámu: {0}"),
+    ("error.unknown_logo", "Neznámé logo: {0}"),
+    (
+        "error.unknown_slip_template",
+        "Neznámá šablona příkazu: {0}",
+    ),
+    (
+        "error.verification_failed",
+        "Vygenerovaný kód neprošel ověřením: {0}",
+    ),
+    ("error.config", "Chyba konfigurace: {0}"),
+    ("error.batch_item", "Platba {number}: {error}"),
+    (
+        "validation.color_format",
+        "Barva musí být ve tvaru #RRGGBB nebo #RRGGBBAA",
+    ),
+    (
+        "validation.logo_format",
+        "Logo musí být registrovaný název nebo base64 data URI",
+    ),
+    (
+        "validation.caption_field",
+        "Pole popisu musí být seznam hodnot amount, iban, vs, ks, ss, due_date oddělených čárkou",
+    ),
+    ("validation.iban_length", "IBAN musí mít 15 až 34 znaků"),
+    (
+        "validation.iban_country_code",
+        "IBAN musí začínat dvoupísmenným kódem země",
+    ),
+    (
+        "validation.iban_check_digits",
+        "Kontrolní číslice IBAN musí být číselné",
+    ),
+    ("validation.iban_characters", "IBAN obsahuje neplatné znaky"),
+    (
+        "validation.swift_length",
+        "SWIFT/BIC musí mít 8 nebo 11 znaků",
+    ),
+    (
+        "validation.swift_characters",
+        "SWIFT/BIC obsahuje neplatné znaky",
+    ),
+    ("validation.due_date_missing", "Datum splatnosti je povinné"),
+    (
+        "validation.due_date_past",
+        "Datum splatnosti nesmí být v minulosti",
+    ),
+    (
+        "validation.variable_symbol_numeric",
+        "Variabilní symbol může obsahovat pouze číslice",
+    ),
+    (
+        "validation.constant_symbol_numeric",
+        "Konstantní symbol může obsahovat pouze číslice",
+    ),
+    (
+        "validation.specific_symbol_numeric",
+        "Specifický symbol může obsahovat pouze číslice",
+    ),
+    ("verify.unreadable", "obrázek neobsahuje čitelný QR kód"),
+    (
+        "verify.text_differs",
+        "naskenovaný text se liší od vygenerovaného kódu",
+    ),
+    ("verify.undecodable", "kód nelze dekódovat"),
+    ("verify.amount", "kód obsahuje jinou částku"),
+    ("verify.currency", "kód obsahuje jinou měnu"),
+    ("verify.account", "kód obsahuje jiný bankovní účet"),
+    ("verify.symbols", "kód obsahuje jiné platební symboly"),
+    ("pdf.unreadable", "dokument nelze přečíst"),
+    ("pdf.encrypted", "šifrované dokumenty nejsou podporovány"),
+    ("pdf.base64", "dokument není platný base64"),
+    ("pdf.no_media_box", "stránka nemá rozměr (MediaBox)"),
+    ("pdf.page_box", "rozměr stránky musí mít 4 čísla"),
+    ("caption.amount", "Částka"),
+    ("caption.iban", "IBAN"),
+    ("caption.vs", "VS"),
+    ("caption.ks", "KS"),
+    ("caption.ss", "SS"),
+    ("caption.due_date", "Splatnost"),
+    ("slip.title", "Platební příkaz"),
+    ("slip.beneficiary", "Příjemce"),
+    ("slip.swift", "SWIFT/BIC"),
+    ("slip.date", "Datum"),
+    ("slip.invoice_id", "Faktura"),
+    ("slip.note", "Poznámka"),
+    ("slip.scan", "Naskenujte kód v aplikaci své banky"),
+    ("alt.payment", "Platba {amount}"),
+    ("alt.payment_to", "Platba {amount} příjemci {beneficiary}"),
+    ("alt.iban", "IBAN {0}"),
+    ("alt.vs", "variabilní symbol {0}"),
+    ("alt.ks", "konstantní symbol {0}"),
+    ("alt.ss", "specifický symbol {0}"),
+    ("alt.due_date", "splatnost {0}"),
+    ("alt.note", "poznámka: {0}"),
+];
+
+fn catalog(locale: Locale) -> &'static [(&'static str, &'static str)] {
+    match locale {
+        Locale::En => EN,
+        Locale::Sk => SK,
+        Locale::Cs => CS,
+    }
+}
+
+fn lookup(locale: Locale, key: &str) -> Option<&'static str> {
+    catalog(locale)
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, message)| *message)
+}
+
+/// Returns the message for `key`, falling back to English and then to the key itself
+pub fn translate(locale: Locale, key: &'static str) -> &'static str {
+    lookup(locale, key)
+        .or_else(|| lookup(Locale::En, key))
+        .unwrap_or(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogs_are_complete() {
+        for locale in Locale::ALL {
+            for key in KEYS {
+                assert!(
+                    lookup(locale, key).is_some(),
+                    "catalog '{}' is missing key '{}'",
+                    locale.code(),
+                    key
+                );
+            }
+            for (key, _) in catalog(locale) {
+                assert!(
+                    KEYS.contains(key),
+                    "catalog '{}' has unknown key '{}'",
+                    locale.code(),
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_accept_language() {
+        assert_eq!(Locale::from_accept_language("sk-SK,sk;q=0.9"), Locale::Sk);
+        assert_eq!(
+            Locale::from_accept_language("de;q=1.0, cs;q=0.5, en;q=0.8"),
+            Locale::En
+        );
+        assert_eq!(Locale::from_accept_language("de, fr"), Locale::En);
+        assert_eq!(Locale::from_accept_language("en;q=0, cs"), Locale::Cs);
+    }
+}
@@ -0,0 +1,385 @@
+use crate::caption::caption_lines;
+use crate::errors::{PayBySquareError, Result};
+use crate::generator::generate_pay_by_square_code;
+use crate::models::{LabelGrid, LabelSheetOptions, PaymentRequest, QrOptions, RgbaColor};
+use crate::pdf::{draw_code, embed_font, pdf_color, CodeLayout};
+use crate::profiles::ValidationProfile;
+use crate::qr::build_symbol;
+use crate::text::{outline_text, TextRun};
+use crate::validation::{
+    validate_label_sheet_options, validate_payment_request_with_profile, validate_qr_options,
+    ValidationWarning,
+};
+use printpdf::{IndirectFontRef, Line, Mm, PdfDocument, PdfLayerReference, Point};
+
+/// A4 page size in millimetres
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+
+/// Largest number of payments on one sheet
+pub const MAX_LABELS: usize = 1000;
+
+/// Largest caption font size in points
+const CAPTION_FONT_SIZE: f32 = 9.0;
+
+/// Caption line height relative to the font size in points, in millimetres
+const CAPTION_LINE_HEIGHT: f32 = 0.5;
+
+/// Length of a cut mark arm in millimetres
+const CUT_MARK_LENGTH: f32 = 2.0;
+
+/// Placement of labels on the page in millimetres
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct LabelGeometry {
+    pub columns: usize,
+    pub rows: usize,
+    pub width: f32,
+    pub height: f32,
+    pub left: f32,
+    pub top: f32,
+    pub column_gap: f32,
+    pub row_gap: f32,
+}
+
+impl LabelGeometry {
+    /// Returns the geometry of a preset, resized to fill the page inside `margin`
+    pub fn new(grid: LabelGrid, margin: Option<f32>) -> Self {
+        let preset = match grid {
+            LabelGrid::Grid3x8 => Self::grid(3, 8, 70.0, 37.0, 0.0, 0.5, 0.0, 0.0),
+            LabelGrid::Grid3x7 => Self::grid(3, 7, 70.0, 42.3, 0.0, 0.45, 0.0, 0.0),
+            LabelGrid::Grid2x7 => Self::grid(2, 7, 99.1, 38.1, 4.65, 15.15, 2.5, 0.0),
+            LabelGrid::Grid2x4 => Self::grid(2, 4, 105.0, 74.25, 0.0, 0.0, 0.0, 0.0),
+        };
+        let Some(margin) = margin else {
+            return preset;
+        };
+
+        let gaps = |count: usize, gap: f32| (count - 1) as f32 * gap;
+        Self {
+            width: (PAGE_WIDTH - 2.0 * margin - gaps(preset.columns, preset.column_gap))
+                / preset.columns as f32,
+            height: (PAGE_HEIGHT - 2.0 * margin - gaps(preset.rows, preset.row_gap))
+                / preset.rows as f32,
+            left: margin,
+            top: margin,
+            ..preset
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn grid(
+        columns: usize,
+        rows: usize,
+        width: f32,
+        height: f32,
+        left: f32,
+        top: f32,
+        column_gap: f32,
+        row_gap: f32,
+    ) -> Self {
+        Self {
+            columns,
+            rows,
+            width,
+            height,
+            left,
+            top,
+            column_gap,
+            row_gap,
+        }
+    }
+
+    pub fn per_page(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    /// Returns the top-left corner of the label at `index` on its page, in
+    /// PDF coordinates with the origin at the bottom-left corner
+    pub fn origin(&self, index: usize) -> (f32, f32) {
+        let (row, column) = (index / self.columns, index % self.columns);
+        (
+            self.left + column as f32 * (self.width + self.column_gap),
+            PAGE_HEIGHT - self.top - row as f32 * (self.height + self.row_gap),
+        )
+    }
+}
+
+/// Renders payments onto A4 label sheets, validating with the standard profile
+///
+/// See [`generate_label_sheet_with_profile`].
+pub fn generate_label_sheet(
+    payments: &[PaymentRequest],
+    opts: &QrOptions,
+    sheet: &LabelSheetOptions,
+) -> Result<Vec<u8>> {
+    generate_label_sheet_with_profile(payments, opts, sheet, &ValidationProfile::standard())
+        .map(|(pdf, _)| pdf)
+}
+
+/// Renders payments onto A4 label sheets as a PDF, one label per payment
+///
+/// Labels fill the grid row by row and continue on new pages. Each label holds
+/// the code with vector modules and its caption (`opts.caption_fields` in
+/// `opts.caption_locale`): beside the code on landscape labels, under it
+/// otherwise. The code takes the space the caption leaves; long captions are set
+/// in a smaller font. Errors of a payment are reported with its number.
+pub fn generate_label_sheet_with_profile(
+    payments: &[PaymentRequest],
+    opts: &QrOptions,
+    sheet: &LabelSheetOptions,
+    profile: &ValidationProfile,
+) -> Result<(Vec<u8>, Vec<ValidationWarning>)> {
+    if payments.is_empty() || payments.len() > MAX_LABELS {
+        return Err(PayBySquareError::OptionOutOfRange {
+            option: "payments".to_string(),
+            min: 1.0,
+            max: MAX_LABELS as f32,
+        });
+    }
+    validate_qr_options(opts)?;
+    validate_label_sheet_options(sheet)?;
+
+    let geometry = LabelGeometry::new(sheet.grid, sheet.margin_mm);
+    let locale = opts.caption_locale.unwrap_or_default();
+
+    let (doc, page, layer) = PdfDocument::new(
+        "PAY by square labels",
+        Mm(PAGE_WIDTH),
+        Mm(PAGE_HEIGHT),
+        "Labels",
+    );
+    let mut layer = doc.get_page(page).get_layer(layer);
+    let font = embed_font(&doc)?;
+
+    let mut warnings = Vec::new();
+    for (i, payment) in payments.iter().enumerate() {
+        let numbered = |error| PayBySquareError::BatchItem {
+            number: i + 1,
+            error: Box::new(error),
+        };
+        warnings.extend(validate_payment_request_with_profile(payment, profile).map_err(numbered)?);
+        let code = generate_pay_by_square_code(payment).map_err(numbered)?;
+        let caption = caption_lines(payment, &opts.caption_fields, locale);
+
+        let index = i % geometry.per_page();
+        if i > 0 && index == 0 {
+            let (page, first) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Labels");
+            layer = doc.get_page(page).get_layer(first);
+        }
+        let (left, top) = geometry.origin(index);
+        draw_label(
+            &layer,
+            &font,
+            &code,
+            &caption,
+            opts,
+            (left, top, geometry.width, geometry.height),
+            sheet.padding_mm,
+        )
+        .map_err(numbered)?;
+        if sheet.cut_marks {
+            draw_cut_marks(&layer, left, top, geometry.width, geometry.height);
+        }
+    }
+
+    let pdf = doc
+        .save_to_bytes()
+        .map_err(|e| PayBySquareError::PdfError(e.to_string()))?;
+    Ok((pdf, warnings))
+}
+
+/// Draws a code and its caption into the label with top-left corner `(left, top)`
+fn draw_label(
+    layer: &PdfLayerReference,
+    font: &IndirectFontRef,
+    code: &str,
+    caption: &[String],
+    opts: &QrOptions,
+    (left, top, width, height): (f32, f32, f32, f32),
+    padding: f32,
+) -> Result<()> {
+    let (qr, logo) = build_symbol(code, opts)?;
+    let inner_width = width - 2.0 * padding;
+    let inner_height = height - 2.0 * padding;
+    let gap = padding.max(1.0);
+
+    // Widest caption line at the largest font size
+    let em = CAPTION_FONT_SIZE * 25.4 / 72.0;
+    let widest = caption
+        .iter()
+        .map(|line| outline_text(&[TextRun::regular(line)], em).width)
+        .fold(0.0, f32::max);
+    let lines = caption.len() as f32;
+    let beside = width > height && !caption.is_empty();
+
+    // Side of the code and the area left to the caption
+    let (side, text_width, text_height) = if caption.is_empty() {
+        (inner_width.min(inner_height), 0.0, 0.0)
+    } else if beside {
+        let side = inner_height.min(inner_width / 2.0);
+        (side, inner_width - side - gap, inner_height)
+    } else {
+        let text_height = inner_height.min(lines * CAPTION_LINE_HEIGHT * CAPTION_FONT_SIZE);
+        let side = inner_width.min(inner_height - text_height - gap);
+        (side, inner_width, text_height)
+    };
+    if side <= 0.0 {
+        return Err(PayBySquareError::PdfError(format!(
+            "label ({:.1} x {:.1} mm) is too small for the code",
+            width, height
+        )));
+    }
+
+    let font_size = if caption.is_empty() {
+        CAPTION_FONT_SIZE
+    } else {
+        CAPTION_FONT_SIZE
+            .min(CAPTION_FONT_SIZE * text_width / widest.max(f32::EPSILON))
+            .min(text_height / (lines * CAPTION_LINE_HEIGHT))
+    };
+    let line_height = font_size * CAPTION_LINE_HEIGHT;
+
+    // Code and caption are centered in the label as a block
+    let layout = CodeLayout::fit(&qr, opts, side);
+    let text_block = widest * font_size / CAPTION_FONT_SIZE;
+    let (code_left, code_top, text_left, text_top) = if beside {
+        let block = side + gap + text_block;
+        let code_left = left + padding + (inner_width - block).max(0.0) / 2.0;
+        let code_top = top - padding - (inner_height - side) / 2.0;
+        let text_top = top - padding - (inner_height - lines * line_height) / 2.0;
+        (code_left, code_top, code_left + side + gap, text_top)
+    } else {
+        let block = side + gap + lines * line_height;
+        let code_top = top - padding - (inner_height - block).max(0.0) / 2.0;
+        let code_left = left + padding + (inner_width - side) / 2.0;
+        let text_left = left + padding + (inner_width - text_block).max(0.0) / 2.0;
+        (code_left, code_top, text_left, code_top - side - gap)
+    };
+
+    draw_code(
+        layer,
+        &qr,
+        logo.as_ref(),
+        opts,
+        &layout,
+        code_left,
+        code_top,
+    );
+
+    layer.set_fill_color(pdf_color(opts.foreground));
+    for (i, line) in caption.iter().enumerate() {
+        // Baseline sits a line height below the top of the line, less the descent
+        let baseline = text_top - (i as f32 + 0.8) * line_height;
+        layer.use_text(line, font_size, Mm(text_left), Mm(baseline), font);
+    }
+    Ok(())
+}
+
+/// Draws crosses at the corners of a label
+fn draw_cut_marks(layer: &PdfLayerReference, left: f32, top: f32, width: f32, height: f32) {
+    layer.set_outline_color(pdf_color(RgbaColor::black()));
+    layer.set_outline_thickness(0.25);
+    for (x, y) in [
+        (left, top),
+        (left + width, top),
+        (left, top - height),
+        (left + width, top - height),
+    ] {
+        for (dx, dy) in [(CUT_MARK_LENGTH, 0.0), (0.0, CUT_MARK_LENGTH)] {
+            layer.add_line(Line {
+                points: vec![
+                    (Point::new(Mm(x - dx), Mm(y - dy)), false),
+                    (Point::new(Mm(x + dx), Mm(y + dy)), false),
+                ],
+                is_closed: false,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payments(count: usize) -> Vec<PaymentRequest> {
+        (0..count)
+            .map(|i| {
+                serde_json::from_value(serde_json::json!({
+                    "amount": 10.0 + i as f64,
+                    "iban": "SK9611000000002918599669",
+                    "variable_symbol": format!("{}", 1000 + i)
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_label_geometry() {
+        for grid in [
+            LabelGrid::Grid3x8,
+            LabelGrid::Grid3x7,
+            LabelGrid::Grid2x7,
+            LabelGrid::Grid2x4,
+        ] {
+            for margin in [None, Some(10.0)] {
+                let geometry = LabelGeometry::new(grid, margin);
+                // Last label ends inside the page, symmetric to the first
+                let (left, top) = geometry.origin(geometry.per_page() - 1);
+                let right = PAGE_WIDTH - left - geometry.width;
+                let bottom = top - geometry.height;
+                assert!((right - geometry.left).abs() < 0.01, "{:?}", grid);
+                assert!((bottom - geometry.top).abs() < 0.01, "{:?}", grid);
+            }
+        }
+
+        let geometry = LabelGeometry::new(LabelGrid::Grid3x8, None);
+        assert_eq!(geometry.per_page(), 24);
+        assert_eq!(geometry.origin(4), (70.0, 297.0 - 0.5 - 37.0));
+    }
+
+    #[test]
+    fn test_generate_label_sheet() {
+        let sheet = LabelSheetOptions {
+            cut_marks: true,
+            ..Default::default()
+        };
+        let pdf = generate_label_sheet(&payments(30), &QrOptions::default(), &sheet).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        let text = String::from_utf8_lossy(&pdf);
+        assert_eq!(
+            text.matches("/MediaBox[0 0 595.27563 841.88983]").count(),
+            2
+        );
+        assert!(text.contains("/FontFile2"));
+    }
+
+    #[test]
+    fn test_label_sheet_errors() {
+        let opts = QrOptions::default();
+        let sheet = LabelSheetOptions::default();
+        assert!(matches!(
+            generate_label_sheet(&[], &opts, &sheet),
+            Err(PayBySquareError::OptionOutOfRange { .. })
+        ));
+
+        let mut batch = payments(3);
+        batch[1].amount = -1.0;
+        match generate_label_sheet(&batch, &opts, &sheet) {
+            Err(PayBySquareError::BatchItem { number, error }) => {
+                assert_eq!(number, 2);
+                assert!(matches!(*error, PayBySquareError::InvalidAmount));
+            }
+            other => panic!("unexpected result: {:?}", other.map(|pdf| pdf.len())),
+        }
+
+        let sheet = LabelSheetOptions {
+            padding_mm: 30.0,
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_label_sheet(&payments(1), &opts, &sheet),
+            Err(PayBySquareError::OptionOutOfRange { .. })
+        ));
+    }
+}
@@ -1,46 +1,304 @@
+pub mod alt_text;
+pub mod caption;
 pub mod errors;
+pub mod escpos;
+pub mod frame;
 pub mod generator;
+pub mod i18n;
+pub mod labels;
+pub mod logo;
 pub mod models;
+pub mod pdf;
+pub mod profiles;
 pub mod qr;
+pub mod reader;
+pub mod sanitize;
+pub mod slip;
+pub mod stamp;
+pub mod svg;
+pub mod templates;
+pub mod terminal;
+pub mod text;
 pub mod validation;
+pub mod verify;
+pub mod zpl;
 
+pub use alt_text::alt_text;
+pub use caption::{caption_lines, CaptionField, CaptionFields};
 pub use errors::{PayBySquareError, Result};
-pub use generator::generate_pay_by_square_code;
+pub use escpos::generate_qr_escpos;
+pub use frame::{generate_framed_qr_image, render_framed_qr};
+pub use generator::{decode_pay_by_square_data, generate_pay_by_square_code};
+pub use i18n::Locale;
+pub use labels::{generate_label_sheet, generate_label_sheet_with_profile};
+pub use logo::LogoRegistry;
 pub use models::{
-    BankAccount, CodeResponse, DirectDebit, DirectDebitScheme, DirectDebitType, PaymentOption,
-    PaymentRequest, Periodicity, QrOptions, StandingOrder,
+    BankAccount, CodeResponse, DirectDebit, DirectDebitScheme, DirectDebitType, FieldChange,
+    FrameVariant, GenerateQrRequest, GenerateQuery, ImageSize, LabelGrid, LabelSheetOptions,
+    LabelSheetRequest, Logo, OutputFormat, PageSize, PaymentOption, PaymentRequest, Periodicity,
+    QrOptions, QrOptionsQuery, QrResponse, RgbaColor, SlipQuery, StampPlacement, StampRequest,
+    StandingOrder, Warning,
 };
-pub use qr::{add_frame, generate_default_frame, generate_qr_image};
-pub use validation::validate_payment_request;
+pub use pdf::generate_qr_pdf;
+pub use profiles::{ProfileRegistry, RuleLevel, ValidationProfile};
+pub use qr::{
+    add_caption, add_frame, convert_image, draw_caption, encode_image, encode_image_with_dpi,
+    generate_default_frame, generate_qr_image, generate_qr_image_with_options, render_qr_image,
+};
+pub use reader::read_qr;
+pub use sanitize::sanitize_payment_request;
+pub use slip::{
+    generate_payment_slip, generate_payment_slip_with_profile, SlipTemplate, SlipTemplateRegistry,
+};
+pub use stamp::{stamp_pdf, stamp_pdf_with_profile};
+pub use svg::generate_qr_svg;
+pub use templates::{FrameTemplate, FrameTemplateRegistry, TemplateMetadata};
+pub use terminal::{generate_qr_text, TextOptions, TextStyle};
+pub use validation::{
+    validate_payment_request, validate_payment_request_with_profile, validate_qr_options, Rule,
+    ValidationWarning,
+};
+pub use verify::{verify_code, verify_image, verify_rendered};
+pub use zpl::generate_qr_zpl;
 
 /// Generates a complete PayBySquare QR code image with optional frame
+///
+/// Returns the encoded image in `opts.format`. Raster output uses `frame_data`
+/// as the frame image (symbol centered at 85%) when given; otherwise the
+/// "PAY by square" (or "INVOICE by square") frame is drawn. With `opts.verify`
+/// the result is scanned and must decode back to the payment.
+///
+/// `frame_data` is decoded on every call; load a [`FrameTemplate`] once and use
+/// [`generate_pay_by_square_qr_with_profile`] when generating repeatedly.
 pub fn generate_pay_by_square_qr(
     payment: &PaymentRequest,
     opts: QrOptions,
     frame_data: Option<&[u8]>,
 ) -> Result<Vec<u8>> {
-    // Validate payment request
-    validate_payment_request(payment)?;
+    let template = frame_data
+        .map(|data| FrameTemplate::from_image("frame", data))
+        .transpose()?;
+    generate_pay_by_square_qr_with_profile(
+        payment,
+        opts,
+        template.as_ref(),
+        &ValidationProfile::standard(),
+    )
+    .map(|(image_data, _)| image_data)
+}
+
+/// Generates a complete PayBySquare QR code image, validating with the given profile
+///
+/// Raster output is placed into `template` when given; otherwise the frame is drawn.
+/// With `opts.verify` raster output is scanned as encoded; SVG, PDF, ZPL and
+/// ESC/POS are checked on a raster rendering of the same symbol.
+pub fn generate_pay_by_square_qr_with_profile(
+    payment: &PaymentRequest,
+    opts: QrOptions,
+    template: Option<&FrameTemplate>,
+    profile: &ValidationProfile,
+) -> Result<(Vec<u8>, Vec<ValidationWarning>)> {
+    generate_pay_by_square_qr_detailed(payment, opts, template, profile)
+        .map(|generated| (generated.image_data, generated.warnings))
+}
+
+/// Encoded image with the code and the parameters of its symbol
+#[derive(Debug)]
+pub struct GeneratedQr {
+    pub code: String,
+    pub image_data: Vec<u8>,
+    /// QR version of the symbol (1-40)
+    pub version: i16,
+    pub ec_level: qrcode::EcLevel,
+    pub warnings: Vec<ValidationWarning>,
+}
+
+/// Generates a complete PayBySquare QR code image like
+/// [`generate_pay_by_square_qr_with_profile`], keeping the code and symbol parameters
+pub fn generate_pay_by_square_qr_detailed(
+    payment: &PaymentRequest,
+    opts: QrOptions,
+    template: Option<&FrameTemplate>,
+    profile: &ValidationProfile,
+) -> Result<GeneratedQr> {
+    // Validate payment request and options
+    let warnings = validate_payment_request_with_profile(payment, profile)?;
+    validate_qr_options(&opts)?;
 
     // Generate PayBySquare code
     let code = generate_pay_by_square_code(payment)?;
+    let (symbol, logo) = qr::build_symbol(&code, &opts)?;
+    let logo = logo.as_ref();
 
-    // Generate QR image
-    let qr_data = generate_qr_image(&code, opts.qr_size)?;
-
-    // Add frame if requested
-    if opts.with_frame {
-        add_frame(qr_data, frame_data)
+    let caption = if opts.caption {
+        caption_lines(
+            payment,
+            &opts.caption_fields,
+            opts.caption_locale.unwrap_or_default(),
+        )
     } else {
-        Ok(qr_data)
+        Vec::new()
+    };
+
+    // Render QR image
+    let image_data = match opts.format {
+        OutputFormat::Png | OutputFormat::Jpeg | OutputFormat::Webp => {
+            // Frame template if provided, otherwise the drawn "by square" frame;
+            // stages work on the image buffer, which is encoded once
+            let (opts, dpi) = qr::print_options(&symbol, &opts, template)?;
+            let img = draw_caption(
+                qr::render_symbol(&symbol, logo, &opts, template)?,
+                &caption,
+                &opts,
+            );
+
+            let format = match opts.format {
+                OutputFormat::Jpeg => image::ImageFormat::Jpeg,
+                OutputFormat::Webp => image::ImageFormat::WebP,
+                _ => image::ImageFormat::Png,
+            };
+            encode_image_with_dpi(&img, format, dpi)?
+        }
+        OutputFormat::Svg => svg::render_svg(&symbol, logo, &opts).into_bytes(),
+        OutputFormat::Pdf => pdf::render_pdf(&symbol, logo, &opts, &caption)?,
+        OutputFormat::Zpl => {
+            zpl::render_zpl(&code, &symbol, logo, &opts, &caption, template)?.into_bytes()
+        }
+        OutputFormat::Escpos => {
+            escpos::render_escpos(&code, &symbol, logo, &opts, &caption, template)?
+        }
+    };
+
+    if opts.verify {
+        match opts.format {
+            OutputFormat::Png | OutputFormat::Jpeg | OutputFormat::Webp => {
+                verify_image(&image_data, &code, payment)?
+            }
+            OutputFormat::Svg | OutputFormat::Pdf | OutputFormat::Zpl | OutputFormat::Escpos => {
+                verify_rendered(
+                    &image::DynamicImage::ImageRgba8(qr::render_qr(
+                        &symbol,
+                        opts.qr_size,
+                        &opts,
+                        logo,
+                    )),
+                    &code,
+                    payment,
+                )?
+            }
+        }
     }
+
+    let version = match symbol.version() {
+        qrcode::Version::Normal(version) | qrcode::Version::Micro(version) => version,
+    };
+    Ok(GeneratedQr {
+        code,
+        image_data,
+        version,
+        ec_level: symbol.error_correction_level(),
+        warnings,
+    })
 }
 
 /// Generates only the PayBySquare code string
 pub fn generate_code_only(payment: &PaymentRequest) -> Result<String> {
+    generate_code_with_profile(payment, &ValidationProfile::standard()).map(|(code, _)| code)
+}
+
+/// Generates only the PayBySquare code string, validating with the given profile
+pub fn generate_code_with_profile(
+    payment: &PaymentRequest,
+    profile: &ValidationProfile,
+) -> Result<(String, Vec<ValidationWarning>)> {
     // Validate payment request
-    validate_payment_request(payment)?;
+    let warnings = validate_payment_request_with_profile(payment, profile)?;
 
     // Generate PayBySquare code
-    generate_pay_by_square_code(payment)
+    let code = generate_pay_by_square_code(payment)?;
+
+    Ok((code, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use qrcode::EcLevel;
+
+    fn payment() -> PaymentRequest {
+        serde_json::from_value(serde_json::json!({
+            "amount": 100.50,
+            "iban": "SK9611000000002918599669",
+            "beneficiary_name": "John Doe",
+            "variable_symbol": "1234567890"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_detailed_image_data_uri() {
+        let opts = QrOptions::default();
+        let generated = generate_pay_by_square_qr_detailed(
+            &payment(),
+            opts.clone(),
+            None,
+            &ValidationProfile::standard(),
+        )
+        .unwrap();
+        assert_eq!(
+            generated.image_data,
+            generate_pay_by_square_qr(&payment(), opts, None).unwrap()
+        );
+
+        let uri = OutputFormat::Png.data_uri(&generated.image_data);
+        let data = uri.strip_prefix("data:image/png;base64,").unwrap();
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .unwrap();
+        assert_eq!(data, generated.image_data);
+        assert_eq!(image::guess_format(&data).unwrap(), image::ImageFormat::Png);
+
+        let uri = OutputFormat::Svg.data_uri(b"<svg/>");
+        assert_eq!(uri, "data:image/svg+xml;base64,PHN2Zy8+");
+    }
+
+    #[test]
+    fn test_detailed_symbol_parameters() {
+        let generated = generate_pay_by_square_qr_detailed(
+            &payment(),
+            QrOptions::default(),
+            None,
+            &ValidationProfile::standard(),
+        )
+        .unwrap();
+        let symbol =
+            qrcode::QrCode::with_error_correction_level(&generated.code, EcLevel::M).unwrap();
+        assert_eq!(generated.ec_level, EcLevel::M);
+        assert_eq!(qrcode::Version::Normal(generated.version), symbol.version());
+
+        // A logo covers modules, so the symbol switches to level H
+        let mut logo = Vec::new();
+        image::RgbaImage::from_pixel(8, 8, image::Rgba([200, 0, 0, 255]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut logo),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        let opts = QrOptions {
+            logo: Some(Logo::Image(logo)),
+            ..Default::default()
+        };
+        let generated = generate_pay_by_square_qr_detailed(
+            &payment(),
+            opts,
+            None,
+            &ValidationProfile::standard(),
+        )
+        .unwrap();
+        let symbol =
+            qrcode::QrCode::with_error_correction_level(&generated.code, EcLevel::H).unwrap();
+        assert_eq!(generated.ec_level, EcLevel::H);
+        assert_eq!(qrcode::Version::Normal(generated.version), symbol.version());
+    }
 }
@@ -0,0 +1,242 @@
+use crate::errors::{PayBySquareError, Result};
+use crate::models::{Logo, QrOptions};
+use image::RgbaImage;
+use qrcode::QrCode;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Largest logo side relative to the symbol width
+const MAX_LOGO_RATIO: f32 = 0.3;
+
+/// Share of the correctable codewords the logo may cover; the rest is left for print damage
+const LOGO_BUDGET_RATIO: f32 = 0.5;
+
+/// Space between the edge of the cleared area and the logo in modules
+const LOGO_PADDING: f32 = 0.5;
+
+/// Image formats accepted for registered logos
+const LOGO_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+
+/// Module-aligned rectangle cleared behind the logo, in modules from the symbol corner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LogoArea {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl LogoArea {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+/// Decoded logo with the area it takes in a symbol
+#[derive(Debug, Clone)]
+pub(crate) struct PlacedLogo {
+    pub image: RgbaImage,
+    pub area: LogoArea,
+}
+
+impl PlacedLogo {
+    /// Returns `(x, y, width, height)` of the logo in modules from the symbol corner
+    ///
+    /// The logo keeps its aspect ratio and is centered in the cleared area.
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        let (image_width, image_height) = self.image.dimensions();
+        let inner_width = self.area.width as f32 - 2.0 * LOGO_PADDING;
+        let inner_height = self.area.height as f32 - 2.0 * LOGO_PADDING;
+        let scale = (inner_width / image_width as f32).min(inner_height / image_height as f32);
+        let width = image_width as f32 * scale;
+        let height = image_height as f32 * scale;
+        (
+            self.area.x as f32 + (self.area.width as f32 - width) / 2.0,
+            self.area.y as f32 + (self.area.height as f32 - height) / 2.0,
+            width,
+            height,
+        )
+    }
+}
+
+/// Decodes a logo and finds the largest centre area the symbol can give up
+///
+/// Named logos must be resolved through a [`LogoRegistry`] first.
+pub(crate) fn place_logo(qr: &QrCode, logo: &Logo) -> Result<PlacedLogo> {
+    let data = match logo {
+        Logo::Named(name) => return Err(PayBySquareError::UnknownLogo(name.clone())),
+        Logo::Image(data) => data,
+    };
+    let image = image::load_from_memory(data)
+        .map_err(|e| PayBySquareError::ImageError(format!("Failed to load logo image: {}", e)))?
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Err(PayBySquareError::ImageError(
+            "Logo image is empty".to_string(),
+        ));
+    }
+
+    let area = logo_area(qr, width as f32 / height as f32)
+        .ok_or_else(|| PayBySquareError::QrError("symbol has no room for a logo".to_string()))?;
+    Ok(PlacedLogo { image, area })
+}
+
+/// Largest centered area with the logo's aspect ratio within the error correction budget
+///
+/// Sides are odd so the area is centered on whole modules. Only data modules
+/// count against the budget; a cleared module destroys at most one codeword bit.
+fn logo_area(qr: &QrCode, aspect: f32) -> Option<LogoArea> {
+    let width = qr.width();
+    let budget = qr.max_allowed_errors() as f32 * 8.0 * LOGO_BUDGET_RATIO;
+    let longest = odd((width as f32 * MAX_LOGO_RATIO) as usize);
+
+    (3..=longest).rev().step_by(2).find_map(|long| {
+        let short = odd((long as f32 * aspect.min(1.0 / aspect)).round() as usize);
+        let (w, h) = if aspect >= 1.0 {
+            (long, short)
+        } else {
+            (short, long)
+        };
+        let area = LogoArea {
+            x: (width - w) / 2,
+            y: (width - h) / 2,
+            width: w,
+            height: h,
+        };
+
+        let cleared = (area.y..area.y + h)
+            .flat_map(|y| (area.x..area.x + w).map(move |x| (x, y)))
+            .filter(|&(x, y)| !qr.is_functional(x, y))
+            .count();
+        (cleared as f32 <= budget).then_some(area)
+    })
+}
+
+/// Rounds down to an odd number, at least 1
+fn odd(value: usize) -> usize {
+    if value.is_multiple_of(2) {
+        value.saturating_sub(1).max(1)
+    } else {
+        value
+    }
+}
+
+/// Logos registered by name
+#[derive(Debug, Clone, Default)]
+pub struct LogoRegistry {
+    logos: HashMap<String, Vec<u8>>,
+}
+
+impl LogoRegistry {
+    /// Loads every PNG, JPEG and WebP image in a directory under its file name without extension
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let mut registry = Self::default();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let supported = path.extension().is_some_and(|e| {
+                LOGO_EXTENSIONS
+                    .iter()
+                    .any(|ext| e.eq_ignore_ascii_case(ext))
+            });
+            if !supported {
+                continue;
+            }
+
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            registry.insert(&name, fs::read(&path)?)?;
+        }
+        Ok(registry)
+    }
+
+    /// Adds or replaces a logo after checking that it decodes
+    pub fn insert(&mut self, name: &str, image_data: Vec<u8>) -> Result<()> {
+        image::load_from_memory(&image_data)
+            .map_err(|e| PayBySquareError::ConfigError(format!("logo '{}': {}", name, e)))?;
+        self.logos.insert(name.to_string(), image_data);
+        Ok(())
+    }
+
+    /// Replaces a named logo in the options with its image
+    pub fn resolve(&self, opts: &mut QrOptions) -> Result<()> {
+        if let Some(Logo::Named(ref name)) = opts.logo {
+            let data = self
+                .logos
+                .get(name)
+                .ok_or_else(|| PayBySquareError::UnknownLogo(name.clone()))?;
+            opts.logo = Some(Logo::Image(data.clone()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+    use qrcode::EcLevel;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_pixel(width, height, Rgba([200, 0, 0, 255]));
+        let mut data = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut data),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_logo_area_within_budget() {
+        let qr = QrCode::with_error_correction_level(b"0004A00090IFU3P5R8G9E0N4FD1K5U", EcLevel::H)
+            .unwrap();
+        let logo = place_logo(&qr, &Logo::Image(png(40, 40))).unwrap();
+        let area = logo.area;
+
+        // Centered with odd sides, inside the budget and the size cap
+        assert_eq!(area.width, area.height);
+        assert_eq!(area.width % 2, 1);
+        assert_eq!(2 * area.x + area.width, qr.width());
+        assert!(area.width as f32 <= qr.width() as f32 * MAX_LOGO_RATIO);
+        assert!(area.width * area.height <= qr.max_allowed_errors() * 8);
+
+        let (x, y, width, height) = logo.bounds();
+        assert!(x >= area.x as f32 && y >= area.y as f32);
+        assert!((width - height).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_logo_area_keeps_aspect_ratio() {
+        let qr = QrCode::with_error_correction_level(b"TEST", EcLevel::H).unwrap();
+        let logo = place_logo(&qr, &Logo::Image(png(90, 30))).unwrap();
+        assert!(logo.area.width > logo.area.height);
+    }
+
+    #[test]
+    fn test_registry_resolves_names() {
+        let mut registry = LogoRegistry::default();
+        registry.insert("acme", png(10, 10)).unwrap();
+        assert!(matches!(
+            registry.insert("broken", b"not an image".to_vec()),
+            Err(PayBySquareError::ConfigError(_))
+        ));
+
+        let mut opts = QrOptions {
+            logo: Some(Logo::Named("acme".to_string())),
+            ..Default::default()
+        };
+        registry.resolve(&mut opts).unwrap();
+        assert_eq!(opts.logo, Some(Logo::Image(png(10, 10))));
+
+        opts.logo = Some(Logo::Named("missing".to_string()));
+        assert!(matches!(
+            registry.resolve(&mut opts),
+            Err(PayBySquareError::UnknownLogo(_))
+        ));
+    }
+}
@@ -1,24 +1,36 @@
 use actix_cors::Cors;
-use actix_web::{error::ResponseError, get, post, web, App, HttpResponse, HttpServer, Responder};
+use actix_web::http::header;
+use actix_web::{get, post, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use pay_by_square_generator::{
-    generate_code_only, generate_pay_by_square_qr, CodeResponse, PaymentRequest, QrOptions,
+    alt_text, generate_code_only, generate_code_with_profile, generate_label_sheet_with_profile,
+    generate_pay_by_square_qr_detailed, generate_pay_by_square_qr_with_profile,
+    generate_payment_slip_with_profile, generate_qr_text, sanitize_payment_request,
+    stamp_pdf_with_profile, CodeResponse, FieldChange, FrameTemplateRegistry, GenerateQrRequest,
+    GenerateQuery, ImageSize, LabelSheetRequest, Locale, LogoRegistry, OutputFormat,
+    PayBySquareError, PaymentRequest, ProfileRegistry, QrOptions, QrOptionsQuery, QrResponse,
+    Result, SlipQuery, SlipTemplateRegistry, StampRequest, TextOptions, TextStyle,
+    ValidationProfile, ValidationWarning,
 };
 use std::env;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-// Embed frame.png at compile time
-const FRAME_DATA: Option<&[u8]> = if cfg!(feature = "embed-frame") {
-    Some(include_bytes!("../frame.png"))
-} else {
-    None
-};
+/// Largest JSON body of `/stamp` in bytes, enough for base64-encoded PDFs;
+/// other endpoints keep the default limit
+const MAX_STAMP_BODY: usize = 16 * 1024 * 1024;
 
 #[derive(OpenApi)]
 #[openapi(
-    paths(generate_qr, generate_code, version),
+    paths(generate_qr, generate, generate_labels, generate_slip, stamp, generate_code, version),
     components(schemas(
         PaymentRequest,
+        GenerateQrRequest,
+        QrOptions,
+        LabelSheetRequest,
+        StampRequest,
+        pay_by_square_generator::StampPlacement,
+        pay_by_square_generator::LabelSheetOptions,
+        pay_by_square_generator::LabelGrid,
         pay_by_square_generator::BankAccount,
         pay_by_square_generator::PaymentOption,
         pay_by_square_generator::StandingOrder,
@@ -27,6 +39,17 @@ const FRAME_DATA: Option<&[u8]> = if cfg!(feature = "embed-frame") {
         pay_by_square_generator::DirectDebitType,
         pay_by_square_generator::Periodicity,
         CodeResponse,
+        QrResponse,
+        ImageSize,
+        FieldChange,
+        pay_by_square_generator::Warning,
+        pay_by_square_generator::Rule,
+        OutputFormat,
+        pay_by_square_generator::PageSize,
+        pay_by_square_generator::FrameVariant,
+        pay_by_square_generator::RgbaColor,
+        pay_by_square_generator::CaptionFields,
+        Locale,
     )),
     tags(
         (name = "pay-by-square-generator", description = "PayBySquare QR code generator API")
@@ -39,28 +62,403 @@ const FRAME_DATA: Option<&[u8]> = if cfg!(feature = "embed-frame") {
 )]
 struct ApiDoc;
 
-/// Generates a PayBySquare QR code image (PNG)
+/// Generates a PayBySquare QR code image (PNG, JPEG, WebP, SVG or PDF)
+///
+/// The format is taken from the `format` query parameter, otherwise negotiated
+/// from the `Accept` header (preferring `options.format` from the body).
 #[utoipa::path(
     post,
     path = "/pay-by-square-generator/generate-qr",
     tag = "pay-by-square-generator",
-    request_body = PaymentRequest,
+    request_body = GenerateQrRequest,
+    params(
+        GenerateQuery,
+        QrOptionsQuery,
+        ("Accept-Language" = Option<String>, Header, description = "Language of error messages (en, sk, cs)"),
+        ("X-API-Key" = Option<String>, Header, description = "API key selecting the client's validation profile")
+    ),
     responses(
-        (status = 200, description = "QR code image generated successfully", content_type = "image/png"),
+        (status = 200, description = "QR code image generated successfully", content_type = ["image/png", "image/jpeg", "image/webp", "image/svg+xml", "application/pdf", "application/zpl", "application/vnd.escpos"],
+            headers(
+                ("Content-Disposition" = String, description = "Suggested file name, e.g. inline; filename=\"pay-by-square.png\""),
+                ("X-Sanitized-Fields" = String, description = "Comma-separated list of fields modified in sanitize mode"),
+                ("X-Validation-Warnings" = String, description = "Comma-separated list of rules reported as warnings"),
+                ("X-Alt-Text" = String, description = "Description of the payment for screen readers, percent-encoded UTF-8")
+            )),
         (status = 400, description = "Invalid request data"),
+        (status = 403, description = "The requested profile differs from the one assigned to the API key"),
+        (status = 406, description = "None of the formats in the Accept header is supported; the body lists supported content types"),
+        (status = 422, description = "The rendered code failed verification"),
         (status = 500, description = "Internal server error")
     )
 )]
 #[post("/pay-by-square-generator/generate-qr")]
-async fn generate_qr(payment: web::Json<PaymentRequest>) -> impl Responder {
-    let opts = QrOptions {
-        with_frame: true,
-        qr_size: 300,
-    };
+#[allow(clippy::too_many_arguments)]
+async fn generate_qr(
+    req: HttpRequest,
+    body: web::Json<GenerateQrRequest>,
+    query: web::Query<GenerateQuery>,
+    options_query: web::Query<QrOptionsQuery>,
+    locale: Locale,
+    profiles: web::Data<ProfileRegistry>,
+    templates: web::Data<FrameTemplateRegistry>,
+    logos: web::Data<LogoRegistry>,
+) -> impl Responder {
+    let GenerateQrRequest { payment, options } = body.into_inner();
+
+    // Body options, overridden by query parameters
+    let mut opts = options.unwrap_or_default();
+    options_query.apply(&mut opts);
+    opts.caption_locale.get_or_insert(locale);
+
+    // An explicit `format` query parameter wins, otherwise the requested format
+    // is used when the Accept header allows it
+    if options_query.format.is_none() {
+        match negotiate_format(&req, opts.format) {
+            Ok(format) => opts.format = format,
+            Err(e) => return e.localized_response(locale),
+        }
+    }
+    let format = opts.format;
+
+    let (payment, changes) = prepare_payment(payment, &query);
+
+    let template = templates.resolve(opts.frame_template.as_deref());
+    let logo = logos.resolve(&mut opts);
+    let result = resolve_profile(&profiles, &req, &query).and_then(|profile| {
+        logo?;
+        generate_pay_by_square_qr_with_profile(&payment, opts, template?, profile)
+    });
+
+    match result {
+        Ok((image_data, warnings)) => {
+            let mut response = HttpResponse::Ok();
+            response.content_type(format.content_type());
+            response.insert_header((
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"{}\"", file_name(&payment, format)),
+            ));
+            if let Some((width, height)) = raster_size(&image_data, format) {
+                response.insert_header(("X-Image-Size", format!("{}x{}", width, height)));
+            }
+            response.insert_header(("X-Alt-Text", percent_encode(&alt_text(&payment, locale))));
+            if !changes.is_empty() {
+                let fields = changes
+                    .iter()
+                    .map(|c| c.field.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                response.append_header(("X-Sanitized-Fields", fields));
+            }
+            if !warnings.is_empty() {
+                let rules = warnings
+                    .iter()
+                    .map(|w| w.rule.name())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                response.append_header(("X-Validation-Warnings", rules));
+            }
+            response.body(image_data)
+        }
+        Err(e) => e.localized_response(locale),
+    }
+}
+
+/// Generates a PayBySquare code with the image as a data URI and symbol metadata
+///
+/// The image format is `options.format`, overridden by the `format` query parameter.
+#[utoipa::path(
+    post,
+    path = "/pay-by-square-generator/generate",
+    tag = "pay-by-square-generator",
+    request_body = GenerateQrRequest,
+    params(
+        GenerateQuery,
+        QrOptionsQuery,
+        ("Accept-Language" = Option<String>, Header, description = "Language of error messages (en, sk, cs)"),
+        ("X-API-Key" = Option<String>, Header, description = "API key selecting the client's validation profile")
+    ),
+    responses(
+        (status = 200, description = "Code and image generated successfully", body = QrResponse),
+        (status = 400, description = "Invalid request data"),
+        (status = 403, description = "The requested profile differs from the one assigned to the API key"),
+        (status = 422, description = "The rendered code failed verification"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[post("/pay-by-square-generator/generate")]
+#[allow(clippy::too_many_arguments)]
+async fn generate(
+    req: HttpRequest,
+    body: web::Json<GenerateQrRequest>,
+    query: web::Query<GenerateQuery>,
+    options_query: web::Query<QrOptionsQuery>,
+    locale: Locale,
+    profiles: web::Data<ProfileRegistry>,
+    templates: web::Data<FrameTemplateRegistry>,
+    logos: web::Data<LogoRegistry>,
+) -> impl Responder {
+    let GenerateQrRequest { payment, options } = body.into_inner();
+
+    // Body options, overridden by query parameters
+    let mut opts = options.unwrap_or_default();
+    options_query.apply(&mut opts);
+    opts.caption_locale.get_or_insert(locale);
+    let format = opts.format;
+
+    let (payment, changes) = prepare_payment(payment, &query);
+
+    let template = templates.resolve(opts.frame_template.as_deref());
+    let logo = logos.resolve(&mut opts);
+    let result = resolve_profile(&profiles, &req, &query).and_then(|profile| {
+        logo?;
+        generate_pay_by_square_qr_detailed(&payment, opts, template?, profile)
+    });
+
+    match result {
+        Ok(generated) => HttpResponse::Ok().json(QrResponse {
+            image: format.data_uri(&generated.image_data),
+            qr_version: generated.version,
+            ec_level: ec_level_name(generated.ec_level).to_string(),
+            alt_text: alt_text(&payment, locale),
+            size: raster_size(&generated.image_data, format)
+                .map(|(width, height)| ImageSize { width, height }),
+            changes: query.sanitize.then_some(changes),
+            warnings: to_warnings(&generated.warnings, locale),
+            code: generated.code,
+        }),
+        Err(e) => e.localized_response(locale),
+    }
+}
+
+/// Generates an A4 label sheet PDF with one PayBySquare code per payment
+///
+/// Each label holds the code and its caption; payments fill the grid row by
+/// row and continue on further pages.
+#[utoipa::path(
+    post,
+    path = "/pay-by-square-generator/labels",
+    tag = "pay-by-square-generator",
+    request_body = LabelSheetRequest,
+    params(
+        GenerateQuery,
+        ("Accept-Language" = Option<String>, Header, description = "Language of error messages and captions (en, sk, cs)"),
+        ("X-API-Key" = Option<String>, Header, description = "API key selecting the client's validation profile")
+    ),
+    responses(
+        (status = 200, description = "Label sheet generated successfully", content_type = "application/pdf",
+            headers(
+                ("Content-Disposition" = String, description = "Suggested file name: inline; filename=\"pay-by-square-labels.pdf\""),
+                ("X-Validation-Warnings" = String, description = "Comma-separated list of rules reported as warnings")
+            )),
+        (status = 400, description = "Invalid request data; errors of a payment name its number"),
+        (status = 403, description = "The requested profile differs from the one assigned to the API key"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[post("/pay-by-square-generator/labels")]
+async fn generate_labels(
+    req: HttpRequest,
+    body: web::Json<LabelSheetRequest>,
+    query: web::Query<GenerateQuery>,
+    locale: Locale,
+    profiles: web::Data<ProfileRegistry>,
+    logos: web::Data<LogoRegistry>,
+) -> impl Responder {
+    let LabelSheetRequest {
+        payments,
+        options,
+        sheet,
+    } = body.into_inner();
+
+    let mut opts = options.unwrap_or_default();
+    opts.caption_locale.get_or_insert(locale);
+    let payments: Vec<PaymentRequest> = payments
+        .into_iter()
+        .map(|payment| prepare_payment(payment, &query).0)
+        .collect();
+
+    let logo = logos.resolve(&mut opts);
+    let result = resolve_profile(&profiles, &req, &query).and_then(|profile| {
+        logo?;
+        generate_label_sheet_with_profile(&payments, &opts, &sheet, profile)
+    });
+
+    match result {
+        Ok((pdf, warnings)) => {
+            let mut response = HttpResponse::Ok();
+            response.content_type(OutputFormat::Pdf.content_type());
+            response.insert_header((
+                header::CONTENT_DISPOSITION,
+                "inline; filename=\"pay-by-square-labels.pdf\"",
+            ));
+            if !warnings.is_empty() {
+                let mut rules: Vec<&str> = warnings.iter().map(|w| w.rule.name()).collect();
+                rules.sort_unstable();
+                rules.dedup();
+                response.append_header(("X-Validation-Warnings", rules.join(", ")));
+            }
+            response.body(pdf)
+        }
+        Err(e) => e.localized_response(locale),
+    }
+}
+
+/// Generates a payment slip PDF with the payment details and PayBySquare code
+///
+/// The layout comes from a slip template: the built-in `default` A4 slip or a
+/// template loaded from `SLIP_TEMPLATES`. Labels, amounts and dates follow the
+/// caption locale.
+#[utoipa::path(
+    post,
+    path = "/pay-by-square-generator/slip",
+    tag = "pay-by-square-generator",
+    request_body = GenerateQrRequest,
+    params(
+        GenerateQuery,
+        SlipQuery,
+        QrOptionsQuery,
+        ("Accept-Language" = Option<String>, Header, description = "Language of error messages and slip labels (en, sk, cs)"),
+        ("X-API-Key" = Option<String>, Header, description = "API key selecting the client's validation profile")
+    ),
+    responses(
+        (status = 200, description = "Payment slip generated successfully", content_type = "application/pdf",
+            headers(
+                ("Content-Disposition" = String, description = "Suggested file name, e.g. inline; filename=\"pay-by-square.pdf\""),
+                ("X-Validation-Warnings" = String, description = "Comma-separated list of rules reported as warnings")
+            )),
+        (status = 400, description = "Invalid request data or unknown slip template"),
+        (status = 403, description = "The requested profile differs from the one assigned to the API key"),
+        (status = 422, description = "The rendered code failed verification"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+#[post("/pay-by-square-generator/slip")]
+#[allow(clippy::too_many_arguments)]
+async fn generate_slip(
+    req: HttpRequest,
+    body: web::Json<GenerateQrRequest>,
+    query: web::Query<GenerateQuery>,
+    slip_query: web::Query<SlipQuery>,
+    options_query: web::Query<QrOptionsQuery>,
+    locale: Locale,
+    profiles: web::Data<ProfileRegistry>,
+    slips: web::Data<SlipTemplateRegistry>,
+    logos: web::Data<LogoRegistry>,
+) -> impl Responder {
+    let GenerateQrRequest { payment, options } = body.into_inner();
+
+    // Body options, overridden by query parameters
+    let mut opts = options.unwrap_or_default();
+    options_query.apply(&mut opts);
+    opts.caption_locale.get_or_insert(locale);
+
+    let (payment, _) = prepare_payment(payment, &query);
+
+    let template = slips.resolve(slip_query.template.as_deref());
+    let logo = logos.resolve(&mut opts);
+    let result = resolve_profile(&profiles, &req, &query).and_then(|profile| {
+        logo?;
+        generate_payment_slip_with_profile(&payment, &opts, template?, profile)
+    });
+
+    match result {
+        Ok((pdf, warnings)) => {
+            let mut response = HttpResponse::Ok();
+            response.content_type(OutputFormat::Pdf.content_type());
+            response.insert_header((
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "inline; filename=\"{}\"",
+                    file_name(&payment, OutputFormat::Pdf)
+                ),
+            ));
+            if !warnings.is_empty() {
+                let rules = warnings
+                    .iter()
+                    .map(|w| w.rule.name())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                response.append_header(("X-Validation-Warnings", rules));
+            }
+            response.body(pdf)
+        }
+        Err(e) => e.localized_response(locale),
+    }
+}
+
+/// Stamps a PayBySquare code onto a page of an existing PDF
+///
+/// The code is drawn with vector modules and appended as an incremental update,
+/// so the rest of the document is left as it was.
+#[utoipa::path(
+    post,
+    path = "/pay-by-square-generator/stamp",
+    tag = "pay-by-square-generator",
+    request_body = StampRequest,
+    params(
+        GenerateQuery,
+        ("Accept-Language" = Option<String>, Header, description = "Language of error messages (en, sk, cs)"),
+        ("X-API-Key" = Option<String>, Header, description = "API key selecting the client's validation profile")
+    ),
+    responses(
+        (status = 200, description = "PDF with the stamped code", content_type = "application/pdf",
+            headers(
+                ("Content-Disposition" = String, description = "Suggested file name, e.g. inline; filename=\"pay-by-square.pdf\""),
+                ("X-Validation-Warnings" = String, description = "Comma-separated list of rules reported as warnings")
+            )),
+        (status = 400, description = "Invalid request data, unreadable PDF or placement outside the page"),
+        (status = 403, description = "The requested profile differs from the one assigned to the API key"),
+        (status = 422, description = "The rendered code failed verification"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn stamp(
+    req: HttpRequest,
+    body: web::Json<StampRequest>,
+    query: web::Query<GenerateQuery>,
+    locale: Locale,
+    profiles: web::Data<ProfileRegistry>,
+    logos: web::Data<LogoRegistry>,
+) -> impl Responder {
+    let request = body.into_inner();
+    let mut opts = request.options.clone().unwrap_or_default();
+    let (payment, _) = prepare_payment(request.payment.clone(), &query);
 
-    match generate_pay_by_square_qr(&payment, opts, FRAME_DATA) {
-        Ok(png_data) => HttpResponse::Ok().content_type("image/png").body(png_data),
-        Err(e) => e.error_response(),
+    let logo = logos.resolve(&mut opts);
+    let result = resolve_profile(&profiles, &req, &query).and_then(|profile| {
+        logo?;
+        stamp_pdf_with_profile(
+            &request.pdf_data()?,
+            &payment,
+            &opts,
+            &request.placement,
+            profile,
+        )
+    });
+
+    match result {
+        Ok((pdf, warnings)) => {
+            let mut response = HttpResponse::Ok();
+            response.content_type(OutputFormat::Pdf.content_type());
+            response.insert_header((
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "inline; filename=\"{}\"",
+                    file_name(&payment, OutputFormat::Pdf)
+                ),
+            ));
+            if !warnings.is_empty() {
+                let rules = warnings
+                    .iter()
+                    .map(|w| w.rule.name())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                response.append_header(("X-Validation-Warnings", rules));
+            }
+            response.body(pdf)
+        }
+        Err(e) => e.localized_response(locale),
     }
 }
 
@@ -70,20 +468,136 @@ async fn generate_qr(payment: web::Json<PaymentRequest>) -> impl Responder {
     path = "/pay-by-square-generator/generate-code",
     tag = "pay-by-square-generator",
     request_body = PaymentRequest,
+    params(
+        GenerateQuery,
+        ("Accept-Language" = Option<String>, Header, description = "Language of error messages (en, sk, cs)"),
+        ("X-API-Key" = Option<String>, Header, description = "API key selecting the client's validation profile")
+    ),
     responses(
         (status = 200, description = "Code generated successfully", body = CodeResponse),
         (status = 400, description = "Invalid request data"),
+        (status = 403, description = "The requested profile differs from the one assigned to the API key"),
         (status = 500, description = "Internal server error")
     )
 )]
 #[post("/pay-by-square-generator/generate-code")]
-async fn generate_code(payment: web::Json<PaymentRequest>) -> impl Responder {
-    match generate_code_only(&payment) {
-        Ok(code) => HttpResponse::Ok().json(CodeResponse { code }),
-        Err(e) => e.error_response(),
+async fn generate_code(
+    req: HttpRequest,
+    payment: web::Json<PaymentRequest>,
+    query: web::Query<GenerateQuery>,
+    locale: Locale,
+    profiles: web::Data<ProfileRegistry>,
+) -> impl Responder {
+    let (payment, changes) = prepare_payment(payment.into_inner(), &query);
+
+    let result = resolve_profile(&profiles, &req, &query)
+        .and_then(|profile| generate_code_with_profile(&payment, profile));
+
+    match result {
+        Ok((code, warnings)) => HttpResponse::Ok().json(CodeResponse {
+            code,
+            changes: query.sanitize.then_some(changes),
+            warnings: to_warnings(&warnings, locale),
+        }),
+        Err(e) => e.localized_response(locale),
+    }
+}
+
+/// Applies sanitize mode to the incoming payment if requested
+fn prepare_payment(
+    payment: PaymentRequest,
+    query: &GenerateQuery,
+) -> (PaymentRequest, Vec<FieldChange>) {
+    if query.sanitize {
+        sanitize_payment_request(&payment)
+    } else {
+        (payment, Vec::new())
+    }
+}
+
+/// Picks the output format from the `Accept` header, preferring `requested`
+fn negotiate_format(req: &HttpRequest, requested: OutputFormat) -> Result<OutputFormat> {
+    let accept = match req.headers().get(header::ACCEPT) {
+        Some(value) => value.to_str().unwrap_or_default(),
+        None => return Ok(requested),
+    };
+
+    let accepted = OutputFormat::accepted(accept);
+    if accepted.contains(&requested) {
+        Ok(requested)
+    } else {
+        accepted
+            .first()
+            .copied()
+            .ok_or_else(|| PayBySquareError::NotAcceptable(accept.to_string()))
+    }
+}
+
+/// Builds the download file name, e.g. "pay-by-square-1234567890.png"
+fn file_name(payment: &PaymentRequest, format: OutputFormat) -> String {
+    let reference = payment
+        .variable_symbol
+        .as_deref()
+        .filter(|vs| !vs.is_empty() && vs.chars().all(|c| c.is_ascii_alphanumeric()));
+    match reference {
+        Some(vs) => format!("pay-by-square-{}.{}", vs, format.extension()),
+        None => format!("pay-by-square.{}", format.extension()),
+    }
+}
+
+/// Returns the pixel size of raster output, which can exceed `qr_size` for dense codes
+fn raster_size(data: &[u8], format: OutputFormat) -> Option<(u32, u32)> {
+    match format {
+        OutputFormat::Png | OutputFormat::Jpeg | OutputFormat::Webp => {
+            image::ImageReader::new(std::io::Cursor::new(data))
+                .with_guessed_format()
+                .ok()?
+                .into_dimensions()
+                .ok()
+        }
+        OutputFormat::Svg | OutputFormat::Pdf | OutputFormat::Zpl | OutputFormat::Escpos => None,
+    }
+}
+
+/// Percent-encodes text for a header value, keeping printable ASCII except `%`
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b' '..=b'~' if byte != b'%' => (byte as char).to_string(),
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn ec_level_name(ec_level: qrcode::EcLevel) -> &'static str {
+    match ec_level {
+        qrcode::EcLevel::L => "L",
+        qrcode::EcLevel::M => "M",
+        qrcode::EcLevel::Q => "Q",
+        qrcode::EcLevel::H => "H",
     }
 }
 
+/// Selects the validation profile from the query or the client's API key
+fn resolve_profile<'a>(
+    profiles: &'a ProfileRegistry,
+    req: &HttpRequest,
+    query: &GenerateQuery,
+) -> Result<&'a ValidationProfile> {
+    let api_key = req
+        .headers()
+        .get("X-API-Key")
+        .and_then(|value| value.to_str().ok());
+    profiles.resolve(query.profile.as_deref(), api_key)
+}
+
+fn to_warnings(
+    warnings: &[ValidationWarning],
+    locale: Locale,
+) -> Vec<pay_by_square_generator::Warning> {
+    warnings.iter().map(|w| w.to_warning(locale)).collect()
+}
+
 /// Returns the application version
 #[utoipa::path(
     get,
@@ -116,8 +630,48 @@ async fn root_redirect() -> impl Responder {
         .finish()
 }
 
+/// Prints the code and its symbol for a payment in a JSON file (`-` for stdin)
+///
+/// Usage: `pay-by-square-generator --print <payment.json|-> [--ascii] [--invert]`
+fn print_qr(args: &[String]) -> std::io::Result<()> {
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidInput, message);
+
+    let mut path = None;
+    let mut opts = TextOptions::default();
+    for arg in args {
+        match arg.as_str() {
+            "--ascii" => opts.style = TextStyle::Ascii,
+            "--invert" => opts.invert = true,
+            _ if path.is_none() => path = Some(arg.as_str()),
+            _ => return Err(invalid(format!("unexpected argument: {}", arg))),
+        }
+    }
+
+    let json = match path {
+        Some("-") => std::io::read_to_string(std::io::stdin())?,
+        Some(path) => std::fs::read_to_string(path)?,
+        None => {
+            return Err(invalid(
+                "--print needs a payment JSON file or -".to_string(),
+            ))
+        }
+    };
+    let payment: PaymentRequest = serde_json::from_str(&json)?;
+    let code = generate_code_only(&payment).map_err(|e| invalid(e.to_string()))?;
+    let text = generate_qr_text(&code, &opts).map_err(|e| invalid(e.to_string()))?;
+
+    println!("{}", code);
+    print!("{}", text);
+    Ok(())
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--print") {
+        return print_qr(&args[1..]);
+    }
+
     // Initialize logging
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
@@ -128,6 +682,34 @@ async fn main() -> std::io::Result<()> {
 
     let bind_address = format!("0.0.0.0:{}", port);
 
+    let profiles = match env::var("VALIDATION_PROFILES") {
+        Ok(path) => ProfileRegistry::load(&path)
+            .unwrap_or_else(|e| panic!("Failed to load validation profiles from {}: {}", path, e)),
+        Err(_) => ProfileRegistry::default(),
+    };
+    let profiles = web::Data::new(profiles);
+
+    let templates = match env::var("FRAME_TEMPLATES") {
+        Ok(dir) => FrameTemplateRegistry::load(&dir)
+            .unwrap_or_else(|e| panic!("Failed to load frame templates from {}: {}", dir, e)),
+        Err(_) => FrameTemplateRegistry::default(),
+    };
+    let templates = web::Data::new(templates);
+
+    let logos = match env::var("LOGOS") {
+        Ok(dir) => LogoRegistry::load(&dir)
+            .unwrap_or_else(|e| panic!("Failed to load logos from {}: {}", dir, e)),
+        Err(_) => LogoRegistry::default(),
+    };
+    let logos = web::Data::new(logos);
+
+    let slips = match env::var("SLIP_TEMPLATES") {
+        Ok(dir) => SlipTemplateRegistry::load(&dir)
+            .unwrap_or_else(|e| panic!("Failed to load slip templates from {}: {}", dir, e)),
+        Err(_) => SlipTemplateRegistry::default(),
+    };
+    let slips = web::Data::new(slips);
+
     println!("🚀 Starting PayBySquare Generator API");
     println!(
         "📖 Documentation: http://localhost:{}/pay-by-square-generator/docs",
@@ -136,7 +718,7 @@ async fn main() -> std::io::Result<()> {
     println!("🔍 Health check: http://localhost:{}/health", port);
     println!("🎯 Listening on: {}", bind_address);
 
-    HttpServer::new(|| {
+    HttpServer::new(move || {
         // Configure CORS
         let cors = Cors::default()
             .allow_any_origin()
@@ -147,9 +729,21 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .wrap(cors)
             .wrap(actix_web::middleware::Logger::default())
+            .app_data(profiles.clone())
+            .app_data(templates.clone())
+            .app_data(logos.clone())
+            .app_data(slips.clone())
             .service(root_redirect)
             .service(health)
             .service(generate_qr)
+            .service(generate)
+            .service(generate_labels)
+            .service(generate_slip)
+            .service(
+                web::resource("/pay-by-square-generator/stamp")
+                    .app_data(web::JsonConfig::default().limit(MAX_STAMP_BODY))
+                    .route(web::post().to(stamp)),
+            )
             .service(generate_code)
             .service(version)
             .service(
@@ -161,3 +755,128 @@ async fn main() -> std::io::Result<()> {
     .run()
     .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_and_read_body_json, call_service, init_service, TestRequest};
+
+    fn payment() -> serde_json::Value {
+        serde_json::json!({
+            "amount": 100.50,
+            "iban": "SK9611000000002918599669",
+            "beneficiary_name": "Ján Novák",
+            "variable_symbol": "1234567890"
+        })
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(
+            percent_encode("Účet č. 5% zľava\nok"),
+            "%C3%9A%C4%8Det %C4%8D. 5%25 z%C4%BEava%0Aok"
+        );
+        assert_eq!(percent_encode("IBAN SK96 1100"), "IBAN SK96 1100");
+    }
+
+    #[actix_web::test]
+    async fn test_alt_text() {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(ProfileRegistry::default()))
+                .app_data(web::Data::new(FrameTemplateRegistry::default()))
+                .app_data(web::Data::new(LogoRegistry::default()))
+                .service(generate_qr)
+                .service(generate),
+        )
+        .await;
+        let expected =
+            "Platba 100,50 EUR príjemcovi Ján Novák, IBAN SK96 1100 0000 0029 1859 9669, \
+                        variabilný symbol 1234567890";
+
+        let req = TestRequest::post()
+            .uri("/pay-by-square-generator/generate-qr")
+            .insert_header((header::ACCEPT_LANGUAGE, "sk"))
+            .set_json(payment())
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let header = resp.headers().get("X-Alt-Text").unwrap().to_str().unwrap();
+        assert_eq!(header, percent_encode(expected));
+
+        let req = TestRequest::post()
+            .uri("/pay-by-square-generator/generate")
+            .insert_header((header::ACCEPT_LANGUAGE, "sk"))
+            .set_json(payment())
+            .to_request();
+        let resp: QrResponse = call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.alt_text, expected);
+    }
+
+    #[actix_web::test]
+    async fn test_generate_json() {
+        use base64::Engine;
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(ProfileRegistry::default()))
+                .app_data(web::Data::new(FrameTemplateRegistry::default()))
+                .app_data(web::Data::new(LogoRegistry::default()))
+                .service(generate),
+        )
+        .await;
+        let mut unsanitized = payment();
+        unsanitized["iban"] = "sk96 1100 0000 0029 1859 9669".into();
+        unsanitized["swift"] = "GIBASKBX12".into();
+
+        let req = TestRequest::post()
+            .uri("/pay-by-square-generator/generate?sanitize=true&profile=lenient")
+            .set_json(&unsanitized)
+            .to_request();
+        let resp: QrResponse = call_and_read_body_json(&app, req).await;
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(resp.image.strip_prefix("data:image/png;base64,").unwrap())
+            .unwrap();
+        let img = image::load_from_memory(&data).unwrap();
+        assert_eq!(
+            resp.size,
+            Some(ImageSize {
+                width: img.width(),
+                height: img.height()
+            })
+        );
+        let symbol =
+            qrcode::QrCode::with_error_correction_level(&resp.code, qrcode::EcLevel::M).unwrap();
+        assert_eq!(qrcode::Version::Normal(resp.qr_version), symbol.version());
+        assert_eq!(resp.ec_level, "M");
+        assert_eq!(
+            resp.alt_text,
+            "Payment of 100.50 EUR to Ján Novák, IBAN SK96 1100 0000 0029 1859 9669, \
+             variable symbol 1234567890"
+        );
+        let changes = resp.changes.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "iban");
+        assert_eq!(resp.warnings.len(), 1);
+        assert_eq!(resp.warnings[0].rule, pay_by_square_generator::Rule::Swift);
+
+        // Vector output has no pixel size
+        let req = TestRequest::post()
+            .uri("/pay-by-square-generator/generate?format=svg")
+            .set_json(payment())
+            .to_request();
+        let resp: QrResponse = call_and_read_body_json(&app, req).await;
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(
+                resp.image
+                    .strip_prefix("data:image/svg+xml;base64,")
+                    .unwrap(),
+            )
+            .unwrap();
+        assert!(String::from_utf8(data).unwrap().starts_with("<svg"));
+        assert_eq!(resp.size, None);
+        assert_eq!(resp.ec_level, "M");
+        assert!(resp.warnings.is_empty());
+        assert!(resp.changes.is_none());
+    }
+}
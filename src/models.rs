@@ -1,6 +1,13 @@
+use crate::caption::CaptionFields;
+use crate::errors::PayBySquareError;
+use crate::i18n::{quality_values, Locale};
+use crate::validation::Rule;
+use base64::Engine;
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use std::fmt;
+use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
 use validator::Validate;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
@@ -184,15 +191,101 @@ pub enum DirectDebitType {
     Recurrent,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
 pub struct QrOptions {
     /// Include frame around QR code (default: true)
     #[serde(default = "default_with_frame")]
     pub with_frame: bool,
 
-    /// QR code size in pixels (default: 300)
+    /// QR code size in pixels (100-2000, default: 300); modules are whole pixels and the rest is padded
     #[serde(default = "default_qr_size")]
+    #[validate(range(min = 100, max = 2000))]
+    #[schema(minimum = 100, maximum = 2000)]
     pub qr_size: u32,
+
+    /// Quiet zone around the symbol in modules (0-16, default: 4)
+    #[serde(default = "default_quiet_zone")]
+    #[validate(range(max = 16))]
+    #[schema(minimum = 0, maximum = 16)]
+    pub quiet_zone: u32,
+
+    /// Colour of dark modules (default: #000000)
+    #[serde(default = "RgbaColor::black")]
+    pub foreground: RgbaColor,
+
+    /// Colour of light modules and quiet zone, may be transparent (default: #FFFFFF)
+    #[serde(default = "RgbaColor::white")]
+    pub background: RgbaColor,
+
+    /// Colour of the drawn frame border and caption (default: #0066CC)
+    #[serde(default = "RgbaColor::frame")]
+    pub frame_color: RgbaColor,
+
+    /// Output image format (default: png)
+    #[serde(default)]
+    pub format: OutputFormat,
+
+    /// Physical size of the symbol without quiet zone in millimetres (10-500, PDF default: 30);
+    /// raster output uses it together with `dpi`
+    #[serde(default)]
+    #[validate(range(min = 10.0, max = 500.0))]
+    #[schema(minimum = 10, maximum = 500)]
+    pub size_mm: Option<f32>,
+
+    /// Print resolution of raster output in dots per inch (72-2400, ZPL and ESC/POS default: 203),
+    /// written into PNG and JPEG; with `size_mm` it also sets the pixel size
+    #[serde(default)]
+    #[validate(range(min = 72, max = 2400))]
+    #[schema(minimum = 72, maximum = 2400)]
+    pub dpi: Option<u32>,
+
+    /// Page size of PDF output (default: fit)
+    #[serde(default)]
+    pub page_size: PageSize,
+
+    /// Print payment details under the code (raster and PDF)
+    #[serde(default)]
+    pub caption: bool,
+
+    /// Caption fields in order, comma-separated: amount, iban, vs, ks, ss, due_date (default: all)
+    #[serde(default)]
+    pub caption_fields: CaptionFields,
+
+    /// Language of the caption labels and number format (default: Accept-Language)
+    #[serde(default)]
+    pub caption_locale: Option<Locale>,
+
+    /// Caption of the drawn frame (default: pay)
+    #[serde(default)]
+    pub frame_variant: FrameVariant,
+
+    /// Frame template for raster output (default: the `default` template if configured, otherwise the drawn frame)
+    #[serde(default)]
+    pub frame_template: Option<String>,
+
+    /// Logo in the centre of the symbol: a registered logo name or a base64 data URI;
+    /// switches to error correction level H
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "company")]
+    pub logo: Option<Logo>,
+
+    /// Largest QR version the symbol may use (1-40, default: 40)
+    #[serde(default = "default_max_version")]
+    #[validate(range(min = 1, max = 40))]
+    #[schema(minimum = 1, maximum = 40)]
+    pub max_version: u8,
+
+    /// Scan the rendered symbol and check it decodes back to the payment (default: false)
+    #[serde(default)]
+    pub verify: bool,
+
+    /// Text printed above the code on ESC/POS receipts, lines separated by `\n`
+    #[serde(default)]
+    pub receipt_header: Option<String>,
+
+    /// Text printed below the code and caption on ESC/POS receipts, lines separated by `\n`
+    #[serde(default)]
+    pub receipt_footer: Option<String>,
 }
 
 impl Default for QrOptions {
@@ -200,7 +293,648 @@ impl Default for QrOptions {
         Self {
             with_frame: true,
             qr_size: 300,
+            quiet_zone: default_quiet_zone(),
+            foreground: RgbaColor::black(),
+            background: RgbaColor::white(),
+            frame_color: RgbaColor::frame(),
+            format: OutputFormat::default(),
+            size_mm: None,
+            dpi: None,
+            page_size: PageSize::default(),
+            caption: false,
+            caption_fields: CaptionFields::default(),
+            caption_locale: None,
+            frame_variant: FrameVariant::default(),
+            frame_template: None,
+            logo: None,
+            max_version: default_max_version(),
+            verify: false,
+            receipt_header: None,
+            receipt_footer: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+    Svg,
+    Pdf,
+    Zpl,
+    Escpos,
+}
+
+impl OutputFormat {
+    /// Supported formats, in order of preference for wildcard `Accept` headers
+    pub const ALL: [OutputFormat; 7] = [
+        OutputFormat::Png,
+        OutputFormat::Jpeg,
+        OutputFormat::Webp,
+        OutputFormat::Svg,
+        OutputFormat::Pdf,
+        OutputFormat::Zpl,
+        OutputFormat::Escpos,
+    ];
+
+    /// Returns the MIME type of the format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Webp => "image/webp",
+            OutputFormat::Svg => "image/svg+xml",
+            OutputFormat::Pdf => "application/pdf",
+            OutputFormat::Zpl => "application/zpl",
+            OutputFormat::Escpos => "application/vnd.escpos",
+        }
+    }
+
+    /// Returns the file extension of the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Pdf => "pdf",
+            OutputFormat::Zpl => "zpl",
+            OutputFormat::Escpos => "bin",
+        }
+    }
+
+    /// Encodes data of the format as a `data:` URI
+    pub fn data_uri(&self, data: &[u8]) -> String {
+        format!(
+            "data:{};base64,{}",
+            self.content_type(),
+            base64::engine::general_purpose::STANDARD.encode(data)
+        )
+    }
+
+    /// Returns the formats acceptable for an `Accept` header value, most preferred first
+    ///
+    /// Wildcards (`*/*`, `image/*`) expand to all matching formats with PNG first,
+    /// except media types the header refuses with `q=0`.
+    pub fn accepted(accept: &str) -> Vec<OutputFormat> {
+        let values = quality_values(accept);
+        let refused: Vec<&str> = values
+            .iter()
+            .filter(|&&(media_range, quality)| quality <= 0.0 && !media_range.ends_with("/*"))
+            .map(|&(media_range, _)| media_range)
+            .collect();
+        let mut ranges: Vec<(f32, &str)> = values
+            .into_iter()
+            .filter(|&(_, quality)| quality > 0.0)
+            .map(|(media_range, quality)| (quality, media_range))
+            .collect();
+
+        // Stable sort keeps header order for equal weights
+        ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut formats = Vec::new();
+        for (_, media_range) in ranges {
+            for format in Self::ALL {
+                let content_type = format.content_type();
+                let matches = match media_range.strip_suffix("/*") {
+                    Some("*") => true,
+                    Some(kind) => content_type.starts_with(&format!("{}/", kind)),
+                    None => media_range.eq_ignore_ascii_case(content_type),
+                };
+                let is_refused = refused
+                    .iter()
+                    .any(|refused| refused.eq_ignore_ascii_case(content_type));
+                if matches && !is_refused && !formats.contains(&format) {
+                    formats.push(format);
+                }
+            }
+        }
+        formats
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+    /// Page fits the code and caption
+    #[default]
+    Fit,
+    A4,
+    A5,
+    A6,
+    Letter,
+}
+
+impl PageSize {
+    /// Returns the page width and height in millimetres (`None` for `Fit`)
+    pub fn dimensions_mm(&self) -> Option<(f32, f32)> {
+        match self {
+            PageSize::Fit => None,
+            PageSize::A4 => Some((210.0, 297.0)),
+            PageSize::A5 => Some((148.0, 210.0)),
+            PageSize::A6 => Some((105.0, 148.0)),
+            PageSize::Letter => Some((215.9, 279.4)),
+        }
+    }
+}
+
+/// Label grid of an A4 sheet, named columns x rows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum LabelGrid {
+    /// 24 labels of 70 x 37 mm (Avery 3474)
+    #[default]
+    #[serde(rename = "3x8")]
+    Grid3x8,
+    /// 21 labels of 70 x 42.3 mm (Avery 3652)
+    #[serde(rename = "3x7")]
+    Grid3x7,
+    /// 14 labels of 99.1 x 38.1 mm (Avery L7163)
+    #[serde(rename = "2x7")]
+    Grid2x7,
+    /// 8 labels of 105 x 74.25 mm (A7)
+    #[serde(rename = "2x4")]
+    Grid2x4,
+}
+
+/// Layout of a label sheet
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+pub struct LabelSheetOptions {
+    /// Label grid preset (default: 3x8)
+    #[serde(default)]
+    pub grid: LabelGrid,
+
+    /// Page margin in millimetres (0-50); labels are resized to fill the page
+    /// inside it (default: the preset's margins)
+    #[serde(default)]
+    #[validate(range(min = 0.0, max = 50.0))]
+    #[schema(minimum = 0, maximum = 50)]
+    pub margin_mm: Option<f32>,
+
+    /// Space between the label edge and its content in millimetres (0-20, default: 3)
+    #[serde(default = "default_label_padding")]
+    #[validate(range(min = 0.0, max = 20.0))]
+    #[schema(minimum = 0, maximum = 20)]
+    pub padding_mm: f32,
+
+    /// Draw cut marks at the label corners (default: false)
+    #[serde(default)]
+    pub cut_marks: bool,
+}
+
+impl Default for LabelSheetOptions {
+    fn default() -> Self {
+        Self {
+            grid: LabelGrid::default(),
+            margin_mm: None,
+            padding_mm: default_label_padding(),
+            cut_marks: false,
+        }
+    }
+}
+
+/// Position of a code stamped onto a PDF page
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+pub struct StampPlacement {
+    /// Page number, starting at 1 (default: 1)
+    #[serde(default = "default_stamp_page")]
+    #[validate(range(min = 1))]
+    #[schema(minimum = 1)]
+    pub page: u32,
+
+    /// Distance of the code's left edge from the left edge of the page in millimetres
+    #[validate(range(min = 0.0))]
+    #[schema(minimum = 0)]
+    pub x_mm: f32,
+
+    /// Distance of the code's top edge from the top edge of the page in millimetres
+    #[validate(range(min = 0.0))]
+    #[schema(minimum = 0)]
+    pub y_mm: f32,
+
+    /// Width of the whole code, including the frame, in millimetres (10-500)
+    #[validate(range(min = 10.0, max = 500.0))]
+    #[schema(minimum = 10, maximum = 500)]
+    pub size_mm: f32,
+}
+
+/// sRGB colour with alpha, written as `#RRGGBB` or `#RRGGBBAA`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(try_from = "String", into = "String")]
+#[schema(value_type = String, example = "#0066CC")]
+pub struct RgbaColor(pub [u8; 4]);
+
+impl RgbaColor {
+    pub fn black() -> Self {
+        Self([0x00, 0x00, 0x00, 0xFF])
+    }
+
+    pub fn white() -> Self {
+        Self([0xFF, 0xFF, 0xFF, 0xFF])
+    }
+
+    /// Default colour of the drawn frame
+    pub fn frame() -> Self {
+        Self([0x00, 0x66, 0xCC, 0xFF])
+    }
+
+    /// Returns the colour as `#RRGGBB`, ignoring alpha
+    pub fn rgb_hex(&self) -> String {
+        let [r, g, b, _] = self.0;
+        format!("#{:02X}{:02X}{:02X}", r, g, b)
+    }
+
+    /// Returns the alpha channel as a fraction (0.0 transparent, 1.0 opaque)
+    pub fn opacity(&self) -> f32 {
+        self.0[3] as f32 / 255.0
+    }
+
+    /// Composites the colour over an opaque backdrop
+    pub fn over(&self, backdrop: RgbaColor) -> RgbaColor {
+        let alpha = self.opacity();
+        let mut result = backdrop.0;
+        for (channel, &value) in result.iter_mut().zip(&self.0).take(3) {
+            *channel = (value as f32 * alpha + *channel as f32 * (1.0 - alpha)).round() as u8;
+        }
+        RgbaColor(result)
+    }
+
+    /// Relative luminance as defined by WCAG 2.x (alpha ignored)
+    pub fn relative_luminance(&self) -> f32 {
+        let linear = |value: u8| {
+            let c = value as f32 / 255.0;
+            if c <= 0.039_28 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let [r, g, b, _] = self.0;
+        0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+    }
+}
+
+impl fmt::Display for RgbaColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0[3] {
+            0xFF => f.write_str(&self.rgb_hex()),
+            alpha => write!(f, "{}{:02X}", self.rgb_hex(), alpha),
+        }
+    }
+}
+
+impl FromStr for RgbaColor {
+    type Err = PayBySquareError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().trim_start_matches('#');
+        let valid = matches!(hex.len(), 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+        if !valid {
+            return Err(PayBySquareError::ValidationError {
+                key: "validation.color_format",
+            });
+        }
+
+        let mut rgba = [0xFF; 4];
+        for (i, channel) in rgba.iter_mut().enumerate().take(hex.len() / 2) {
+            *channel = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap_or_default();
+        }
+        Ok(RgbaColor(rgba))
+    }
+}
+
+impl TryFrom<String> for RgbaColor {
+    type Error = PayBySquareError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<RgbaColor> for String {
+    fn from(color: RgbaColor) -> Self {
+        color.to_string()
+    }
+}
+
+/// Centre logo, written as a registered name or a `data:<type>;base64,<data>` URI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Logo {
+    /// Logo registered on the server
+    Named(String),
+    /// Encoded image (PNG, JPEG, WebP)
+    Image(Vec<u8>),
+}
+
+impl FromStr for Logo {
+    type Err = PayBySquareError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some(uri) = s.strip_prefix("data:") else {
+            return Ok(Logo::Named(s.to_string()));
+        };
+
+        let invalid = || PayBySquareError::ValidationError {
+            key: "validation.logo_format",
+        };
+        let (_, data) = uri.split_once(";base64,").ok_or_else(invalid)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(data.trim())
+            .map(Logo::Image)
+            .map_err(|_| invalid())
+    }
+}
+
+impl TryFrom<String> for Logo {
+    type Error = PayBySquareError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Logo> for String {
+    fn from(logo: Logo) -> Self {
+        match logo {
+            Logo::Named(name) => name,
+            Logo::Image(data) => {
+                let mime = image::guess_format(&data)
+                    .map(|format| format.to_mime_type())
+                    .unwrap_or("application/octet-stream");
+                format!(
+                    "data:{};base64,{}",
+                    mime,
+                    base64::engine::general_purpose::STANDARD.encode(data)
+                )
+            }
+        }
+    }
+}
+
+/// Variant of the "by square" frame drawn around the code
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameVariant {
+    /// "PAY by square" frame for payment orders
+    #[default]
+    Pay,
+    /// "INVOICE by square" frame for invoices
+    Invoice,
+}
+
+impl FrameVariant {
+    /// Returns the bold word of the frame caption
+    pub fn label(&self) -> &'static str {
+        match self {
+            FrameVariant::Pay => "PAY",
+            FrameVariant::Invoice => "INVOICE",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GenerateQuery {
+    /// Normalize IBAN, SWIFT, currency and symbols before validation (default: false)
+    #[serde(default)]
+    pub sanitize: bool,
+
+    /// Validation profile (strict, standard, lenient or a configured custom profile);
+    /// must match the profile assigned to the API key, if any
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SlipQuery {
+    /// Slip template (default or a configured template name)
+    pub template: Option<String>,
+}
+
+/// QR options passed as query parameters; they override `options` from the body
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QrOptionsQuery {
+    /// Include frame around QR code
+    pub with_frame: Option<bool>,
+
+    /// QR code size in pixels (100-2000)
+    #[param(minimum = 100, maximum = 2000)]
+    pub qr_size: Option<u32>,
+
+    /// Quiet zone around the symbol in modules (0-16)
+    #[param(minimum = 0, maximum = 16)]
+    pub quiet_zone: Option<u32>,
+
+    /// Colour of dark modules (#RRGGBB or #RRGGBBAA)
+    #[param(value_type = Option<String>)]
+    pub foreground: Option<RgbaColor>,
+
+    /// Colour of light modules and quiet zone (#RRGGBB or #RRGGBBAA)
+    #[param(value_type = Option<String>)]
+    pub background: Option<RgbaColor>,
+
+    /// Colour of the drawn frame (#RRGGBB or #RRGGBBAA)
+    #[param(value_type = Option<String>)]
+    pub frame_color: Option<RgbaColor>,
+
+    /// Output image format; overrides the Accept header
+    #[param(inline)]
+    pub format: Option<OutputFormat>,
+
+    /// Physical size of the symbol without quiet zone in millimetres (10-500)
+    #[param(minimum = 10, maximum = 500)]
+    pub size_mm: Option<f32>,
+
+    /// Print resolution of raster output in dots per inch (72-2400)
+    #[param(minimum = 72, maximum = 2400)]
+    pub dpi: Option<u32>,
+
+    /// Page size of PDF output
+    #[param(inline)]
+    pub page_size: Option<PageSize>,
+
+    /// Print payment details under the code (raster and PDF)
+    pub caption: Option<bool>,
+
+    /// Caption fields in order, comma-separated: amount, iban, vs, ks, ss, due_date
+    #[param(value_type = Option<String>)]
+    pub caption_fields: Option<CaptionFields>,
+
+    /// Language of the caption labels and number format
+    #[param(inline)]
+    pub caption_locale: Option<Locale>,
+
+    /// Caption of the drawn frame
+    #[param(inline)]
+    pub frame_variant: Option<FrameVariant>,
+
+    /// Frame template for raster output
+    pub frame_template: Option<String>,
+
+    /// Registered logo name or base64 data URI placed in the centre of the symbol
+    #[param(value_type = Option<String>)]
+    pub logo: Option<Logo>,
+
+    /// Largest QR version the symbol may use (1-40)
+    #[param(minimum = 1, maximum = 40)]
+    pub max_version: Option<u8>,
+
+    /// Scan the rendered symbol and check it decodes back to the payment
+    pub verify: Option<bool>,
+
+    /// Text printed above the code on ESC/POS receipts
+    pub receipt_header: Option<String>,
+
+    /// Text printed below the code on ESC/POS receipts
+    pub receipt_footer: Option<String>,
+}
+
+impl QrOptionsQuery {
+    /// Overrides options with the values present in the query
+    pub fn apply(&self, opts: &mut QrOptions) {
+        if let Some(with_frame) = self.with_frame {
+            opts.with_frame = with_frame;
+        }
+        if let Some(qr_size) = self.qr_size {
+            opts.qr_size = qr_size;
+        }
+        if let Some(quiet_zone) = self.quiet_zone {
+            opts.quiet_zone = quiet_zone;
+        }
+        if let Some(foreground) = self.foreground {
+            opts.foreground = foreground;
+        }
+        if let Some(background) = self.background {
+            opts.background = background;
+        }
+        if let Some(frame_color) = self.frame_color {
+            opts.frame_color = frame_color;
+        }
+        if let Some(format) = self.format {
+            opts.format = format;
+        }
+        if let Some(size_mm) = self.size_mm {
+            opts.size_mm = Some(size_mm);
+        }
+        if let Some(dpi) = self.dpi {
+            opts.dpi = Some(dpi);
+        }
+        if let Some(page_size) = self.page_size {
+            opts.page_size = page_size;
         }
+        if let Some(caption) = self.caption {
+            opts.caption = caption;
+        }
+        if let Some(ref caption_fields) = self.caption_fields {
+            opts.caption_fields = caption_fields.clone();
+        }
+        if let Some(caption_locale) = self.caption_locale {
+            opts.caption_locale = Some(caption_locale);
+        }
+        if let Some(frame_variant) = self.frame_variant {
+            opts.frame_variant = frame_variant;
+        }
+        if let Some(ref frame_template) = self.frame_template {
+            opts.frame_template = Some(frame_template.clone());
+        }
+        if let Some(ref logo) = self.logo {
+            opts.logo = Some(logo.clone());
+        }
+        if let Some(max_version) = self.max_version {
+            opts.max_version = max_version;
+        }
+        if let Some(verify) = self.verify {
+            opts.verify = verify;
+        }
+        if let Some(ref receipt_header) = self.receipt_header {
+            opts.receipt_header = Some(receipt_header.clone());
+        }
+        if let Some(ref receipt_footer) = self.receipt_footer {
+            opts.receipt_footer = Some(receipt_footer.clone());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "amount": 100.50,
+    "iban": "SK9611000000002918599669",
+    "currency": "EUR",
+    "beneficiary_name": "John Doe",
+    "variable_symbol": "1234567890",
+    "options": {
+        "with_frame": false,
+        "qr_size": 400,
+        "format": "svg"
+    }
+}))]
+pub struct GenerateQrRequest {
+    /// Payment data
+    #[serde(flatten)]
+    pub payment: PaymentRequest,
+
+    /// QR rendering options
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<QrOptions>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "payments": [
+        {"amount": 100.50, "iban": "SK9611000000002918599669", "variable_symbol": "1001"},
+        {"amount": 42.00, "iban": "SK9611000000002918599669", "variable_symbol": "1002"}
+    ],
+    "options": {"caption_fields": "amount,vs"},
+    "sheet": {"grid": "3x8", "cut_marks": true}
+}))]
+pub struct LabelSheetRequest {
+    /// Payments, one label each
+    pub payments: Vec<PaymentRequest>,
+
+    /// QR rendering options shared by all labels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<QrOptions>,
+
+    /// Sheet layout
+    #[serde(default)]
+    pub sheet: LabelSheetOptions,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "pdf": "JVBERi0xLjcK...",
+    "payment": {"amount": 100.50, "iban": "SK9611000000002918599669", "variable_symbol": "1001"},
+    "placement": {"page": 1, "x_mm": 150, "y_mm": 230, "size_mm": 40}
+}))]
+pub struct StampRequest {
+    /// PDF document, base64-encoded or as a `data:application/pdf;base64,` URI
+    pub pdf: String,
+
+    /// Payment encoded in the stamped code
+    pub payment: PaymentRequest,
+
+    /// QR rendering options
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<QrOptions>,
+
+    /// Page and position of the code
+    pub placement: StampPlacement,
+}
+
+impl StampRequest {
+    /// Decodes the PDF document
+    pub fn pdf_data(&self) -> Result<Vec<u8>, PayBySquareError> {
+        let data = match self.pdf.strip_prefix("data:") {
+            Some(uri) => uri.split_once(";base64,").map_or(uri, |(_, data)| data),
+            None => &self.pdf,
+        };
+        base64::engine::general_purpose::STANDARD
+            .decode(data.trim())
+            .map_err(|_| PayBySquareError::InvalidPdf { key: "pdf.base64" })
     }
 }
 
@@ -208,6 +942,75 @@ impl Default for QrOptions {
 pub struct CodeResponse {
     /// PayBySquare code as text string
     pub code: String,
+
+    /// Modifications applied to the input in sanitize mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<FieldChange>>,
+
+    /// Rule violations reported as warnings by the validation profile
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct QrResponse {
+    /// PayBySquare code as text string
+    pub code: String,
+
+    /// Image as a data URI, e.g. "data:image/png;base64,..."
+    pub image: String,
+
+    /// QR version of the symbol (1-40)
+    pub qr_version: i16,
+
+    /// Error correction level of the symbol: L, M, Q or H
+    #[schema(example = "M")]
+    pub ec_level: String,
+
+    /// Description of the payment for screen readers, in the language of the request
+    #[schema(
+        example = "Payment of 100.50 EUR to John Doe, IBAN SK96 1100 0000 0029 1859 9669, variable symbol 1234567890"
+    )]
+    pub alt_text: String,
+
+    /// Pixel size of raster images
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<ImageSize>,
+
+    /// Modifications applied to the input in sanitize mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<FieldChange>>,
+
+    /// Rule violations reported as warnings by the validation profile
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ImageSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldChange {
+    /// Path of the modified field (e.g. "bank_accounts[0].iban")
+    pub field: String,
+
+    /// Value as received
+    pub original: String,
+
+    /// Value after normalization
+    pub sanitized: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Warning {
+    /// Validation rule that was violated
+    pub rule: Rule,
+
+    /// Description of the violation
+    pub message: String,
 }
 
 fn default_currency() -> String {
@@ -221,3 +1024,83 @@ fn default_with_frame() -> bool {
 fn default_qr_size() -> u32 {
     300
 }
+
+fn default_quiet_zone() -> u32 {
+    4
+}
+
+fn default_max_version() -> u8 {
+    40
+}
+
+fn default_label_padding() -> f32 {
+    3.0
+}
+
+fn default_stamp_page() -> u32 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgba_color_parse() {
+        let color: RgbaColor = "#0066cc".parse().unwrap();
+        assert_eq!(color, RgbaColor::frame());
+        assert_eq!(color.to_string(), "#0066CC");
+
+        let color: RgbaColor = "#FFFFFF00".parse().unwrap();
+        assert_eq!(color.0, [0xFF, 0xFF, 0xFF, 0x00]);
+        assert_eq!(color.to_string(), "#FFFFFF00");
+
+        assert!("#12345".parse::<RgbaColor>().is_err());
+        assert!("red".parse::<RgbaColor>().is_err());
+    }
+
+    #[test]
+    fn test_logo_parse() {
+        assert_eq!("acme".parse::<Logo>().unwrap(), Logo::Named("acme".into()));
+
+        let logo: Logo = "data:image/png;base64,iVBORw0KGgo=".parse().unwrap();
+        assert_eq!(logo, Logo::Image(b"\x89PNG\r\n\x1a\n".to_vec()));
+        assert_eq!(String::from(logo), "data:image/png;base64,iVBORw0KGgo=");
+
+        assert!("data:image/png,raw".parse::<Logo>().is_err());
+        assert!("data:image/png;base64,!!".parse::<Logo>().is_err());
+    }
+
+    #[test]
+    fn test_output_format_data_uri() {
+        assert_eq!(
+            OutputFormat::Svg.data_uri(b"<svg/>"),
+            "data:image/svg+xml;base64,PHN2Zy8+"
+        );
+    }
+
+    #[test]
+    fn test_output_format_accepted() {
+        assert_eq!(
+            OutputFormat::accepted("image/webp, image/png;q=0.5"),
+            [OutputFormat::Webp, OutputFormat::Png]
+        );
+        assert_eq!(
+            OutputFormat::accepted("application/pdf;q=0.9, image/*"),
+            [
+                OutputFormat::Png,
+                OutputFormat::Jpeg,
+                OutputFormat::Webp,
+                OutputFormat::Svg,
+                OutputFormat::Pdf
+            ]
+        );
+        assert!(OutputFormat::accepted("text/html, image/gif").is_empty());
+        assert!(OutputFormat::accepted("image/png;q=0").is_empty());
+
+        // Refused types stay refused when a wildcard comes later
+        let accepted = OutputFormat::accepted("image/png;q=0, */*;q=0.1");
+        assert!(!accepted.contains(&OutputFormat::Png));
+        assert_eq!(accepted[0], OutputFormat::Jpeg);
+    }
+}
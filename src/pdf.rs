@@ -0,0 +1,362 @@
+use crate::errors::{PayBySquareError, Result};
+use crate::frame::{Contour, FrameLayout, Segment};
+use crate::logo::PlacedLogo;
+use crate::models::{QrOptions, RgbaColor};
+use crate::qr::{build_symbol, dark_runs};
+use printpdf::path::{PaintMode, WindingOrder};
+use printpdf::{
+    Color, ColorBits, ColorSpace, Image, ImageTransform, ImageXObject, IndirectFontRef, Mm,
+    PdfDocument, PdfLayerReference, Point, Polygon, Px, Rect, Rgb,
+};
+use qrcode::QrCode;
+
+/// Default physical size of the symbol (without quiet zone) in millimetres
+pub const DEFAULT_SIZE_MM: f32 = 30.0;
+
+/// Caption font size in points
+const CAPTION_FONT_SIZE: f32 = 9.0;
+
+/// Caption line height in millimetres
+const CAPTION_LINE_HEIGHT: f32 = 4.5;
+
+/// Generates a QR code as a single-page PDF with vector modules
+///
+/// The symbol is drawn at exactly `opts.size_mm` (default 30 mm) surrounded by
+/// a quiet zone of `opts.quiet_zone` modules. `caption` lines are printed under the
+/// code in the embedded Noto Sans font. Translucent colours are flattened onto
+/// the white page.
+pub fn generate_qr_pdf(code: &str, opts: &QrOptions, caption: &[String]) -> Result<Vec<u8>> {
+    let (qr, logo) = build_symbol(code, opts)?;
+    render_pdf(&qr, logo.as_ref(), opts, caption)
+}
+
+/// Renders an encoded symbol as a PDF page, see [`generate_qr_pdf`]
+pub(crate) fn render_pdf(
+    qr: &QrCode,
+    logo: Option<&PlacedLogo>,
+    opts: &QrOptions,
+    caption: &[String],
+) -> Result<Vec<u8>> {
+    let size_mm = opts.size_mm.unwrap_or(DEFAULT_SIZE_MM);
+    let layout = CodeLayout::new(qr, opts, size_mm / qr.width() as f32);
+    let content_width = layout.width;
+    let caption_height = if caption.is_empty() {
+        0.0
+    } else {
+        (caption.len() as f32 + 0.5) * CAPTION_LINE_HEIGHT
+    };
+    let content_height = content_width + caption_height;
+
+    let (page_width, page_height) = opts
+        .page_size
+        .dimensions_mm()
+        .unwrap_or((content_width, content_height));
+    if page_width < content_width || page_height < content_height {
+        // The content width grows linearly with the symbol size, the caption doesn't
+        let fit = (page_width / content_width).min((page_height - caption_height) / content_width);
+        return Err(PayBySquareError::OptionOutOfRange {
+            option: "size_mm".to_string(),
+            min: 10.0,
+            max: (size_mm * fit * 10.0).floor() / 10.0,
+        });
+    }
+
+    let (doc, page, layer) =
+        PdfDocument::new("PAY by square", Mm(page_width), Mm(page_height), "QR");
+    let layer = doc.get_page(page).get_layer(layer);
+
+    // Content is centered on the page; PDF origin is the bottom-left corner
+    let left = (page_width - content_width) / 2.0;
+    let top = (page_height + content_height) / 2.0;
+    let (symbol_left, _) = draw_code(&layer, qr, logo, opts, &layout, left, top);
+
+    if !caption.is_empty() {
+        let font = embed_font(&doc)?;
+        layer.set_fill_color(pdf_color(opts.foreground));
+        for (i, line) in caption.iter().enumerate() {
+            let baseline = top - content_width - (i as f32 + 1.0) * CAPTION_LINE_HEIGHT;
+            layer.use_text(
+                line,
+                CAPTION_FONT_SIZE,
+                Mm(symbol_left),
+                Mm(baseline),
+                &font,
+            );
+        }
+    }
+
+    doc.save_to_bytes()
+        .map_err(|e| PayBySquareError::PdfError(e.to_string()))
+}
+
+/// Physical layout of a symbol with its quiet zone, optionally inside the frame
+pub(crate) struct CodeLayout {
+    /// Module size in millimetres
+    pub module: f32,
+    /// Symbol including the quiet zone in millimetres
+    pub symbol: f32,
+    pub frame: Option<FrameLayout>,
+    /// Width and height of the whole code in millimetres
+    pub width: f32,
+}
+
+impl CodeLayout {
+    /// Lays out a symbol with modules of `module` millimetres
+    pub fn new(qr: &QrCode, opts: &QrOptions, module: f32) -> Self {
+        let symbol = (qr.width() as u32 + 2 * opts.quiet_zone) as f32 * module;
+        let frame = opts.with_frame.then(|| {
+            // Frame proportions are linear, so the unit layout gives the symbol share
+            let unit = FrameLayout::new(1.0, opts.frame_variant);
+            FrameLayout::new(symbol / unit.qr_size, opts.frame_variant)
+        });
+        let width = frame.as_ref().map_or(symbol, |frame| frame.size);
+        Self {
+            module,
+            symbol,
+            frame,
+            width,
+        }
+    }
+
+    /// Lays out a symbol so the whole code is `width` millimetres wide
+    pub fn fit(qr: &QrCode, opts: &QrOptions, width: f32) -> Self {
+        let unit = Self::new(qr, opts, 1.0);
+        Self::new(qr, opts, width / unit.width)
+    }
+}
+
+/// Draws the code with its top-left corner at `(left, top)`
+///
+/// Returns the top-left corner of the symbol's quiet zone.
+pub(crate) fn draw_code(
+    layer: &PdfLayerReference,
+    qr: &QrCode,
+    logo: Option<&PlacedLogo>,
+    opts: &QrOptions,
+    layout: &CodeLayout,
+    left: f32,
+    top: f32,
+) -> (f32, f32) {
+    let (module, symbol) = (layout.module, layout.symbol);
+    let quiet_zone = opts.quiet_zone as usize;
+
+    let (symbol_left, symbol_top) = match layout.frame {
+        Some(ref frame) => {
+            layer.set_fill_color(pdf_color(opts.frame_color));
+            layer.add_polygon(Polygon {
+                rings: frame
+                    .contours
+                    .iter()
+                    .map(|contour| ring(contour, left, top))
+                    .collect(),
+                mode: PaintMode::Fill,
+                winding_order: WindingOrder::NonZero,
+            });
+            (left + frame.qr_x, top - frame.qr_y)
+        }
+        None => (left, top),
+    };
+
+    // Quiet zone background and modules
+    fill_rect(
+        layer,
+        symbol_left,
+        symbol_top - symbol,
+        symbol,
+        symbol,
+        pdf_color(opts.background),
+    );
+
+    layer.set_fill_color(pdf_color(opts.foreground));
+    for (x, y, length) in dark_runs(qr, logo.map(|logo| &logo.area)) {
+        let llx = symbol_left + (x + quiet_zone) as f32 * module;
+        let ury = symbol_top - (y + quiet_zone) as f32 * module;
+        layer.add_rect(Rect::new(
+            Mm(llx),
+            Mm(ury - module),
+            Mm(llx + length as f32 * module),
+            Mm(ury),
+        ));
+    }
+
+    if let Some(logo) = logo {
+        let (x, y, width, height) = logo.bounds();
+        add_logo(
+            layer,
+            logo,
+            opts.background,
+            symbol_left + (x + quiet_zone as f32) * module,
+            symbol_top - (y + height + quiet_zone as f32) * module,
+            width * module,
+        );
+    }
+    (symbol_left, symbol_top)
+}
+
+/// Converts a colour to an opaque PDF colour as seen on a white page
+pub(crate) fn pdf_color(color: RgbaColor) -> Color {
+    let [r, g, b, _] = color.over(RgbaColor::white()).0.map(|c| c as f32 / 255.0);
+    Color::Rgb(Rgb::new(r, g, b, None))
+}
+
+/// Places the logo with its lower-left corner at `(x, y)`, scaled to `width` millimetres
+///
+/// Transparency is flattened onto the background as seen on the white page.
+fn add_logo(
+    layer: &PdfLayerReference,
+    logo: &PlacedLogo,
+    background: RgbaColor,
+    x: f32,
+    y: f32,
+    width: f32,
+) {
+    let backdrop = background.over(RgbaColor::white());
+    let image_data = logo
+        .image
+        .pixels()
+        .flat_map(|pixel| {
+            let [r, g, b, _] = RgbaColor(pixel.0).over(backdrop).0;
+            [r, g, b]
+        })
+        .collect();
+    let (image_width, image_height) = logo.image.dimensions();
+
+    Image::from(ImageXObject {
+        width: Px(image_width as usize),
+        height: Px(image_height as usize),
+        color_space: ColorSpace::Rgb,
+        bits_per_component: ColorBits::Bit8,
+        interpolate: true,
+        image_data,
+        image_filter: None,
+        smask: None,
+        clipping_bbox: None,
+    })
+    .add_to_layer(
+        layer.clone(),
+        ImageTransform {
+            translate_x: Some(Mm(x)),
+            translate_y: Some(Mm(y)),
+            // Pixels per inch that make the image `width` millimetres wide
+            dpi: Some(image_width as f32 * 25.4 / width),
+            ..Default::default()
+        },
+    );
+}
+
+fn fill_rect(layer: &PdfLayerReference, x: f32, y: f32, width: f32, height: f32, color: Color) {
+    layer.set_fill_color(color);
+    layer.add_rect(Rect::new(Mm(x), Mm(y), Mm(x + width), Mm(y + height)));
+}
+
+/// Converts a frame contour to a PDF ring anchored at the top-left corner of the content
+fn ring(contour: &Contour, left: f32, top: f32) -> Vec<(Point, bool)> {
+    let point = |p: ab_glyph::Point| Point::new(Mm(left + p.x), Mm(top - p.y));
+
+    // printpdf draws a cubic curve when a point and the following control point are flagged
+    let mut ring = vec![(point(contour.start), false)];
+    let cubic = |ring: &mut Vec<(Point, bool)>, c0, c1, p| {
+        if let Some(last) = ring.last_mut() {
+            last.1 = true;
+        }
+        ring.extend([(point(c0), true), (point(c1), false), (point(p), false)]);
+    };
+
+    let mut from = contour.start;
+    for segment in &contour.segments {
+        match *segment {
+            Segment::Line(p) => ring.push((point(p), false)),
+            Segment::Quad(c, p) => {
+                // Degree elevation of the quadratic curve
+                let towards = |a: ab_glyph::Point| {
+                    ab_glyph::point(a.x + (c.x - a.x) * 2.0 / 3.0, a.y + (c.y - a.y) * 2.0 / 3.0)
+                };
+                cubic(&mut ring, towards(from), towards(p), p);
+            }
+            Segment::Cubic(c0, c1, p) => cubic(&mut ring, c0, c1, p),
+        }
+        from = segment.end();
+    }
+    ring
+}
+
+pub(crate) fn embed_font(doc: &printpdf::PdfDocumentReference) -> Result<IndirectFontRef> {
+    doc.add_external_font(notosans::REGULAR_TTF)
+        .map_err(|e| PayBySquareError::PdfError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{OutputFormat, PageSize};
+    use actix_web::ResponseError;
+
+    fn options() -> QrOptions {
+        QrOptions {
+            with_frame: false,
+            format: OutputFormat::Pdf,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_generate_qr_pdf() {
+        let pdf = generate_qr_pdf("TEST", &options(), &[]).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+
+        // 30 mm symbol + 2 x 4 modules of a 21-module symbol = 41.43 mm = 117.43 pt
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/MediaBox[0 0 117.43"));
+    }
+
+    #[test]
+    fn test_generate_qr_pdf_embeds_caption_font() {
+        let caption = ["Amount: 100.50 EUR".to_string()];
+        let pdf = generate_qr_pdf("TEST", &options(), &caption).unwrap();
+        assert!(String::from_utf8_lossy(&pdf).contains("/FontFile2"));
+    }
+
+    #[test]
+    fn test_generate_qr_pdf_with_frame() {
+        let opts = QrOptions {
+            with_frame: true,
+            ..options()
+        };
+        let framed = generate_qr_pdf("TEST", &opts, &[]).unwrap();
+        let text = String::from_utf8_lossy(&framed);
+        // Frame is wider than the bare symbol
+        let width: f32 = text
+            .split("/MediaBox[0 0 ")
+            .nth(1)
+            .and_then(|rest| rest.split(' ').next())
+            .and_then(|width| width.parse().ok())
+            .unwrap();
+        assert!(width > 117.43 && width < 150.0);
+    }
+
+    #[test]
+    fn test_generate_qr_pdf_rejects_small_page() {
+        let opts = QrOptions {
+            size_mm: Some(200.0),
+            page_size: PageSize::A6,
+            ..options()
+        };
+        let err = generate_qr_pdf("TEST", &opts, &[]).unwrap_err();
+        assert!(matches!(
+            err,
+            PayBySquareError::OptionOutOfRange { ref option, max, .. }
+                if option == "size_mm" && max < 105.0
+        ));
+        assert_eq!(err.status_code(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        // The largest size reported fits
+        let PayBySquareError::OptionOutOfRange { max, .. } = err else {
+            unreachable!()
+        };
+        let opts = QrOptions {
+            size_mm: Some(max),
+            ..opts
+        };
+        assert!(generate_qr_pdf("TEST", &opts, &[]).is_ok());
+    }
+}
@@ -0,0 +1,255 @@
+use crate::errors::{PayBySquareError, Result};
+use crate::validation::Rule;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use utoipa::ToSchema;
+
+/// How a validation rule is enforced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    /// Violation fails the request
+    Error,
+    /// Violation is reported but the code is still generated
+    Warning,
+    /// Rule is not checked
+    Off,
+}
+
+/// Named set of rule levels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationProfile {
+    pub name: String,
+    pub rules: BTreeMap<Rule, RuleLevel>,
+}
+
+impl ValidationProfile {
+    /// All rules enforced, including due date and numeric symbols
+    pub fn strict() -> Self {
+        Self::with_levels("strict", |_| RuleLevel::Error)
+    }
+
+    /// Default behaviour: format and length rules enforced
+    pub fn standard() -> Self {
+        Self::with_levels("standard", |rule| match rule {
+            Rule::DueDate | Rule::NumericSymbols => RuleLevel::Off,
+            _ => RuleLevel::Error,
+        })
+    }
+
+    /// Only amount and bank account are enforced, format problems are warnings
+    pub fn lenient() -> Self {
+        Self::with_levels("lenient", |rule| match rule {
+            Rule::Amount | Rule::BankAccount => RuleLevel::Error,
+            Rule::Iban | Rule::Swift | Rule::FieldLength => RuleLevel::Warning,
+            Rule::DueDate | Rule::NumericSymbols => RuleLevel::Off,
+        })
+    }
+
+    /// Returns a built-in profile by name
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "strict" => Some(Self::strict()),
+            "standard" => Some(Self::standard()),
+            "lenient" => Some(Self::lenient()),
+            _ => None,
+        }
+    }
+
+    /// Returns the level of a rule (rules missing from the profile are off)
+    pub fn level(&self, rule: Rule) -> RuleLevel {
+        self.rules.get(&rule).copied().unwrap_or(RuleLevel::Off)
+    }
+
+    fn with_levels(name: &str, level: impl Fn(Rule) -> RuleLevel) -> Self {
+        Self {
+            name: name.to_string(),
+            rules: Rule::ALL.iter().map(|&rule| (rule, level(rule))).collect(),
+        }
+    }
+}
+
+/// Validation profiles and API key assignments
+#[derive(Debug, Clone)]
+pub struct ProfileRegistry {
+    profiles: HashMap<String, ValidationProfile>,
+    api_keys: HashMap<String, String>,
+    default_profile: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistryConfig {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: HashMap<String, ProfileConfig>,
+    #[serde(default)]
+    api_keys: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileConfig {
+    /// Built-in profile used as base (default: standard)
+    extends: Option<String>,
+    #[serde(default)]
+    rules: BTreeMap<Rule, RuleLevel>,
+}
+
+impl Default for ProfileRegistry {
+    fn default() -> Self {
+        let profiles = [
+            ValidationProfile::strict(),
+            ValidationProfile::standard(),
+            ValidationProfile::lenient(),
+        ]
+        .into_iter()
+        .map(|profile| (profile.name.clone(), profile))
+        .collect();
+
+        Self {
+            profiles,
+            api_keys: HashMap::new(),
+            default_profile: "standard".to_string(),
+        }
+    }
+}
+
+impl ProfileRegistry {
+    /// Loads custom profiles from a TOML file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_toml(&content)
+    }
+
+    /// Parses custom profiles from TOML, on top of the built-in ones
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let config: RegistryConfig =
+            toml::from_str(content).map_err(|e| PayBySquareError::ConfigError(e.to_string()))?;
+
+        let mut registry = Self::default();
+
+        for (name, profile_config) in config.profiles {
+            let base = profile_config.extends.as_deref().unwrap_or("standard");
+            let mut profile = ValidationProfile::builtin(base).ok_or_else(|| {
+                PayBySquareError::ConfigError(format!(
+                    "profile '{}' extends unknown profile '{}'",
+                    name, base
+                ))
+            })?;
+            profile.name = name.clone();
+            profile.rules.extend(profile_config.rules);
+            registry.profiles.insert(name, profile);
+        }
+
+        for (key, profile) in &config.api_keys {
+            if !registry.profiles.contains_key(profile) {
+                return Err(PayBySquareError::ConfigError(format!(
+                    "API key '{}' refers to unknown profile '{}'",
+                    key, profile
+                )));
+            }
+        }
+        registry.api_keys = config.api_keys;
+
+        if let Some(default_profile) = config.default_profile {
+            registry.get(&default_profile)?;
+            registry.default_profile = default_profile;
+        }
+
+        Ok(registry)
+    }
+
+    /// Returns a profile by name
+    pub fn get(&self, name: &str) -> Result<&ValidationProfile> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| PayBySquareError::UnknownProfile(name.to_string()))
+    }
+
+    /// Selects the profile for a request: the API key's profile, then explicit name, then default
+    ///
+    /// A client whose API key is assigned a profile can't request a different
+    /// one, so it can't opt out of the rules it is held to.
+    pub fn resolve(
+        &self,
+        requested: Option<&str>,
+        api_key: Option<&str>,
+    ) -> Result<&ValidationProfile> {
+        let assigned = api_key
+            .and_then(|key| self.api_keys.get(key))
+            .map(String::as_str);
+        let name = match (assigned, requested) {
+            (Some(assigned), Some(requested)) if requested != assigned => {
+                return Err(PayBySquareError::ProfileNotAllowed(requested.to_string()));
+            }
+            (Some(assigned), _) => assigned,
+            (None, requested) => requested.unwrap_or(&self.default_profile),
+        };
+        self.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default_profile = "eshop"
+
+[profiles.eshop]
+extends = "strict"
+rules = { swift = "warning" }
+
+[profiles.erp]
+extends = "lenient"
+rules = { iban = "off" }
+
+[api_keys]
+"erp-key" = "erp"
+"strict-key" = "strict"
+"#;
+
+    #[test]
+    fn test_from_toml() {
+        let registry = ProfileRegistry::from_toml(CONFIG).unwrap();
+
+        let eshop = registry.resolve(None, None).unwrap();
+        assert_eq!(eshop.name, "eshop");
+        assert_eq!(eshop.level(Rule::DueDate), RuleLevel::Error);
+        assert_eq!(eshop.level(Rule::Swift), RuleLevel::Warning);
+
+        let erp = registry.resolve(None, Some("erp-key")).unwrap();
+        assert_eq!(erp.level(Rule::Iban), RuleLevel::Off);
+        assert_eq!(erp.level(Rule::Swift), RuleLevel::Warning);
+
+        let lenient = registry
+            .resolve(Some("lenient"), Some("unknown-key"))
+            .unwrap();
+        assert_eq!(lenient.name, "lenient");
+    }
+
+    #[test]
+    fn test_api_key_profile_wins() {
+        let registry = ProfileRegistry::from_toml(CONFIG).unwrap();
+
+        // The key's own profile may be named, any other is refused
+        let strict = registry
+            .resolve(Some("strict"), Some("strict-key"))
+            .unwrap();
+        assert_eq!(strict.name, "strict");
+        assert!(matches!(
+            registry.resolve(Some("lenient"), Some("strict-key")),
+            Err(PayBySquareError::ProfileNotAllowed(name)) if name == "lenient"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let registry = ProfileRegistry::default();
+        assert!(matches!(
+            registry.resolve(Some("missing"), None),
+            Err(PayBySquareError::UnknownProfile(_))
+        ));
+        assert!(ProfileRegistry::from_toml("[api_keys]\nk = \"missing\"").is_err());
+    }
+}
@@ -1,32 +1,233 @@
 use crate::errors::{PayBySquareError, Result};
-use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba, RgbaImage};
-use qrcode::QrCode;
+use crate::frame::{fill_contours, framed_size_for, render_framed_symbol, Contour};
+use crate::logo::{place_logo, LogoArea, PlacedLogo};
+use crate::models::{QrOptions, RgbaColor};
+use crate::templates::FrameTemplate;
+use crate::text::{outline_text, TextRun};
+use crate::validation::validate_print_resolution;
+use ab_glyph::point;
+use image::codecs::jpeg::{JpegEncoder, PixelDensity};
+use image::{imageops, ImageBuffer, Rgb, RgbImage, Rgba, RgbaImage};
+use qrcode::{Color, EcLevel, QrCode, Version};
+
+/// Quiet zone around the symbol in modules
+pub(crate) const QUIET_ZONE: usize = 4;
+
+/// Caption em size relative to the image width
+const CAPTION_EM_RATIO: f32 = 0.045;
+
+/// Caption line height relative to the em size
+const CAPTION_LINE_HEIGHT: f32 = 1.4;
+
+/// Space above and below the caption lines relative to the em size
+const CAPTION_PADDING: f32 = 0.5;
+
+/// Encodes a code string into a QR symbol
+pub(crate) fn build_qr_code(code: &str) -> Result<QrCode> {
+    QrCode::new(code.as_bytes()).map_err(|e| PayBySquareError::QrError(e.to_string()))
+}
+
+/// Encodes a code string with the error correction level for `opts`
+///
+/// With a logo the symbol uses error correction level H. Symbols above
+/// `opts.max_version` are refused.
+pub(crate) fn encode_symbol(code: &str, opts: &QrOptions) -> Result<QrCode> {
+    let qr = match opts.logo {
+        Some(_) => QrCode::with_error_correction_level(code.as_bytes(), EcLevel::H)
+            .map_err(|e| PayBySquareError::QrError(e.to_string()))?,
+        None => build_qr_code(code)?,
+    };
+
+    let version = match qr.version() {
+        Version::Normal(version) | Version::Micro(version) => version,
+    };
+    if version > opts.max_version as i16 {
+        return Err(PayBySquareError::VersionTooHigh {
+            version,
+            max: opts.max_version,
+        });
+    }
+    Ok(qr)
+}
+
+/// Encodes a code string for rendering with `opts` and places the logo in its centre
+pub(crate) fn build_symbol(code: &str, opts: &QrOptions) -> Result<(QrCode, Option<PlacedLogo>)> {
+    let qr = encode_symbol(code, opts)?;
+    let logo = opts
+        .logo
+        .as_ref()
+        .map(|logo| place_logo(&qr, logo))
+        .transpose()?;
+    Ok((qr, logo))
+}
+
+/// Returns horizontal runs of dark modules as `(x, y, length)` in module units
+///
+/// Modules inside `cleared` are left out.
+pub(crate) fn dark_runs(qr: &QrCode, cleared: Option<&LogoArea>) -> Vec<(usize, usize, usize)> {
+    let width = qr.width();
+    let colors = qr.to_colors();
+    let mut runs = Vec::new();
+    let is_dark = |x: usize, y: usize, color: Color| {
+        color == Color::Dark && !cleared.is_some_and(|area| area.contains(x, y))
+    };
+
+    for (y, row) in colors.chunks(width).enumerate() {
+        let mut x = 0;
+        while x < width {
+            if !is_dark(x, y, row[x]) {
+                x += 1;
+                continue;
+            }
+
+            let start = x;
+            while x < width && is_dark(x, y, row[x]) {
+                x += 1;
+            }
+            runs.push((start, y, x - start));
+        }
+    }
+
+    runs
+}
 
 /// Generates a QR code image from a code string
 pub fn generate_qr_image(code: &str, size: u32) -> Result<Vec<u8>> {
-    // Generate QR code
-    let qr = QrCode::new(code.as_bytes()).map_err(|e| PayBySquareError::QrError(e.to_string()))?;
+    let opts = QrOptions {
+        qr_size: size,
+        quiet_zone: QUIET_ZONE as u32,
+        ..Default::default()
+    };
+    generate_qr_image_with_options(code, &opts)
+}
 
-    // Convert to image
-    let qr_image = qr.render::<image::Luma<u8>>().build();
+/// Generates a QR code image with the size, quiet zone and colours of `opts`
+///
+/// See [`render_qr`] for how the requested size is met.
+pub fn generate_qr_image_with_options(code: &str, opts: &QrOptions) -> Result<Vec<u8>> {
+    encode_image(&render_qr_image(code, opts)?, image::ImageFormat::Png)
+}
 
-    // Resize to desired size
-    let resized =
-        image::imageops::resize(&qr_image, size, size, image::imageops::FilterType::Nearest);
+/// Renders a QR code image with the options of `opts` without encoding it
+pub fn render_qr_image(code: &str, opts: &QrOptions) -> Result<RgbaImage> {
+    let (qr, logo) = build_symbol(code, opts)?;
+    Ok(render_qr(&qr, opts.qr_size, opts, logo.as_ref()))
+}
 
-    // Convert to RGBA for consistency
-    let rgba_image = DynamicImage::ImageLuma8(resized).to_rgba8();
+/// Renders an encoded symbol as raster output draws it, before the caption
+///
+/// With `opts.with_frame` the symbol is placed into `template` when given,
+/// otherwise into the drawn frame.
+pub(crate) fn render_symbol(
+    qr: &QrCode,
+    logo: Option<&PlacedLogo>,
+    opts: &QrOptions,
+    template: Option<&FrameTemplate>,
+) -> Result<RgbaImage> {
+    match (opts.with_frame, template) {
+        (true, Some(template)) => template.render_symbol(qr, logo, opts),
+        (true, None) => Ok(render_framed_symbol(qr, logo, opts)),
+        (false, _) => Ok(render_qr(qr, opts.qr_size, opts, logo)),
+    }
+}
 
-    // Encode to PNG
-    let mut png_data = Vec::new();
-    rgba_image
-        .write_to(
-            &mut std::io::Cursor::new(&mut png_data),
-            image::ImageFormat::Png,
-        )
-        .map_err(|e| PayBySquareError::ImageError(e.to_string()))?;
+/// Largest pixel size of raster output sized for print
+const MAX_PRINT_PIXELS: u32 = 2000;
+
+/// Sizes raster output for printing at `opts.dpi`
+///
+/// With `opts.size_mm` the module size is rounded to whole pixels and
+/// `qr_size` is set so the symbol gets it, in the drawn frame or a template
+/// scaled to fit. The returned resolution is the requested one adjusted so the
+/// symbol measures exactly `size_mm`. Without `size_mm` the options are kept
+/// and the resolution is returned as requested.
+pub(crate) fn print_options(
+    qr: &QrCode,
+    opts: &QrOptions,
+    template: Option<&FrameTemplate>,
+) -> Result<(QrOptions, Option<f32>)> {
+    let (dpi, size_mm) = match (opts.dpi, opts.size_mm) {
+        (None, _) => return Ok((opts.clone(), None)),
+        (Some(dpi), None) => return Ok((opts.clone(), Some(dpi as f32))),
+        (Some(dpi), Some(size_mm)) => (dpi as f32, size_mm),
+    };
+
+    let width = qr.width() as u32;
+    let dimension = width + 2 * opts.quiet_zone;
+    let mut module = ((size_mm / 25.4 * dpi / width as f32).round() as u32).max(1);
+    let qr_size = match (opts.with_frame, template) {
+        (false, _) => module * dimension,
+        (true, None) => framed_size_for(module * dimension, opts.frame_variant),
+        (true, Some(template)) => match template.fixed_symbol_size() {
+            Some(size) => {
+                module = (size / dimension).max(1);
+                opts.qr_size
+            }
+            None => module * dimension,
+        },
+    };
+    if qr_size > MAX_PRINT_PIXELS {
+        return Err(PayBySquareError::PrintSizeTooLarge {
+            pixels: qr_size,
+            max: MAX_PRINT_PIXELS,
+        });
+    }
+
+    let opts = QrOptions {
+        qr_size,
+        ..opts.clone()
+    };
+    Ok((opts, Some((module * width) as f32 * 25.4 / size_mm)))
+}
+
+/// Renders a symbol with square modules of a whole number of pixels
+///
+/// The module size is the largest that fits `size`; the remaining pixels are
+/// added as background border so the image is exactly `size` wide. When one
+/// pixel per module doesn't fit, the image is as large as the symbol instead.
+/// Quiet zone and colours are taken from `opts`; a logo is drawn over its
+/// cleared area.
+pub(crate) fn render_qr(
+    qr: &QrCode,
+    size: u32,
+    opts: &QrOptions,
+    logo: Option<&PlacedLogo>,
+) -> RgbaImage {
+    let quiet_zone = opts.quiet_zone;
+    let dimension = qr.width() as u32 + 2 * quiet_zone;
+    let module = (size / dimension).max(1);
+    let side = size.max(dimension * module);
+    let offset = (side - dimension * module) / 2 + quiet_zone * module;
+
+    let mut img = RgbaImage::from_pixel(side, side, Rgba(opts.background.0));
+    let foreground = Rgba(opts.foreground.0);
+    for (x, y, length) in dark_runs(qr, logo.map(|logo| &logo.area)) {
+        let left = offset + x as u32 * module;
+        let top = offset + y as u32 * module;
+        for py in top..top + module {
+            for px in left..left + length as u32 * module {
+                img.put_pixel(px, py, foreground);
+            }
+        }
+    }
 
-    Ok(png_data)
+    if let Some(logo) = logo {
+        let module = module as f32;
+        let (x, y, width, height) = logo.bounds();
+        let resized = imageops::resize(
+            &logo.image,
+            ((width * module).round() as u32).max(1),
+            ((height * module).round() as u32).max(1),
+            imageops::FilterType::Lanczos3,
+        );
+        imageops::overlay(
+            &mut img,
+            &resized,
+            (offset as f32 + x * module).round() as i64,
+            (offset as f32 + y * module).round() as i64,
+        );
+    }
+    img
 }
 
 /// Adds a frame around the QR code
@@ -41,44 +242,238 @@ pub fn add_frame(qr_data: Vec<u8>, frame_data: Option<&[u8]>) -> Result<Vec<u8>>
     let qr_img = image::load_from_memory(&qr_data)
         .map_err(|e| PayBySquareError::ImageError(format!("Failed to load QR image: {}", e)))?;
 
-    // Load frame image
-    let frame_img = image::load_from_memory(frame_bytes)
-        .map_err(|e| PayBySquareError::ImageError(format!("Failed to load frame image: {}", e)))?;
+    // Symbol centered at 85% of the frame size
+    let framed = FrameTemplate::from_image("frame", frame_bytes)?.compose(
+        &qr_img.to_rgba8(),
+        1.0,
+        &QrOptions::default(),
+    )?;
+    encode_image(&framed, image::ImageFormat::Png)
+}
 
-    // Calculate QR position (centered at 85% of frame size)
-    let frame_width = frame_img.width();
-    let frame_height = frame_img.height();
+/// Adds caption lines under a PNG image
+///
+/// See [`draw_caption`].
+pub fn add_caption(image_data: Vec<u8>, lines: &[String], opts: &QrOptions) -> Result<Vec<u8>> {
+    if lines.is_empty() {
+        return Ok(image_data);
+    }
 
-    let target_qr_size = ((frame_width.min(frame_height) as f32) * 0.85) as u32;
+    let img = image::load_from_memory(&image_data)
+        .map_err(|e| PayBySquareError::ImageError(format!("Failed to load QR image: {}", e)))?
+        .to_rgba8();
+    encode_image(&draw_caption(img, lines, opts), image::ImageFormat::Png)
+}
 
-    // Resize QR to fit in frame
-    let qr_resized = image::imageops::resize(
-        &qr_img,
-        target_qr_size,
-        target_qr_size,
-        image::imageops::FilterType::Nearest,
-    );
+/// Adds caption lines under an image
+///
+/// The image is extended downwards with the background colour and every line is
+/// centered in the foreground colour, set in the embedded Noto Sans so the output
+/// doesn't depend on installed fonts. The text shrinks when the longest line
+/// doesn't fit the width.
+pub fn draw_caption(img: RgbaImage, lines: &[String], opts: &QrOptions) -> RgbaImage {
+    if lines.is_empty() {
+        return img;
+    }
+    let (width, height) = img.dimensions();
 
-    // Calculate center position
-    let x_offset = (frame_width - target_qr_size) / 2;
-    let y_offset = (frame_height - target_qr_size) / 2;
+    let outline = |em: f32| -> Vec<_> {
+        lines
+            .iter()
+            .map(|line| outline_text(&[TextRun::regular(line)], em))
+            .collect()
+    };
+    let mut em = width as f32 * CAPTION_EM_RATIO;
+    let mut texts = outline(em);
+    let widest = texts.iter().map(|text| text.width).fold(0.0, f32::max);
+    let available = width as f32 - 2.0 * em;
+    if widest > available {
+        em *= available / widest;
+        texts = outline(em);
+    }
 
-    // Create a new image with the frame
-    let mut result = frame_img.to_rgba8();
+    let line_height = em * CAPTION_LINE_HEIGHT;
+    let padding = em * CAPTION_PADDING;
+    let caption_height = (2.0 * padding + lines.len() as f32 * line_height).ceil() as u32;
 
-    // Overlay QR code on frame
-    image::imageops::overlay(&mut result, &qr_resized, x_offset as i64, y_offset as i64);
+    let mut result = RgbaImage::from_pixel(width, height + caption_height, Rgba(opts.background.0));
+    imageops::replace(&mut result, &img, 0, 0);
 
-    // Encode to PNG
-    let mut png_data = Vec::new();
+    // Baselines sit at the same place in every line box, leaving room for descenders
+    let contours: Vec<Contour> = texts
+        .iter()
+        .enumerate()
+        .flat_map(|(i, text)| {
+            let left = (width as f32 - text.width) / 2.0;
+            let baseline = height as f32 + padding + (i as f32 + 0.75) * line_height;
+            text.contours
+                .iter()
+                .map(move |c| c.map(|p| point(p.x + left, p.y + baseline)))
+        })
+        .collect();
+    fill_contours(&mut result, &contours, opts.foreground);
     result
-        .write_to(
-            &mut std::io::Cursor::new(&mut png_data),
-            image::ImageFormat::Png,
-        )
-        .map_err(|e| PayBySquareError::ImageError(e.to_string()))?;
+}
+
+/// Re-encodes a PNG image into another raster format
+pub fn convert_image(png_data: &[u8], format: image::ImageFormat) -> Result<Vec<u8>> {
+    let img = image::load_from_memory(png_data)
+        .map_err(|e| PayBySquareError::ImageError(format!("Failed to load QR image: {}", e)))?;
+    encode_image(&img.to_rgba8(), format)
+}
+
+/// Encodes an image in a raster format
+///
+/// JPEG has no alpha channel, so translucent pixels are flattened onto white.
+/// PNG goes through [`encode_png`] for compact, deterministic output.
+pub fn encode_image(img: &RgbaImage, format: image::ImageFormat) -> Result<Vec<u8>> {
+    encode_image_with_dpi(img, format, None)
+}
+
+/// Encodes an image in a raster format with a print resolution
+///
+/// The resolution is written as pHYs in PNG, rounded to whole pixels per
+/// metre, and as JFIF density in JPEG, rounded to whole dots per inch; WebP
+/// output carries none. Resolutions that don't round to 1-65535 dpi are
+/// rejected. See [`encode_image`].
+pub fn encode_image_with_dpi(
+    img: &RgbaImage,
+    format: image::ImageFormat,
+    dpi: Option<f32>,
+) -> Result<Vec<u8>> {
+    if let Some(dpi) = dpi {
+        validate_print_resolution(dpi)?;
+    }
+    if format == image::ImageFormat::Png {
+        return encode_png(img, dpi);
+    }
+    let mut data = Vec::new();
+    let mut writer = std::io::Cursor::new(&mut data);
+    let result = if format == image::ImageFormat::Jpeg {
+        let rgb: RgbImage = ImageBuffer::from_fn(img.width(), img.height(), |x, y| {
+            let [r, g, b, _] = RgbaColor(img.get_pixel(x, y).0).over(RgbaColor::white()).0;
+            Rgb([r, g, b])
+        });
+        let mut encoder = JpegEncoder::new(&mut writer);
+        if let Some(dpi) = dpi {
+            encoder.set_pixel_density(PixelDensity::dpi(dpi.round() as u16));
+        }
+        encoder.encode_image(&rgb)
+    } else {
+        img.write_to(&mut writer, format)
+    };
+    result.map_err(|e| PayBySquareError::ImageError(e.to_string()))?;
+
+    Ok(data)
+}
+
+/// Encodes an image as PNG in the smallest colour type that holds it losslessly
+///
+/// Opaque black-and-white images become 1-bit grayscale and images with at
+/// most 256 colours a palette of 1, 2, 4 or 8 bits, anything else RGB or RGBA.
+/// Deflate runs at maximum level and no time or text chunks are written, so
+/// the same pixels always give the same bytes. A resolution is written as pHYs.
+fn encode_png(img: &RgbaImage, dpi: Option<f32>) -> Result<Vec<u8>> {
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    let (width, height) = img.dimensions();
+    let palette = image_palette(img);
+    let opaque = img.pixels().all(|p| p[3] == 255);
+
+    let (color, depth, pixels) = match &palette {
+        Some(colors) if colors.iter().all(|c| *c == BLACK || *c == WHITE) => {
+            let bits = pack_bits(img, 1, |p| (p.0 == WHITE) as u8);
+            (png::ColorType::Grayscale, png::BitDepth::One, bits)
+        }
+        Some(colors) => {
+            let depth = match colors.len() {
+                0..=2 => 1,
+                3..=4 => 2,
+                5..=16 => 4,
+                _ => 8,
+            };
+            let bits = pack_bits(img, depth, |p| {
+                colors.iter().position(|c| *c == p.0).unwrap_or(0) as u8
+            });
+            let depth = png::BitDepth::from_u8(depth).unwrap_or(png::BitDepth::Eight);
+            (png::ColorType::Indexed, depth, bits)
+        }
+        None if opaque => {
+            let rgb = img.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
+            (png::ColorType::Rgb, png::BitDepth::Eight, rgb)
+        }
+        None => (
+            png::ColorType::Rgba,
+            png::BitDepth::Eight,
+            img.as_raw().clone(),
+        ),
+    };
+
+    let mut data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut data, width, height);
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    encoder.set_deflate_compression(png::DeflateCompression::Level(9));
+    encoder.set_filter(png::Filter::Adaptive);
+    encoder.set_pixel_dims(dpi.map(|dpi| {
+        let per_metre = (dpi / 0.0254).round() as u32;
+        png::PixelDimensions {
+            xppu: per_metre,
+            yppu: per_metre,
+            unit: png::Unit::Meter,
+        }
+    }));
+    if color == png::ColorType::Indexed {
+        let colors = palette.unwrap_or_default();
+        encoder.set_palette(
+            colors
+                .iter()
+                .flat_map(|c| [c[0], c[1], c[2]])
+                .collect::<Vec<_>>(),
+        );
+        if !opaque {
+            encoder.set_trns(colors.iter().map(|c| c[3]).collect::<Vec<_>>());
+        }
+    }
+
+    let png_error = |e: png::EncodingError| PayBySquareError::ImageError(e.to_string());
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    writer.write_image_data(&pixels).map_err(png_error)?;
+    writer.finish().map_err(png_error)?;
 
-    Ok(png_data)
+    Ok(data)
+}
+
+/// Returns the distinct colours of an image in order of first appearance,
+/// or `None` when there are more than a palette can hold
+fn image_palette(img: &RgbaImage) -> Option<Vec<[u8; 4]>> {
+    let mut colors: Vec<[u8; 4]> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for pixel in img.pixels() {
+        if seen.insert(pixel.0) {
+            if colors.len() == 256 {
+                return None;
+            }
+            colors.push(pixel.0);
+        }
+    }
+    Some(colors)
+}
+
+/// Packs one value per pixel into rows of `depth` bits, most significant first
+fn pack_bits(img: &RgbaImage, depth: u8, value: impl Fn(&Rgba<u8>) -> u8) -> Vec<u8> {
+    if depth == 8 {
+        return img.pixels().map(value).collect();
+    }
+    let per_byte = 8 / depth as u32;
+    let row_len = img.width().div_ceil(per_byte) as usize;
+    let mut data = vec![0; row_len * img.height() as usize];
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let shift = 8 - depth as u32 * (x % per_byte + 1);
+        data[y as usize * row_len + (x / per_byte) as usize] |= value(pixel) << shift;
+    }
+    data
 }
 
 /// Generates a simple frame if none exists
@@ -119,6 +514,7 @@ pub fn generate_default_frame(size: u32) -> Vec<u8> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::Logo;
 
     #[test]
     fn test_generate_qr_image() {
@@ -128,6 +524,327 @@ mod tests {
         assert!(!png_data.is_empty());
     }
 
+    /// Returns the bit depth and colour type from the IHDR chunk
+    fn png_header(data: &[u8]) -> (u8, u8) {
+        assert_eq!(&data[12..16], b"IHDR");
+        (data[24], data[25])
+    }
+
+    #[test]
+    fn test_encode_png_compact() {
+        let code = "0004A00090IFU3P5R8G9E0N4FD1K5U";
+        let img = render_qr_image(code, &QrOptions::default()).unwrap();
+        let data = encode_image(&img, image::ImageFormat::Png).unwrap();
+        assert_eq!(png_header(&data), (1, 0));
+        assert_eq!(image::load_from_memory(&data).unwrap().to_rgba8(), img);
+
+        let mut rgba = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut rgba),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+        assert!(data.len() * 3 < rgba.len());
+
+        // Only IHDR, IDAT and IEND chunks
+        let mut chunks = Vec::new();
+        let mut at = 8;
+        while at < data.len() {
+            let len = u32::from_be_bytes(data[at..at + 4].try_into().unwrap()) as usize;
+            chunks.push(
+                std::str::from_utf8(&data[at + 4..at + 8])
+                    .unwrap()
+                    .to_string(),
+            );
+            at += len + 12;
+        }
+        assert!(chunks
+            .iter()
+            .all(|c| ["IHDR", "IDAT", "IEND"].contains(&c.as_str())));
+
+        // Byte-identical across calls
+        assert_eq!(
+            data,
+            generate_qr_image_with_options(code, &QrOptions::default()).unwrap()
+        );
+        assert_eq!(
+            generate_qr_image_with_options(code, &QrOptions::default()).unwrap(),
+            generate_qr_image_with_options(code, &QrOptions::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_print_options() {
+        let qr = build_qr_code("0004A00090IFU3P5R8G9E0N4FD1K5U").unwrap();
+        let width = qr.width() as f32;
+        let dimension = qr.width() as u32 + 8;
+        let print = |with_frame| QrOptions {
+            with_frame,
+            size_mm: Some(25.0),
+            dpi: Some(300),
+            ..Default::default()
+        };
+
+        // Symbol measures exactly 25 mm at the returned resolution
+        let (opts, dpi) = print_options(&qr, &print(false), None).unwrap();
+        let dpi = dpi.unwrap();
+        let module = opts.qr_size / dimension;
+        assert_eq!(opts.qr_size % dimension, 0);
+        assert!((module as f32 * width / dpi * 25.4 - 25.0).abs() < 1e-3);
+        assert!((dpi - 300.0).abs() < 300.0 / width);
+
+        // Drawn frame gets the same module size
+        let (framed, framed_dpi) = print_options(&qr, &print(true), None).unwrap();
+        assert_eq!(framed_dpi, Some(dpi));
+        let layout = crate::frame::FrameLayout::new(framed.qr_size as f32, framed.frame_variant);
+        assert_eq!(layout.qr_size.floor() as u32 / dimension, module);
+
+        // Resolution alone keeps the pixel size
+        let opts = QrOptions {
+            dpi: Some(600),
+            ..Default::default()
+        };
+        let (unchanged, dpi) = print_options(&qr, &opts, None).unwrap();
+        assert_eq!((unchanged.qr_size, dpi), (300, Some(600.0)));
+
+        let opts = QrOptions {
+            size_mm: Some(200.0),
+            dpi: Some(600),
+            ..Default::default()
+        };
+        assert!(matches!(
+            print_options(&qr, &opts, None),
+            Err(PayBySquareError::PrintSizeTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_encode_image_with_dpi() {
+        let img = render_qr_image("0004A00090IFU3P5R8G9E0N4FD1K5U", &QrOptions::default()).unwrap();
+
+        let data = encode_image_with_dpi(&img, image::ImageFormat::Png, Some(300.0)).unwrap();
+        let reader = png::Decoder::new(std::io::Cursor::new(&data))
+            .read_info()
+            .unwrap();
+        let dims = reader.info().pixel_dims.unwrap();
+        assert_eq!(
+            (dims.xppu, dims.yppu, dims.unit),
+            (11811, 11811, png::Unit::Meter)
+        );
+
+        // JFIF density in dots per inch
+        let data = encode_image_with_dpi(&img, image::ImageFormat::Jpeg, Some(300.0)).unwrap();
+        assert_eq!(&data[6..11], b"JFIF\0");
+        assert_eq!(data[13], 1);
+        assert_eq!(u16::from_be_bytes([data[14], data[15]]), 300);
+
+        // Fractional resolutions are rounded, out-of-range ones rejected
+        let data = encode_image_with_dpi(&img, image::ImageFormat::Jpeg, Some(299.6)).unwrap();
+        assert_eq!(u16::from_be_bytes([data[14], data[15]]), 300);
+        for format in [image::ImageFormat::Png, image::ImageFormat::Jpeg] {
+            for dpi in [0.4, 65535.5, f32::NAN] {
+                assert!(matches!(
+                    encode_image_with_dpi(&img, format, Some(dpi)),
+                    Err(PayBySquareError::OptionOutOfRange { .. })
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_png_palette() {
+        let code = "0004A00090IFU3P5R8G9E0N4FD1K5U";
+        let opts = QrOptions {
+            foreground: RgbaColor([0, 0, 128, 200]),
+            ..Default::default()
+        };
+        let img = render_qr_image(code, &opts).unwrap();
+        let data = encode_image(&img, image::ImageFormat::Png).unwrap();
+        assert_eq!(png_header(&data), (1, 3));
+        assert_eq!(image::load_from_memory(&data).unwrap().to_rgba8(), img);
+
+        // Gradient with too many colours for a palette
+        let img = RgbaImage::from_fn(64, 64, |x, y| Rgba([(x * 4) as u8, (y * 4) as u8, 0, 255]));
+        let data = encode_image(&img, image::ImageFormat::Png).unwrap();
+        assert_eq!(png_header(&data), (8, 2));
+        assert_eq!(image::load_from_memory(&data).unwrap().to_rgba8(), img);
+
+        let img = RgbaImage::from_fn(20, 3, |x, _| Rgba([0, 0, 0, (x * 12) as u8]));
+        let data = encode_image(&img, image::ImageFormat::Png).unwrap();
+        assert_eq!(png_header(&data), (8, 3));
+        assert_eq!(image::load_from_memory(&data).unwrap().to_rgba8(), img);
+    }
+
+    /// Returns the lengths of same-coloured runs in a row of pixels
+    fn run_lengths(img: &RgbaImage, y: u32, from: u32, to: u32) -> Vec<u32> {
+        let mut runs = vec![1];
+        for x in from + 1..to {
+            if img.get_pixel(x, y) == img.get_pixel(x - 1, y) {
+                *runs.last_mut().unwrap() += 1;
+            } else {
+                runs.push(1);
+            }
+        }
+        runs
+    }
+
+    fn options(quiet_zone: u32) -> QrOptions {
+        QrOptions {
+            quiet_zone,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_qr_uniform_modules() {
+        let qr = build_qr_code("0004A00090IFU3P5R8G9E0N4FD1K5U").unwrap();
+        let dimension = qr.width() as u32 + 8;
+
+        for size in [100, 300, 333, 1000] {
+            let img = render_qr(&qr, size, &options(4), None);
+            assert_eq!(img.dimensions(), (size, size));
+
+            let module = size / dimension;
+            let offset = (size - dimension * module) / 2 + 4 * module;
+
+            // Timing pattern in row 6 alternates single modules between the finders
+            let y = offset + 6 * module + module / 2;
+            let from = offset + 8 * module;
+            let to = offset + (qr.width() as u32 - 8) * module;
+            assert!(run_lengths(&img, y, from, to)
+                .iter()
+                .all(|&run| run == module));
+
+            // Every run in every symbol row is a whole number of modules
+            let end = offset + qr.width() as u32 * module;
+            for row in 0..qr.width() as u32 {
+                let y = offset + row * module;
+                assert!(run_lengths(&img, y, offset, end)
+                    .iter()
+                    .all(|&run| run % module == 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_qr_quiet_zone() {
+        let qr = build_qr_code("TEST").unwrap();
+
+        // 21 modules without quiet zone at 10 px per module, finder starts at the edge
+        let img = render_qr(&qr, 210, &options(0), None);
+        assert_eq!(img.get_pixel(0, 0).0, [0, 0, 0, 255]);
+
+        // Too small for one pixel per module: symbol keeps its size
+        let img = render_qr(&qr, 20, &options(4), None);
+        assert_eq!(img.dimensions(), (29, 29));
+    }
+
+    #[test]
+    fn test_render_qr_colors() {
+        let qr = build_qr_code("TEST").unwrap();
+        let opts = QrOptions {
+            foreground: "#003366".parse().unwrap(),
+            background: "#FFFFFF00".parse().unwrap(),
+            ..options(0)
+        };
+        let img = render_qr(&qr, 210, &opts, None);
+        assert_eq!(img.get_pixel(0, 0).0, [0x00, 0x33, 0x66, 0xFF]);
+        assert_eq!(img.get_pixel(15, 15).0, [0xFF, 0xFF, 0xFF, 0x00]);
+
+        // Transparent background becomes white in JPEG
+        let png = generate_qr_image_with_options(
+            "TEST",
+            &QrOptions {
+                qr_size: 210,
+                ..opts
+            },
+        )
+        .unwrap();
+        let jpeg = convert_image(&png, image::ImageFormat::Jpeg).unwrap();
+        let img = image::load_from_memory(&jpeg).unwrap().to_rgb8();
+        assert!(img.get_pixel(15, 15).0.iter().all(|&c| c > 240));
+    }
+
+    #[test]
+    fn test_build_symbol_with_logo() {
+        let mut logo = Vec::new();
+        RgbaImage::from_pixel(20, 20, Rgba([255, 0, 0, 255]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut logo),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        let opts = QrOptions {
+            logo: Some(Logo::Image(logo)),
+            ..options(0)
+        };
+
+        let (qr, placed) = build_symbol("TEST", &opts).unwrap();
+        assert_eq!(qr.error_correction_level(), EcLevel::H);
+        let area = placed.as_ref().unwrap().area;
+        assert!(dark_runs(&qr, Some(&area))
+            .iter()
+            .all(|&(x, y, length)| (x..x + length).all(|x| !area.contains(x, y))));
+
+        // Logo drawn in the centre of the symbol
+        let img = render_qr(&qr, 210, &opts, placed.as_ref());
+        assert_eq!(img.get_pixel(105, 105).0, [255, 0, 0, 255]);
+
+        // Level H needs a larger symbol than allowed
+        let opts = QrOptions {
+            max_version: 1,
+            ..opts
+        };
+        assert!(matches!(
+            build_symbol("0004A00090IFU3P5R8G9E0N4FD1K5U", &opts),
+            Err(PayBySquareError::VersionTooHigh { max: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_add_caption() {
+        let png_data = generate_qr_image("TEST", 300).unwrap();
+        let lines = [
+            "Amount: 1,234.50 EUR".to_string(),
+            "IBAN: SK96 1100 0000 0029 1859 9669".to_string(),
+        ];
+        let captioned = add_caption(png_data.clone(), &lines, &QrOptions::default()).unwrap();
+        let img = image::load_from_memory(&captioned).unwrap().to_rgba8();
+
+        // Two lines of 1.4 em plus padding below the unchanged symbol
+        let em = 300.0 * CAPTION_EM_RATIO;
+        assert_eq!(img.width(), 300);
+        assert_eq!(img.height(), 300 + (em * 3.8).ceil() as u32);
+        let original = image::load_from_memory(&png_data).unwrap().to_rgba8();
+        assert_eq!(original.get_pixel(150, 299), img.get_pixel(150, 299));
+
+        let ink = img
+            .enumerate_pixels()
+            .filter(|&(_, y, pixel)| y >= 300 && pixel.0[0] < 128)
+            .count();
+        assert!(ink > 200);
+
+        // Long lines are shrunk to the width
+        let long = ["X".repeat(200)];
+        let captioned = add_caption(png_data.clone(), &long, &QrOptions::default()).unwrap();
+        let img = image::load_from_memory(&captioned).unwrap().to_rgba8();
+        assert!(img.height() < 300 + (em * 2.4).ceil() as u32);
+
+        assert_eq!(
+            add_caption(png_data.clone(), &[], &QrOptions::default()).unwrap(),
+            png_data
+        );
+    }
+
+    #[test]
+    fn test_convert_image() {
+        let png_data = generate_qr_image("TEST", 300).unwrap();
+        for format in [image::ImageFormat::Jpeg, image::ImageFormat::WebP] {
+            let data = convert_image(&png_data, format).unwrap();
+            assert_eq!(image::guess_format(&data).unwrap(), format);
+        }
+    }
+
     #[test]
     fn test_generate_default_frame() {
         let frame = generate_default_frame(400);
@@ -0,0 +1,885 @@
+use image::DynamicImage;
+use qrcode::bits::Bits;
+use qrcode::ec::construct_codewords;
+use qrcode::{EcLevel, Version};
+
+/// Largest deviation of the finder triangle from a right isosceles triangle
+const MAX_SHAPE_DEVIATION: f32 = 0.15;
+
+/// Reads a QR code from an image
+///
+/// Meant for checking rendered output: the symbol has to be upright and
+/// undistorted, as rendered by this crate, so camera photos are out of scope.
+/// Returns `None` when no symbol can be found or decoded.
+pub fn read_qr(img: &DynamicImage) -> Option<String> {
+    let bitmap = Bitmap::new(img);
+    let [top_left, top_right, bottom_left] = locate(&bitmap.finders())?;
+
+    let module = (top_left.module + top_right.module + bottom_left.module) / 3.0;
+    let span = (distance(top_left, top_right) + distance(top_left, bottom_left)) / 2.0;
+    let estimated = ((span / module + 7.0 - 17.0) / 4.0)
+        .round()
+        .clamp(1.0, 40.0) as usize;
+
+    let mut grid = Grid::new(top_left, top_right, bottom_left, estimated);
+    if estimated >= 7 {
+        let version = read_version(&bitmap, &grid)?;
+        if version != estimated {
+            grid = Grid::new(top_left, top_right, bottom_left, version);
+        }
+    }
+
+    let modules = grid.sample(&bitmap);
+    let data = decode_symbol(&modules, grid.version)?;
+    String::from_utf8(data).ok()
+}
+
+/// Binarized image, dark pixels are `true`
+struct Bitmap {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+}
+
+impl Bitmap {
+    /// Converts to grayscale over white and splits at the Otsu threshold
+    fn new(img: &DynamicImage) -> Self {
+        let rgba = img.to_rgba8();
+        let luma: Vec<u8> = rgba
+            .pixels()
+            .map(|p| {
+                let [r, g, b, a] = p.0;
+                let gray = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+                let alpha = a as f32 / 255.0;
+                (gray * alpha + 255.0 * (1.0 - alpha)).round() as u8
+            })
+            .collect();
+        let threshold = otsu_threshold(&luma);
+        Self {
+            width: rgba.width() as usize,
+            height: rgba.height() as usize,
+            dark: luma.iter().map(|&l| l <= threshold).collect(),
+        }
+    }
+
+    /// Returns `None` outside the image
+    fn is_dark(&self, x: i64, y: i64) -> Option<bool> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        Some(self.dark[y as usize * self.width + x as usize])
+    }
+
+    /// Counts pixels of one colour from `(x, y)` in direction `(dx, dy)`
+    fn run(&self, mut x: i64, mut y: i64, dx: i64, dy: i64, dark: bool) -> i64 {
+        let mut count = 0;
+        while self.is_dark(x, y) == Some(dark) {
+            count += 1;
+            x += dx;
+            y += dy;
+        }
+        count
+    }
+
+    /// Checks for a finder pattern through `(x, y)` along one axis
+    ///
+    /// Returns the centre of the pattern on that axis and the module size.
+    fn cross_check(&self, x: i64, y: i64, dx: i64, dy: i64) -> Option<(f32, f32)> {
+        if self.is_dark(x, y) != Some(true) {
+            return None;
+        }
+        let back = self.run(x, y, -dx, -dy, true);
+        let forward = self.run(x + dx, y + dy, dx, dy, true);
+
+        let (bx, by) = (x - dx * back, y - dy * back);
+        let light_before = self.run(bx, by, -dx, -dy, false);
+        let dark_before = self.run(
+            bx - dx * light_before,
+            by - dy * light_before,
+            -dx,
+            -dy,
+            true,
+        );
+        let (fx, fy) = (x + dx * (forward + 1), y + dy * (forward + 1));
+        let light_after = self.run(fx, fy, dx, dy, false);
+        let dark_after = self.run(fx + dx * light_after, fy + dy * light_after, dx, dy, true);
+
+        let module = finder_module([
+            dark_before,
+            light_before,
+            back + forward,
+            light_after,
+            dark_after,
+        ])?;
+        let start = x * dx + y * dy - back + 1;
+        Some((start as f32 + (back + forward) as f32 / 2.0, module))
+    }
+
+    /// Scans every row for 1:1:3:1:1 runs and confirms them vertically and horizontally
+    fn finders(&self) -> Vec<Finder> {
+        let mut finders: Vec<Finder> = Vec::new();
+        for y in 0..self.height {
+            let runs = self.row_runs(y);
+            for window in runs.windows(5) {
+                if !window[0].2 {
+                    continue;
+                }
+                let counts = [0, 1, 2, 3, 4].map(|i| window[i].1);
+                if finder_module(counts).is_none() {
+                    continue;
+                }
+
+                let x = window[2].0 + window[2].1 / 2;
+                let Some((cy, vertical)) = self.cross_check(x, y as i64, 0, 1) else {
+                    continue;
+                };
+                let Some((cx, horizontal)) = self.cross_check(x, cy as i64, 1, 0) else {
+                    continue;
+                };
+                let module = (vertical + horizontal) / 2.0;
+                if (vertical - horizontal).abs() > module / 2.0 {
+                    continue;
+                }
+
+                match finders.iter_mut().find(|f| f.matches(cx, cy, module)) {
+                    Some(finder) => finder.merge(cx, cy, module),
+                    None => finders.push(Finder {
+                        x: cx,
+                        y: cy,
+                        module,
+                        count: 1,
+                    }),
+                }
+            }
+        }
+        finders
+    }
+
+    /// Returns `(start, length, dark)` runs of a row
+    fn row_runs(&self, y: usize) -> Vec<(i64, i64, bool)> {
+        let row = &self.dark[y * self.width..(y + 1) * self.width];
+        let mut runs: Vec<(i64, i64, bool)> = Vec::new();
+        for (x, &dark) in row.iter().enumerate() {
+            match runs.last_mut() {
+                Some(run) if run.2 == dark => run.1 += 1,
+                _ => runs.push((x as i64, 1, dark)),
+            }
+        }
+        runs
+    }
+}
+
+/// Threshold maximising the variance between dark and light pixels
+fn otsu_threshold(luma: &[u8]) -> u8 {
+    let mut histogram = [0u64; 256];
+    for &l in luma {
+        histogram[l as usize] += 1;
+    }
+    let total = luma.len() as f64;
+    let sum: f64 = (0..256).map(|i| i as f64 * histogram[i] as f64).sum();
+
+    let (mut best, mut best_variance) = (127, 0.0);
+    let (mut weight, mut dark_sum) = (0.0, 0.0);
+    for (i, &count) in histogram.iter().enumerate() {
+        weight += count as f64;
+        dark_sum += i as f64 * count as f64;
+        if weight == 0.0 || weight == total {
+            continue;
+        }
+        let dark_mean = dark_sum / weight;
+        let light_mean = (sum - dark_sum) / (total - weight);
+        let variance = weight * (total - weight) * (dark_mean - light_mean).powi(2);
+        if variance > best_variance {
+            best = i as u8;
+            best_variance = variance;
+        }
+    }
+    best
+}
+
+/// Module size of dark-light-dark-light-dark runs in 1:1:3:1:1 ratio
+fn finder_module(counts: [i64; 5]) -> Option<f32> {
+    let total: i64 = counts.iter().sum();
+    if total < 7 {
+        return None;
+    }
+    let module = total as f32 / 7.0;
+    counts
+        .iter()
+        .zip([1.0, 1.0, 3.0, 1.0, 1.0])
+        .all(|(&count, ratio)| (count as f32 - ratio * module).abs() < ratio * module / 2.0)
+        .then_some(module)
+}
+
+/// Centre of a finder pattern in pixels
+#[derive(Debug, Clone, Copy)]
+struct Finder {
+    x: f32,
+    y: f32,
+    module: f32,
+    count: usize,
+}
+
+impl Finder {
+    fn matches(&self, x: f32, y: f32, module: f32) -> bool {
+        (self.x - x).abs() <= self.module
+            && (self.y - y).abs() <= self.module
+            && (self.module - module).abs() <= self.module.max(1.0)
+    }
+
+    fn merge(&mut self, x: f32, y: f32, module: f32) {
+        let n = self.count as f32;
+        self.x = (self.x * n + x) / (n + 1.0);
+        self.y = (self.y * n + y) / (n + 1.0);
+        self.module = (self.module * n + module) / (n + 1.0);
+        self.count += 1;
+    }
+}
+
+fn distance(a: Finder, b: Finder) -> f32 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+/// Picks the three finders closest to a right isosceles triangle
+///
+/// Returns them as top-left, top-right and bottom-left.
+fn locate(finders: &[Finder]) -> Option<[Finder; 3]> {
+    let mut best: Option<([Finder; 3], f32)> = None;
+    for (i, &a) in finders.iter().enumerate() {
+        for (j, &b) in finders.iter().enumerate().skip(i + 1) {
+            for &c in finders.iter().skip(j + 1) {
+                let modules = [a.module, b.module, c.module];
+                let largest = modules.iter().cloned().fold(f32::MIN, f32::max);
+                let smallest = modules.iter().cloned().fold(f32::MAX, f32::min);
+                if largest > smallest * 1.5 {
+                    continue;
+                }
+
+                // The corner is opposite the longest side
+                let mut sides = [
+                    (distance(b, c), a, b, c),
+                    (distance(a, c), b, a, c),
+                    (distance(a, b), c, a, b),
+                ];
+                sides.sort_by(|x, y| y.0.total_cmp(&x.0));
+                let (hypotenuse, corner, p, q) = sides[0];
+                let (leg_1, leg_2) = (distance(corner, p), distance(corner, q));
+                if leg_1.min(leg_2) < 14.0 * smallest {
+                    continue;
+                }
+                let deviation = (leg_1 - leg_2).abs() / leg_1.max(leg_2)
+                    + (hypotenuse - leg_1.hypot(leg_2)).abs() / hypotenuse;
+                if deviation > MAX_SHAPE_DEVIATION || best.is_some_and(|(_, d)| d <= deviation) {
+                    continue;
+                }
+
+                // Top-right is clockwise from bottom-left with y pointing down
+                let cross =
+                    (p.x - corner.x) * (q.y - corner.y) - (p.y - corner.y) * (q.x - corner.x);
+                let triple = if cross > 0.0 {
+                    [corner, p, q]
+                } else {
+                    [corner, q, p]
+                };
+                best = Some((triple, deviation));
+            }
+        }
+    }
+    best.map(|(triple, _)| triple)
+}
+
+/// Maps module coordinates to pixels from the finder centres
+struct Grid {
+    version: usize,
+    origin: (f32, f32),
+    right: (f32, f32),
+    down: (f32, f32),
+}
+
+impl Grid {
+    fn new(top_left: Finder, top_right: Finder, bottom_left: Finder, version: usize) -> Self {
+        // Finder centres are 3.5 modules from the symbol edge
+        let span = (symbol_width(version) - 7) as f32;
+        let right = (
+            (top_right.x - top_left.x) / span,
+            (top_right.y - top_left.y) / span,
+        );
+        let down = (
+            (bottom_left.x - top_left.x) / span,
+            (bottom_left.y - top_left.y) / span,
+        );
+        Self {
+            version,
+            origin: (
+                top_left.x - 3.5 * (right.0 + down.0),
+                top_left.y - 3.5 * (right.1 + down.1),
+            ),
+            right,
+            down,
+        }
+    }
+
+    fn is_dark(&self, bitmap: &Bitmap, x: usize, y: usize) -> bool {
+        let (u, v) = (x as f32 + 0.5, y as f32 + 0.5);
+        let px = self.origin.0 + u * self.right.0 + v * self.down.0;
+        let py = self.origin.1 + u * self.right.1 + v * self.down.1;
+        bitmap
+            .is_dark(px.floor() as i64, py.floor() as i64)
+            .unwrap_or(false)
+    }
+
+    fn sample(&self, bitmap: &Bitmap) -> Modules {
+        let width = symbol_width(self.version);
+        Modules {
+            width,
+            dark: (0..width * width)
+                .map(|i| self.is_dark(bitmap, i % width, i / width))
+                .collect(),
+        }
+    }
+}
+
+/// Sampled modules of a symbol
+struct Modules {
+    width: usize,
+    dark: Vec<bool>,
+}
+
+impl Modules {
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.width + x]
+    }
+
+    /// Reads bits at the given coordinates, most significant first
+    fn number(&self, coords: impl Iterator<Item = (usize, usize)>) -> u32 {
+        coords.fold(0, |acc, (x, y)| (acc << 1) | self.get(x, y) as u32)
+    }
+}
+
+fn symbol_width(version: usize) -> usize {
+    17 + 4 * version
+}
+
+/// Reads the version information next to the top-right finder (versions 7 and up)
+fn read_version(bitmap: &Bitmap, grid: &Grid) -> Option<usize> {
+    let width = symbol_width(grid.version);
+    let bits = (0..18).fold(0, |acc, i| {
+        let (x, y) = (width - 9 - i % 3, 5 - i / 3);
+        (acc << 1) | grid.is_dark(bitmap, x, y) as u32
+    });
+    (7..=40)
+        .map(|version| {
+            let distance = (bch_code(version as u32, 0x1F25, 12) ^ bits).count_ones();
+            (distance, version)
+        })
+        .min()
+        .filter(|&(distance, _)| distance <= 3)
+        .map(|(_, version)| version)
+}
+
+/// Appends the BCH remainder of `data` for the generator polynomial `poly` of degree `degree`
+fn bch_code(data: u32, poly: u32, degree: u32) -> u32 {
+    let bit_length = |value: u32| 32 - value.leading_zeros();
+    let mut remainder = data << degree;
+    while bit_length(remainder) > degree {
+        remainder ^= poly << (bit_length(remainder) - bit_length(poly));
+    }
+    (data << degree) | remainder
+}
+
+/// Reads the error correction level and mask from the better of the two format copies
+fn read_format(modules: &Modules) -> Option<(EcLevel, u8)> {
+    let w = modules.width;
+    let main = modules.number(
+        (0..6)
+            .map(|x| (x, 8))
+            .chain([(7, 8), (8, 8), (8, 7)])
+            .chain((0..6).rev().map(|y| (8, y))),
+    );
+    let side = modules.number(
+        (1..8)
+            .map(|i| (8, w - i))
+            .chain((1..9).rev().map(|i| (w - i, 8))),
+    );
+
+    let (distance, format) = (0..32)
+        .map(|format| {
+            let code = bch_code(format, 0x537, 10) ^ 0x5412;
+            let distance = (code ^ main).count_ones().min((code ^ side).count_ones());
+            (distance, format)
+        })
+        .min()?;
+    if distance > 3 {
+        return None;
+    }
+
+    let ec_level = match format >> 3 {
+        0b01 => EcLevel::L,
+        0b00 => EcLevel::M,
+        0b11 => EcLevel::Q,
+        _ => EcLevel::H,
+    };
+    Some((ec_level, (format & 0b111) as u8))
+}
+
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = if version == 32 {
+        26
+    } else {
+        (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2
+    };
+    let last = symbol_width(version) - 7;
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| last - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Returns whether a module belongs to a function pattern or format/version information
+fn is_function(version: usize, alignment: &[usize], x: usize, y: usize) -> bool {
+    let w = symbol_width(version);
+    if (x < 9 && (y < 9 || y >= w - 8)) || (x >= w - 8 && y < 9) || x == 6 || y == 6 {
+        return true;
+    }
+    if version >= 7 && ((x >= w - 11 && x < w - 8 && y < 6) || (y >= w - 11 && y < w - 8 && x < 6))
+    {
+        return true;
+    }
+    let last = w - 7;
+    alignment.iter().any(|&cx| {
+        alignment.iter().any(|&cy| {
+            let overlaps_finder = (cx == 6 && (cy == 6 || cy == last)) || (cx == last && cy == 6);
+            !overlaps_finder && x.abs_diff(cx) <= 2 && y.abs_diff(cy) <= 2
+        })
+    })
+}
+
+fn is_masked(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (y / 2 + x / 3).is_multiple_of(2),
+        5 => (x * y) % 2 + (x * y) % 3 == 0,
+        6 => ((x * y) % 2 + (x * y) % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + (x * y) % 3).is_multiple_of(2),
+    }
+}
+
+/// Unmasks the symbol, corrects errors and returns the encoded bytes
+fn decode_symbol(modules: &Modules, version: usize) -> Option<Vec<u8>> {
+    let (ec_level, mask) = read_format(modules)?;
+    let w = modules.width;
+    let alignment = alignment_positions(version);
+
+    // Codewords are placed in two-module columns zigzagging from the bottom right
+    let mut bits = Vec::new();
+    let mut right = w - 1;
+    while right >= 1 {
+        if right == 6 {
+            right = 5;
+        }
+        let upward = (right + 1) & 2 == 0;
+        for i in 0..w {
+            let y = if upward { w - 1 - i } else { i };
+            for x in [right, right - 1] {
+                if !is_function(version, &alignment, x, y) {
+                    bits.push(modules.get(x, y) ^ is_masked(mask, x, y));
+                }
+            }
+        }
+        if right < 2 {
+            break;
+        }
+        right -= 2;
+    }
+    let codewords: Vec<u8> = bits
+        .chunks_exact(8)
+        .map(|byte| byte.iter().fold(0, |acc, &bit| (acc << 1) | bit as u8))
+        .collect();
+
+    let data = correct_blocks(&codewords, version, ec_level)?;
+    parse_segments(&data, version)
+}
+
+/// Splits interleaved codewords into blocks, corrects them and joins their data
+fn correct_blocks(codewords: &[u8], version: usize, ec_level: EcLevel) -> Option<Vec<u8>> {
+    let qr_version = Version::Normal(version as i16);
+    let data_len = Bits::new(qr_version).max_len(ec_level).ok()? / 8;
+
+    // Block layout is taken from the encoder: with `b` blocks the second byte
+    // of the first block lands at index `b` after interleaving
+    let mut probe = vec![0u8; data_len];
+    probe[1] = 1;
+    let (interleaved, ec) = construct_codewords(&probe, qr_version, ec_level).ok()?;
+    let block_count = interleaved.iter().position(|&b| b == 1)?;
+    let ec_len = ec.len() / block_count;
+
+    let short = data_len / block_count;
+    let long_count = data_len % block_count;
+    let sizes: Vec<usize> = (0..block_count)
+        .map(|b| short + usize::from(b >= block_count - long_count))
+        .collect();
+
+    let mut blocks: Vec<Vec<u8>> = sizes
+        .iter()
+        .map(|&size| Vec::with_capacity(size + ec_len))
+        .collect();
+    let mut next = codewords.iter().copied();
+    for i in 0..=short {
+        for (block, &size) in blocks.iter_mut().zip(&sizes) {
+            if i < size {
+                block.push(next.next()?);
+            }
+        }
+    }
+    for _ in 0..ec_len {
+        for block in blocks.iter_mut() {
+            block.push(next.next()?);
+        }
+    }
+
+    let gf = Galois::new();
+    let mut data = Vec::with_capacity(data_len);
+    for (mut block, size) in blocks.into_iter().zip(sizes) {
+        gf.correct(&mut block, ec_len)?;
+        data.extend_from_slice(&block[..size]);
+    }
+    Some(data)
+}
+
+/// GF(256) arithmetic with the QR polynomial x^8 + x^4 + x^3 + x^2 + 1
+struct Galois {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+impl Galois {
+    fn new() -> Self {
+        let mut exp = [0u8; 512];
+        let mut log = [0u8; 256];
+        let mut x: u16 = 1;
+        for (i, e) in exp.iter_mut().enumerate() {
+            *e = x as u8;
+            if i < 255 {
+                log[x as usize] = i as u8;
+            }
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= 0x11D;
+            }
+        }
+        Self { exp, log }
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        if a == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + 255 - self.log[b as usize] as usize]
+    }
+
+    /// α to the power of `e`
+    fn pow(&self, e: usize) -> u8 {
+        self.exp[e % 255]
+    }
+
+    /// Evaluates a polynomial with the lowest coefficient first
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter().rev().fold(0, |acc, &c| self.mul(acc, x) ^ c)
+    }
+
+    /// Syndromes of a block with the first byte as the highest coefficient
+    fn syndromes(&self, block: &[u8], ec_len: usize) -> Vec<u8> {
+        (0..ec_len)
+            .map(|j| {
+                let x = self.pow(j);
+                block.iter().fold(0, |acc, &c| self.mul(acc, x) ^ c)
+            })
+            .collect()
+    }
+
+    /// Corrects a Reed-Solomon block in place (Berlekamp-Massey, Chien search, Forney)
+    fn correct(&self, block: &mut [u8], ec_len: usize) -> Option<()> {
+        let syndromes = self.syndromes(block, ec_len);
+        if syndromes.iter().all(|&s| s == 0) {
+            return Some(());
+        }
+
+        // Error locator polynomial
+        let mut locator = vec![1u8];
+        let mut previous = vec![1u8];
+        let mut errors = 0;
+        let mut shift = 1;
+        let mut previous_discrepancy = 1u8;
+        for k in 0..ec_len {
+            let discrepancy = (1..=errors.min(locator.len() - 1)).fold(syndromes[k], |d, i| {
+                d ^ self.mul(locator[i], syndromes[k - i])
+            });
+            if discrepancy == 0 {
+                shift += 1;
+                continue;
+            }
+            let coefficient = self.div(discrepancy, previous_discrepancy);
+            let mut next = locator.clone();
+            next.resize(next.len().max(previous.len() + shift), 0);
+            for (i, &p) in previous.iter().enumerate() {
+                next[i + shift] ^= self.mul(coefficient, p);
+            }
+            if 2 * errors <= k {
+                errors = k + 1 - errors;
+                previous = std::mem::replace(&mut locator, next);
+                previous_discrepancy = discrepancy;
+                shift = 1;
+            } else {
+                locator = next;
+                shift += 1;
+            }
+        }
+        if 2 * errors > ec_len {
+            return None;
+        }
+
+        let n = block.len();
+        let positions: Vec<usize> = (0..n)
+            .filter(|&i| {
+                let inverse = self.pow(255 - (n - 1 - i) % 255);
+                self.eval(&locator, inverse) == 0
+            })
+            .collect();
+        if positions.len() != errors {
+            return None;
+        }
+
+        // Error evaluator and formal derivative of the locator
+        let mut evaluator = vec![0u8; ec_len];
+        for (i, &s) in syndromes.iter().enumerate() {
+            for (j, &l) in locator.iter().enumerate() {
+                if i + j < ec_len {
+                    evaluator[i + j] ^= self.mul(s, l);
+                }
+            }
+        }
+        let derivative: Vec<u8> = locator
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, &l)| if i % 2 == 1 { l } else { 0 })
+            .collect();
+
+        for i in positions {
+            let x = self.pow(n - 1 - i);
+            let inverse = self.div(1, x);
+            let denominator = self.eval(&derivative, inverse);
+            if denominator == 0 {
+                return None;
+            }
+            let magnitude = self.div(self.eval(&evaluator, inverse), denominator);
+            block[i] ^= self.mul(x, magnitude);
+        }
+
+        self.syndromes(block, ec_len)
+            .iter()
+            .all(|&s| s == 0)
+            .then_some(())
+    }
+}
+
+/// Reads bits most significant first
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn available(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    fn read(&mut self, count: usize) -> Option<u32> {
+        if count > self.available() {
+            return None;
+        }
+        let value = (self.position..self.position + count).fold(0, |acc, i| {
+            (acc << 1) | u32::from(self.data[i / 8] >> (7 - i % 8) & 1)
+        });
+        self.position += count;
+        Some(value)
+    }
+}
+
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Decodes numeric, alphanumeric and byte segments
+fn parse_segments(data: &[u8], version: usize) -> Option<Vec<u8>> {
+    let class = match version {
+        1..=9 => 0,
+        10..=26 => 1,
+        _ => 2,
+    };
+    let mut reader = BitReader { data, position: 0 };
+    let mut text = Vec::new();
+
+    while reader.available() >= 4 {
+        match reader.read(4)? {
+            0b0000 => break,
+            0b0001 => {
+                let mut count = reader.read([10, 12, 14][class])?;
+                while count > 0 {
+                    let digits = count.min(3);
+                    let value = reader.read([0, 4, 7, 10][digits as usize])?;
+                    if value >= 10u32.pow(digits) {
+                        return None;
+                    }
+                    text.extend(format!("{:0width$}", value, width = digits as usize).bytes());
+                    count -= digits;
+                }
+            }
+            0b0010 => {
+                let mut count = reader.read([9, 11, 13][class])?;
+                while count > 0 {
+                    let chars = count.min(2);
+                    let value = reader.read(if chars == 2 { 11 } else { 6 })? as usize;
+                    if chars == 2 {
+                        text.push(*ALPHANUMERIC.get(value / 45)?);
+                    }
+                    text.push(*ALPHANUMERIC.get(value % 45)?);
+                    count -= chars;
+                }
+            }
+            0b0100 => {
+                let count = reader.read([8, 16, 16][class])?;
+                for _ in 0..count {
+                    text.push(reader.read(8)? as u8);
+                }
+            }
+            0b0111 => {
+                // ECI designator, the bytes are passed through unchanged
+                let first = reader.read(8)?;
+                if first & 0x80 != 0 {
+                    reader.read(if first & 0x40 == 0 { 8 } else { 16 })?;
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Logo, QrOptions, RgbaColor};
+    use crate::qr::{add_frame, convert_image, generate_qr_image, generate_qr_image_with_options};
+    use qrcode::QrCode;
+
+    const CODE: &str = "0004A00090IFU3P5R8G9E0N4FD1K5U";
+
+    fn read(data: &[u8]) -> Option<String> {
+        read_qr(&image::load_from_memory(data).unwrap())
+    }
+
+    #[test]
+    fn test_read_rendered_sizes() {
+        for size in [100, 173, 300, 1000] {
+            assert_eq!(
+                read(&generate_qr_image(CODE, size).unwrap()).as_deref(),
+                Some(CODE)
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_large_versions() {
+        // Version 7+ symbols carry version information; the longest is version 40
+        for len in [150, 600, 3000] {
+            let code: String = "0123456789ABCDEFGHIJKLMNOPQRSTUV"
+                .chars()
+                .cycle()
+                .take(len)
+                .collect();
+            let data = generate_qr_image(&code, 2000).unwrap();
+            assert_eq!(read(&data), Some(code));
+        }
+    }
+
+    #[test]
+    fn test_read_framed_jpeg_with_logo() {
+        let mut logo = Vec::new();
+        image::RgbaImage::from_pixel(20, 20, image::Rgba([20, 20, 20, 255]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut logo),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        let opts = QrOptions {
+            foreground: RgbaColor([0x20, 0x30, 0x80, 0xFF]),
+            logo: Some(Logo::Image(logo)),
+            ..Default::default()
+        };
+        let png = add_frame(generate_qr_image_with_options(CODE, &opts).unwrap(), None).unwrap();
+        let jpeg = convert_image(&png, image::ImageFormat::Jpeg).unwrap();
+        assert_eq!(read(&jpeg).as_deref(), Some(CODE));
+    }
+
+    #[test]
+    fn test_read_blank_image() {
+        let blank = DynamicImage::new_rgb8(200, 200);
+        assert_eq!(read_qr(&blank), None);
+    }
+
+    #[test]
+    fn test_corrects_errors() {
+        let gf = Galois::new();
+        let data = b"PAY by square".to_vec();
+        let mut block = data.clone();
+        block.extend(qrcode::ec::create_error_correction_code(&data, 10));
+
+        block[0] ^= 0xFF;
+        block[7] ^= 0x12;
+        block[20] ^= 0x01;
+        gf.correct(&mut block, 10).unwrap();
+        assert_eq!(&block[..data.len()], &data[..]);
+
+        for i in 0..6 {
+            block[i * 3] ^= 0x55;
+        }
+        assert!(gf.correct(&mut block, 10).is_none());
+    }
+
+    #[test]
+    fn test_function_modules_match_encoder() {
+        for version in [1, 2, 7, 14, 32, 40] {
+            let qr =
+                QrCode::with_version(b"1", Version::Normal(version as i16), EcLevel::L).unwrap();
+            let alignment = alignment_positions(version);
+            let w = qr.width();
+            for y in 0..w {
+                for x in 0..w {
+                    // The encoder doesn't report version information as functional
+                    if qr.is_functional(x, y) {
+                        assert!(
+                            is_function(version, &alignment, x, y),
+                            "v{} ({}, {})",
+                            version,
+                            x,
+                            y
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::models::{FieldChange, PaymentRequest};
+
+/// Canonicalizes a payment request before validation.
+///
+/// Returns the normalized request together with the list of fields that were
+/// changed, so clients can fix the data on their side.
+pub fn sanitize_payment_request(payment: &PaymentRequest) -> (PaymentRequest, Vec<FieldChange>) {
+    let mut sanitized = payment.clone();
+    let mut changes = Vec::new();
+
+    // IBAN: remove whitespace, uppercase
+    if let Some(ref mut iban) = sanitized.iban {
+        apply("iban", iban, normalize_iban, &mut changes);
+    }
+
+    // SWIFT: remove whitespace, uppercase, drop "XXX" branch code
+    if let Some(ref mut swift) = sanitized.swift {
+        apply("swift", swift, normalize_swift, &mut changes);
+    }
+
+    // Bank accounts
+    if let Some(ref mut accounts) = sanitized.bank_accounts {
+        for (i, account) in accounts.iter_mut().enumerate() {
+            apply(
+                &format!("bank_accounts[{}].iban", i),
+                &mut account.iban,
+                normalize_iban,
+                &mut changes,
+            );
+            if let Some(ref mut swift) = account.swift {
+                apply(
+                    &format!("bank_accounts[{}].swift", i),
+                    swift,
+                    normalize_swift,
+                    &mut changes,
+                );
+            }
+        }
+    }
+
+    // Currency: trim, uppercase
+    apply(
+        "currency",
+        &mut sanitized.currency,
+        |v| v.trim().to_uppercase(),
+        &mut changes,
+    );
+
+    // Symbols: trim surrounding whitespace
+    if let Some(ref mut vs) = sanitized.variable_symbol {
+        apply("variable_symbol", vs, trim, &mut changes);
+    }
+
+    if let Some(ref mut cs) = sanitized.constant_symbol {
+        apply("constant_symbol", cs, trim, &mut changes);
+    }
+
+    if let Some(ref mut ss) = sanitized.specific_symbol {
+        apply("specific_symbol", ss, trim, &mut changes);
+    }
+
+    (sanitized, changes)
+}
+
+/// Replaces `value` with its normalized form and records the change, if any
+fn apply(
+    field: &str,
+    value: &mut String,
+    normalize: impl Fn(&str) -> String,
+    changes: &mut Vec<FieldChange>,
+) {
+    let normalized = normalize(value);
+    if normalized != *value {
+        changes.push(FieldChange {
+            field: field.to_string(),
+            original: std::mem::replace(value, normalized.clone()),
+            sanitized: normalized,
+        });
+    }
+}
+
+/// Removes all whitespace and uppercases the IBAN
+fn normalize_iban(iban: &str) -> String {
+    iban.chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Removes whitespace, uppercases and strips the "XXX" primary office suffix
+fn normalize_swift(swift: &str) -> String {
+    let swift_clean = normalize_iban(swift);
+    match swift_clean.strip_suffix("XXX") {
+        Some(primary) if swift_clean.len() == 11 => primary.to_string(),
+        _ => swift_clean,
+    }
+}
+
+fn trim(value: &str) -> String {
+    value.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment() -> PaymentRequest {
+        serde_json::from_value(serde_json::json!({
+            "amount": 10.0,
+            "iban": "sk96 1100 0000 0029 1859 9669",
+            "swift": "tatrskbxxxx",
+            "variable_symbol": "  123"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sanitize_canonicalizes_fields() {
+        let (sanitized, changes) = sanitize_payment_request(&payment());
+        assert_eq!(sanitized.iban.as_deref(), Some("SK9611000000002918599669"));
+        assert_eq!(sanitized.swift.as_deref(), Some("TATRSKBX"));
+        assert_eq!(sanitized.variable_symbol.as_deref(), Some("123"));
+
+        let fields: Vec<_> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["iban", "swift", "variable_symbol"]);
+        assert_eq!(changes[2].original, "  123");
+    }
+
+    #[test]
+    fn test_sanitize_clean_input_has_no_changes() {
+        let (sanitized, _) = sanitize_payment_request(&payment());
+        let (_, changes) = sanitize_payment_request(&sanitized);
+        assert!(changes.is_empty());
+    }
+}
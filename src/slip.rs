@@ -0,0 +1,696 @@
+use crate::caption::{format_amount, format_date, group_iban, payment_iban};
+use crate::errors::{PayBySquareError, Result};
+use crate::generator::generate_pay_by_square_code;
+use crate::i18n::{translate, Locale};
+use crate::models::{PageSize, PaymentRequest, QrOptions, RgbaColor};
+use crate::pdf::{draw_code, embed_font, pdf_color, CodeLayout};
+use crate::profiles::ValidationProfile;
+use crate::qr::{build_symbol, render_qr_image};
+use crate::templates::{file_stem, has_extension};
+use crate::text::{outline_text, TextRun, Weight};
+use crate::validation::{
+    validate_payment_request_with_profile, validate_qr_options, ValidationWarning,
+};
+use crate::verify::verify_rendered;
+use printpdf::path::{PaintMode, WindingOrder};
+use printpdf::{Line, Mm, PdfDocument, PdfLayerReference, Point, Polygon};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the built-in template, used when a request doesn't name one
+pub const DEFAULT_SLIP_TEMPLATE: &str = "default";
+
+/// Source of the built-in template
+const DEFAULT_SLIP_SOURCE: &str = include_str!("../slips/default.toml");
+
+/// Line height relative to the font size
+const LINE_HEIGHT: f32 = 1.4;
+
+/// Millimetres per typographic point
+const MM_PER_PT: f32 = 25.4 / 72.0;
+
+/// Horizontal anchor of a text element
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Element of a slip template
+///
+/// Positions and sizes are in millimetres from the top-left corner of the
+/// page; text is placed by the baseline of its first line. Font sizes and
+/// line thicknesses are in points.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SlipElement {
+    /// Text with `{field}` and `{label.name}` placeholders; lines whose fields
+    /// are all missing are left out
+    Text {
+        x: f32,
+        y: f32,
+        text: String,
+        size: Option<f32>,
+        #[serde(default)]
+        bold: bool,
+        color: Option<RgbaColor>,
+        #[serde(default)]
+        align: Align,
+        /// Text is set smaller when a line is wider
+        width: Option<f32>,
+    },
+    /// Label and value rows for the given fields; missing fields are skipped
+    Fields {
+        x: f32,
+        y: f32,
+        fields: Vec<String>,
+        #[serde(default = "default_label_width")]
+        label_width: f32,
+        size: Option<f32>,
+        color: Option<RgbaColor>,
+        label_color: Option<RgbaColor>,
+        /// Values are set smaller when a row is wider
+        width: Option<f32>,
+    },
+    Line {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        color: Option<RgbaColor>,
+        #[serde(default = "default_thickness")]
+        thickness: f32,
+    },
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        stroke: Option<RgbaColor>,
+        fill: Option<RgbaColor>,
+        #[serde(default = "default_thickness")]
+        thickness: f32,
+    },
+    /// PAY by square code `size` millimetres wide, in the drawn frame unless
+    /// `frame` is false
+    Qr {
+        x: f32,
+        y: f32,
+        size: f32,
+        frame: Option<bool>,
+    },
+}
+
+/// Declarative layout of a payment slip, loaded from TOML
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlipTemplate {
+    #[serde(skip)]
+    pub name: String,
+    /// Page size; `fit` is not allowed (default: a4)
+    #[serde(default = "default_page")]
+    pub page: PageSize,
+    /// Default font size in points (default: 10)
+    #[serde(default = "default_font_size")]
+    pub font_size: f32,
+    /// Default text colour (default: #000000)
+    #[serde(default = "RgbaColor::black")]
+    pub color: RgbaColor,
+    #[serde(default, rename = "element")]
+    pub elements: Vec<SlipElement>,
+}
+
+impl SlipTemplate {
+    /// Parses and checks a template
+    ///
+    /// Unknown placeholders and fields, non-positive sizes and a `fit` page are
+    /// configuration errors.
+    pub fn from_toml(name: &str, source: &str) -> Result<Self> {
+        let error = |message: String| {
+            PayBySquareError::ConfigError(format!("slip template '{}': {}", name, message))
+        };
+        let mut template: SlipTemplate =
+            toml::from_str(source).map_err(|e| error(e.to_string()))?;
+        template.name = name.to_string();
+
+        if template.page == PageSize::Fit {
+            return Err(error("page must have a fixed size".to_string()));
+        }
+        if template.font_size.is_nan() || template.font_size <= 0.0 {
+            return Err(error("font_size must be greater than 0".to_string()));
+        }
+
+        for element in &template.elements {
+            let sizes: &[Option<f32>] = match element {
+                SlipElement::Text {
+                    text, size, width, ..
+                } => {
+                    for placeholder in placeholders(text) {
+                        if !is_placeholder(placeholder) {
+                            return Err(error(format!("unknown placeholder {{{}}}", placeholder)));
+                        }
+                    }
+                    &[*size, *width]
+                }
+                SlipElement::Fields {
+                    fields,
+                    label_width,
+                    size,
+                    width,
+                    ..
+                } => {
+                    if let Some(field) = fields.iter().find(|f| !ROW_FIELDS.contains(&f.as_str())) {
+                        return Err(error(format!("unknown field '{}'", field)));
+                    }
+                    &[*size, *width, Some(*label_width)]
+                }
+                SlipElement::Line { thickness, .. } => &[Some(*thickness)],
+                SlipElement::Rect {
+                    width,
+                    height,
+                    thickness,
+                    ..
+                } => &[Some(*width), Some(*height), Some(*thickness)],
+                SlipElement::Qr { size, .. } => &[Some(*size)],
+            };
+            if sizes
+                .iter()
+                .flatten()
+                .any(|size| size.is_nan() || *size <= 0.0)
+            {
+                return Err(error("sizes must be greater than 0".to_string()));
+            }
+        }
+
+        Ok(template)
+    }
+
+    /// Returns the built-in A4 template
+    pub fn builtin() -> Self {
+        Self::from_toml(DEFAULT_SLIP_TEMPLATE, DEFAULT_SLIP_SOURCE)
+            .expect("built-in slip template is valid")
+    }
+}
+
+fn default_page() -> PageSize {
+    PageSize::A4
+}
+
+fn default_font_size() -> f32 {
+    10.0
+}
+
+fn default_label_width() -> f32 {
+    35.0
+}
+
+fn default_thickness() -> f32 {
+    0.5
+}
+
+/// Fields that can be listed in a `fields` element
+const ROW_FIELDS: [&str; 10] = [
+    "amount",
+    "iban",
+    "swift",
+    "vs",
+    "ks",
+    "ss",
+    "due_date",
+    "date",
+    "invoice_id",
+    "note",
+];
+
+/// Fields that can only be used as text placeholders
+const TEXT_FIELDS: [&str; 4] = [
+    "currency",
+    "beneficiary_name",
+    "beneficiary_address_1",
+    "beneficiary_address_2",
+];
+
+/// Returns the placeholder names in a text, without braces
+fn placeholders(text: &str) -> impl Iterator<Item = &str> {
+    text.split('{')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+}
+
+/// Returns whether a placeholder names a label or a payment field
+fn is_placeholder(name: &str) -> bool {
+    match name.strip_prefix("label.") {
+        Some(label) => label_key(label).is_some(),
+        None => TEXT_FIELDS.contains(&name) || ROW_FIELDS.contains(&name),
+    }
+}
+
+/// Returns the catalog key of a label placeholder
+fn label_key(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "title" => "slip.title",
+        "beneficiary" => "slip.beneficiary",
+        "amount" => "caption.amount",
+        "iban" => "caption.iban",
+        "swift" => "slip.swift",
+        "vs" => "caption.vs",
+        "ks" => "caption.ks",
+        "ss" => "caption.ss",
+        "due_date" => "caption.due_date",
+        "date" => "slip.date",
+        "invoice_id" => "slip.invoice_id",
+        "note" => "slip.note",
+        "scan" => "slip.scan",
+        _ => return None,
+    })
+}
+
+/// Returns the formatted value of a field, `None` when the payment doesn't have it
+fn field_value(payment: &PaymentRequest, name: &str, locale: Locale) -> Option<String> {
+    match name {
+        "amount" => Some(format_amount(payment.amount, locale)),
+        "currency" => Some(payment.currency.clone()),
+        "iban" => payment_iban(payment).map(group_iban),
+        "swift" => payment.swift.clone().or_else(|| {
+            payment
+                .bank_accounts
+                .as_ref()
+                .and_then(|accounts| accounts.first())
+                .and_then(|account| account.swift.clone())
+        }),
+        "vs" => payment.variable_symbol.clone(),
+        "ks" => payment.constant_symbol.clone(),
+        "ss" => payment.specific_symbol.clone(),
+        "due_date" => payment
+            .payment_due_date
+            .map(|date| format_date(date, locale)),
+        "date" => payment.date.map(|date| format_date(date, locale)),
+        "invoice_id" => payment.invoice_id.clone(),
+        "note" => payment.note.clone(),
+        "beneficiary_name" => payment.beneficiary_name.clone(),
+        "beneficiary_address_1" => payment.beneficiary_address_1.clone(),
+        "beneficiary_address_2" => payment.beneficiary_address_2.clone(),
+        _ => None,
+    }
+    .filter(|value| !value.is_empty())
+}
+
+/// Replaces the placeholders of a text, leaving out lines whose fields are all missing
+fn fill_text(text: &str, payment: &PaymentRequest, locale: Locale) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| {
+            let mut filled = String::new();
+            let (mut fields, mut present) = (0, 0);
+            let mut rest = line;
+            while let Some((before, after)) = rest.split_once('{') {
+                let Some((name, after)) = after.split_once('}') else {
+                    break;
+                };
+                filled.push_str(before);
+                match name.strip_prefix("label.").and_then(label_key) {
+                    Some(key) => filled.push_str(translate(locale, key)),
+                    None => {
+                        fields += 1;
+                        if let Some(value) = field_value(payment, name, locale) {
+                            present += 1;
+                            filled.push_str(&value);
+                        }
+                    }
+                }
+                rest = after;
+            }
+            filled.push_str(rest);
+            (fields == 0 || present > 0).then_some(filled)
+        })
+        .collect()
+}
+
+/// Payment slip templates available to requests
+#[derive(Debug, Clone)]
+pub struct SlipTemplateRegistry {
+    templates: HashMap<String, SlipTemplate>,
+}
+
+impl Default for SlipTemplateRegistry {
+    /// Registry with the built-in template
+    fn default() -> Self {
+        let mut registry = Self {
+            templates: HashMap::new(),
+        };
+        registry.insert(SlipTemplate::builtin());
+        registry
+    }
+}
+
+impl SlipTemplateRegistry {
+    /// Loads every `<name>.toml` of a directory next to the built-in template
+    ///
+    /// A `default.toml` replaces the built-in template.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let mut paths = fs::read_dir(dir.as_ref())?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<PathBuf>>>()?;
+        paths.sort();
+
+        let mut registry = Self::default();
+        for path in paths.iter().filter(|p| has_extension(p, "toml")) {
+            let template = SlipTemplate::from_toml(&file_stem(path), &fs::read_to_string(path)?)?;
+            registry.insert(template);
+        }
+        Ok(registry)
+    }
+
+    /// Adds or replaces a template
+    pub fn insert(&mut self, template: SlipTemplate) {
+        self.templates.insert(template.name.clone(), template);
+    }
+
+    /// Selects the template for a request: explicit name, then the `default` template
+    pub fn resolve(&self, requested: Option<&str>) -> Result<&SlipTemplate> {
+        let name = requested.unwrap_or(DEFAULT_SLIP_TEMPLATE);
+        self.templates
+            .get(name)
+            .ok_or_else(|| PayBySquareError::UnknownSlipTemplate(name.to_string()))
+    }
+}
+
+/// Generates a payment slip PDF, validating with the standard profile
+///
+/// See [`generate_payment_slip_with_profile`].
+pub fn generate_payment_slip(
+    payment: &PaymentRequest,
+    opts: &QrOptions,
+    template: &SlipTemplate,
+) -> Result<Vec<u8>> {
+    generate_payment_slip_with_profile(payment, opts, template, &ValidationProfile::standard())
+        .map(|(pdf, _)| pdf)
+}
+
+/// Generates a payment slip PDF laid out by a template
+///
+/// Text is set in the embedded Noto Sans font; labels, amounts and dates follow
+/// `opts.caption_locale`. Codes use the colours, quiet zone, frame variant and
+/// logo of `opts` and are drawn with vector modules. With `opts.verify` the
+/// code is checked on a raster rendering of the same symbol.
+pub fn generate_payment_slip_with_profile(
+    payment: &PaymentRequest,
+    opts: &QrOptions,
+    template: &SlipTemplate,
+    profile: &ValidationProfile,
+) -> Result<(Vec<u8>, Vec<ValidationWarning>)> {
+    let warnings = validate_payment_request_with_profile(payment, profile)?;
+    validate_qr_options(opts)?;
+
+    let code = generate_pay_by_square_code(payment)?;
+    let locale = opts.caption_locale.unwrap_or_default();
+    let (page_width, page_height) = template.page.dimensions_mm().unwrap_or((210.0, 297.0));
+
+    let (doc, page, layer) =
+        PdfDocument::new("PAY by square", Mm(page_width), Mm(page_height), "Slip");
+    let layer = doc.get_page(page).get_layer(layer);
+    let has_text = |bold: bool| {
+        template.elements.iter().any(|element| match element {
+            SlipElement::Text { bold: b, .. } => *b == bold,
+            SlipElement::Fields { .. } => !bold,
+            _ => false,
+        })
+    };
+    let regular = has_text(false).then(|| embed_font(&doc)).transpose()?;
+    let bold = has_text(true)
+        .then(|| {
+            doc.add_external_font(notosans::BOLD_TTF)
+                .map_err(|e| PayBySquareError::PdfError(e.to_string()))
+        })
+        .transpose()?;
+
+    // Template coordinates grow downwards from the top-left corner
+    let page_y = |y: f32| page_height - y;
+
+    for element in &template.elements {
+        match element {
+            SlipElement::Text {
+                x,
+                y,
+                text,
+                size,
+                bold: is_bold,
+                color,
+                align,
+                width,
+            } => {
+                let weight = if *is_bold {
+                    Weight::Bold
+                } else {
+                    Weight::Regular
+                };
+                let font = if *is_bold { &bold } else { &regular };
+                let lines = fill_text(text, payment, locale);
+                let size = size.unwrap_or(template.font_size);
+                let size = fit_size(&lines, weight, size, *width);
+                layer.set_fill_color(pdf_color(color.unwrap_or(template.color)));
+                for (i, line) in lines.iter().enumerate() {
+                    let line_width = text_width(line, weight, size);
+                    let left = match align {
+                        Align::Left => *x,
+                        Align::Center => x - line_width / 2.0,
+                        Align::Right => x - line_width,
+                    };
+                    let baseline = y + i as f32 * size * MM_PER_PT * LINE_HEIGHT;
+                    if let Some(font) = font {
+                        layer.use_text(line, size, Mm(left), Mm(page_y(baseline)), font);
+                    }
+                }
+            }
+            SlipElement::Fields {
+                x,
+                y,
+                fields,
+                label_width,
+                size,
+                color,
+                label_color,
+                width,
+            } => {
+                let rows: Vec<(&str, String)> = fields
+                    .iter()
+                    .filter_map(|field| {
+                        let value = field_value(payment, field, locale)?;
+                        let value = match field.as_str() {
+                            "amount" => format!("{} {}", value, payment.currency),
+                            _ => value,
+                        };
+                        let label = label_key(field).map(|key| translate(locale, key))?;
+                        Some((label, value))
+                    })
+                    .collect();
+                let size = size.unwrap_or(template.font_size);
+                let values: Vec<String> = rows.iter().map(|(_, value)| value.clone()).collect();
+                let value_size = fit_size(
+                    &values,
+                    Weight::Regular,
+                    size,
+                    width.map(|width| width - label_width),
+                );
+                let Some(font) = &regular else {
+                    continue;
+                };
+                for (i, (label, value)) in rows.iter().enumerate() {
+                    let baseline = page_y(y + i as f32 * size * MM_PER_PT * LINE_HEIGHT);
+                    layer.set_fill_color(pdf_color(label_color.unwrap_or(template.color)));
+                    layer.use_text(*label, size, Mm(*x), Mm(baseline), font);
+                    layer.set_fill_color(pdf_color(color.unwrap_or(template.color)));
+                    layer.use_text(value, value_size, Mm(x + label_width), Mm(baseline), font);
+                }
+            }
+            SlipElement::Line {
+                x1,
+                y1,
+                x2,
+                y2,
+                color,
+                thickness,
+            } => {
+                layer.set_outline_color(pdf_color(color.unwrap_or(template.color)));
+                layer.set_outline_thickness(*thickness);
+                layer.add_line(Line {
+                    points: vec![
+                        (Point::new(Mm(*x1), Mm(page_y(*y1))), false),
+                        (Point::new(Mm(*x2), Mm(page_y(*y2))), false),
+                    ],
+                    is_closed: false,
+                });
+            }
+            SlipElement::Rect {
+                x,
+                y,
+                width,
+                height,
+                stroke,
+                fill,
+                thickness,
+            } => draw_rect(
+                &layer,
+                (*x, page_y(*y), *width, *height),
+                *stroke,
+                *fill,
+                *thickness,
+            ),
+            SlipElement::Qr { x, y, size, frame } => {
+                let opts = QrOptions {
+                    with_frame: frame.unwrap_or(true),
+                    ..opts.clone()
+                };
+                let (qr, logo) = build_symbol(&code, &opts)?;
+                let layout = CodeLayout::fit(&qr, &opts, *size);
+                draw_code(&layer, &qr, logo.as_ref(), &opts, &layout, *x, page_y(*y));
+            }
+        }
+    }
+
+    if opts.verify {
+        verify_rendered(
+            &image::DynamicImage::ImageRgba8(render_qr_image(&code, opts)?),
+            &code,
+            payment,
+        )?;
+    }
+
+    let pdf = doc
+        .save_to_bytes()
+        .map_err(|e| PayBySquareError::PdfError(e.to_string()))?;
+    Ok((pdf, warnings))
+}
+
+/// Width of a line of text in millimetres at `size` points
+fn text_width(text: &str, weight: Weight, size: f32) -> f32 {
+    outline_text(&[TextRun { weight, text }], size * MM_PER_PT).width
+}
+
+/// Returns `size`, reduced so the widest line fits `width` millimetres
+fn fit_size(lines: &[String], weight: Weight, size: f32, width: Option<f32>) -> f32 {
+    let Some(width) = width else {
+        return size;
+    };
+    let widest = lines
+        .iter()
+        .map(|line| text_width(line, weight, size))
+        .fold(0.0, f32::max);
+    if widest > width {
+        size * width / widest
+    } else {
+        size
+    }
+}
+
+/// Draws a rectangle with its top-left corner at `(x, top)` in page coordinates
+fn draw_rect(
+    layer: &PdfLayerReference,
+    (x, top, width, height): (f32, f32, f32, f32),
+    stroke: Option<RgbaColor>,
+    fill: Option<RgbaColor>,
+    thickness: f32,
+) {
+    let mode = match (stroke, fill) {
+        (Some(_), Some(_)) => PaintMode::FillStroke,
+        (None, Some(_)) => PaintMode::Fill,
+        (_, None) => PaintMode::Stroke,
+    };
+    layer.set_outline_color(pdf_color(stroke.unwrap_or(RgbaColor::black())));
+    layer.set_outline_thickness(thickness);
+    if let Some(fill) = fill {
+        layer.set_fill_color(pdf_color(fill));
+    }
+    let corners = [
+        (x, top),
+        (x + width, top),
+        (x + width, top - height),
+        (x, top - height),
+    ];
+    layer.add_polygon(Polygon {
+        rings: vec![corners
+            .iter()
+            .map(|&(x, y)| (Point::new(Mm(x), Mm(y)), false))
+            .collect()],
+        mode,
+        winding_order: WindingOrder::NonZero,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn payment() -> PaymentRequest {
+        serde_json::from_value(serde_json::json!({
+            "amount": 1234.5,
+            "iban": "SK9611000000002918599669",
+            "variable_symbol": "1234567890",
+            "beneficiary_name": "Acme s.r.o.",
+            "beneficiary_address_1": "Hlavná 1"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_generate_payment_slip() {
+        let template = SlipTemplate::builtin();
+        assert_eq!(template.page, PageSize::A4);
+        assert!(template
+            .elements
+            .iter()
+            .any(|e| matches!(e, SlipElement::Qr { .. })));
+
+        let pdf = generate_payment_slip(&payment(), &QrOptions::default(), &template).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/MediaBox[0 0 595.27563 841.88983]"));
+        // Regular and bold fonts are embedded
+        assert_eq!(text.matches("/FontFile2").count(), 2);
+    }
+
+    #[test]
+    fn test_fill_text() {
+        let mut payment = payment();
+        payment.payment_due_date = NaiveDate::from_ymd_opt(2026, 3, 31);
+        let text = "{label.title}\n{beneficiary_name}\n{beneficiary_address_2}\nVS {vs}, {ss}";
+        assert_eq!(
+            fill_text(text, &payment, Locale::Sk),
+            ["Platobný príkaz", "Acme s.r.o.", "VS 1234567890, "]
+        );
+        assert_eq!(
+            fill_text("{label.due_date}: {due_date}", &payment, Locale::En),
+            ["Due date: 2026-03-31"]
+        );
+    }
+
+    #[test]
+    fn test_template_errors() {
+        let error = |source: &str| SlipTemplate::from_toml("custom", source).unwrap_err();
+        for source in [
+            "page = \"fit\"",
+            "[[element]]\ntype = \"text\"\nx = 0\ny = 0\ntext = \"{iban} {unknown}\"",
+            "[[element]]\ntype = \"fields\"\nx = 0\ny = 0\nfields = [\"beneficiary_name\"]",
+            "[[element]]\ntype = \"qr\"\nx = 0\ny = 0\nsize = 0",
+            "[[element]]\ntype = \"circle\"\nx = 0\ny = 0",
+        ] {
+            assert!(
+                matches!(error(source), PayBySquareError::ConfigError(_)),
+                "{}",
+                source
+            );
+        }
+
+        let registry = SlipTemplateRegistry::default();
+        assert_eq!(registry.resolve(None).unwrap().name, "default");
+        assert!(matches!(
+            registry.resolve(Some("missing")),
+            Err(PayBySquareError::UnknownSlipTemplate(name)) if name == "missing"
+        ));
+    }
+}
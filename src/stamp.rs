@@ -0,0 +1,504 @@
+use crate::errors::{PayBySquareError, Result};
+use crate::generator::generate_pay_by_square_code;
+use crate::models::{PaymentRequest, QrOptions, StampPlacement};
+use crate::pdf::{draw_code, CodeLayout};
+use crate::profiles::ValidationProfile;
+use crate::qr::{build_symbol, render_qr_image};
+use crate::validation::{
+    validate_payment_request_with_profile, validate_qr_options, validate_stamp_placement,
+    ValidationWarning,
+};
+use crate::verify::verify_rendered;
+use lopdf::content::Content;
+use lopdf::{dictionary, Dictionary, Document, IncrementalDocument, Object, ObjectId, Stream};
+use printpdf::{Mm, PdfDocument};
+use std::collections::BTreeMap;
+
+/// Points per millimetre
+const PT_PER_MM: f32 = 72.0 / 25.4;
+
+/// Resource name of the stamped code, numbered when the page already uses it
+const XOBJECT_NAME: &str = "PayBySquare";
+
+/// Stamps a PayBySquare code onto a page of a PDF, validating with the standard profile
+///
+/// See [`stamp_pdf_with_profile`].
+pub fn stamp_pdf(
+    pdf: &[u8],
+    payment: &PaymentRequest,
+    opts: &QrOptions,
+    placement: &StampPlacement,
+) -> Result<Vec<u8>> {
+    stamp_pdf_with_profile(
+        pdf,
+        payment,
+        opts,
+        placement,
+        &ValidationProfile::standard(),
+    )
+    .map(|(pdf, _)| pdf)
+}
+
+/// Stamps a PayBySquare code onto a page of a PDF
+///
+/// The code is drawn with vector modules, in the drawn frame when
+/// `opts.with_frame` is set, `placement.size_mm` wide with its top-left corner
+/// `x_mm` and `y_mm` from the top-left corner of the page's visible area. The
+/// position is measured on the unrotated page. The code is appended as an
+/// incremental update, so the bytes of the original document are kept as they
+/// are. Encrypted documents are not supported.
+pub fn stamp_pdf_with_profile(
+    pdf: &[u8],
+    payment: &PaymentRequest,
+    opts: &QrOptions,
+    placement: &StampPlacement,
+    profile: &ValidationProfile,
+) -> Result<(Vec<u8>, Vec<ValidationWarning>)> {
+    let warnings = validate_payment_request_with_profile(payment, profile)?;
+    validate_qr_options(opts)?;
+    validate_stamp_placement(placement)?;
+
+    let document = Document::load_mem(pdf).map_err(invalid_pdf)?;
+    if document.is_encrypted() {
+        return Err(PayBySquareError::InvalidPdf {
+            key: "pdf.encrypted",
+        });
+    }
+
+    let pages = document.get_pages();
+    let page_id =
+        *pages
+            .get(&placement.page)
+            .ok_or_else(|| PayBySquareError::OptionOutOfRange {
+                option: "page".to_string(),
+                min: 1.0,
+                max: pages.len() as f32,
+            })?;
+
+    // Visible page area in points
+    let [left, bottom, right, top] = page_box(&document, page_id)?;
+    let size = placement.size_mm;
+    let page_width = (right - left) / PT_PER_MM;
+    let page_height = (top - bottom) / PT_PER_MM;
+    check_position("x_mm", placement.x_mm, page_width - size)?;
+    check_position("y_mm", placement.y_mm, page_height - size)?;
+
+    let code = generate_pay_by_square_code(payment)?;
+    let stamp = render_stamp(&code, opts, size)?;
+
+    let mut doc = IncrementalDocument::create_from(pdf.to_vec(), document);
+    let form = import_form(
+        &stamp,
+        &mut doc.new_document,
+        [
+            left + placement.x_mm * PT_PER_MM,
+            top - (placement.y_mm + size) * PT_PER_MM,
+        ],
+    )?;
+    add_to_page(&mut doc, page_id, form)?;
+
+    if opts.verify {
+        verify_rendered(
+            &image::DynamicImage::ImageRgba8(render_qr_image(&code, opts)?),
+            &code,
+            payment,
+        )?;
+    }
+
+    let mut stamped = Vec::new();
+    doc.save_to(&mut stamped)
+        .map_err(|e| PayBySquareError::PdfError(e.to_string()))?;
+    Ok((stamped, warnings))
+}
+
+/// Parser errors aren't passed on, their text is neither localized nor useful to clients
+fn invalid_pdf(_: lopdf::Error) -> PayBySquareError {
+    PayBySquareError::InvalidPdf {
+        key: "pdf.unreadable",
+    }
+}
+
+fn check_position(option: &str, value: f32, max: f32) -> Result<()> {
+    if !(0.0..=max).contains(&value) {
+        return Err(PayBySquareError::OptionOutOfRange {
+            option: option.to_string(),
+            min: 0.0,
+            max: max.max(0.0),
+        });
+    }
+    Ok(())
+}
+
+/// Returns the crop box of a page, falling back to the media box, as `[left, bottom, right, top]`
+fn page_box(document: &Document, page_id: ObjectId) -> Result<[f32; 4]> {
+    let rect = [b"CropBox".as_slice(), b"MediaBox"]
+        .iter()
+        .find_map(|key| inherited(document, page_id, key))
+        .ok_or(PayBySquareError::InvalidPdf {
+            key: "pdf.no_media_box",
+        })?;
+    let values = document
+        .dereference(rect)
+        .and_then(|(_, rect)| rect.as_array())
+        .map_err(invalid_pdf)?
+        .iter()
+        .map(|value| {
+            document
+                .dereference(value)
+                .and_then(|(_, value)| value.as_float())
+        })
+        .collect::<lopdf::Result<Vec<f32>>>()
+        .map_err(invalid_pdf)?;
+    let [x1, y1, x2, y2] = values[..] else {
+        return Err(PayBySquareError::InvalidPdf {
+            key: "pdf.page_box",
+        });
+    };
+    Ok([x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)])
+}
+
+/// Returns a page attribute, looking it up in the page tree when the page doesn't set it
+fn inherited<'a>(document: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+    let mut node = document.get_dictionary(page_id).ok()?;
+    // Page trees are shallow; the limit guards against cycles
+    for _ in 0..64 {
+        if let Ok(value) = node.get(key) {
+            return Some(value);
+        }
+        let parent = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+        node = document.get_dictionary(parent).ok()?;
+    }
+    None
+}
+
+/// Draws the code on a page of its own size and returns that document
+fn render_stamp(code: &str, opts: &QrOptions, size: f32) -> Result<Document> {
+    let (qr, logo) = build_symbol(code, opts)?;
+    let layout = CodeLayout::fit(&qr, opts, size);
+
+    let (doc, page, layer) = PdfDocument::new("PAY by square", Mm(size), Mm(size), "QR");
+    let layer = doc.get_page(page).get_layer(layer);
+    draw_code(&layer, &qr, logo.as_ref(), opts, &layout, 0.0, size);
+
+    let bytes = doc
+        .save_to_bytes()
+        .map_err(|e| PayBySquareError::PdfError(e.to_string()))?;
+    Document::load_mem(&bytes).map_err(|e| PayBySquareError::PdfError(e.to_string()))
+}
+
+/// Copies the page of the stamp into a form XObject of `target`, placed at `origin` in points
+fn import_form(stamp: &Document, target: &mut Document, origin: [f32; 2]) -> Result<ObjectId> {
+    let pdf_error = |e: lopdf::Error| PayBySquareError::PdfError(e.to_string());
+    let page_id = *stamp
+        .get_pages()
+        .get(&1)
+        .ok_or_else(|| PayBySquareError::PdfError("stamp has no page".to_string()))?;
+
+    // printpdf wraps the layer in optional content; the form draws without it
+    let mut content =
+        Content::decode(&stamp.get_page_content(page_id).map_err(pdf_error)?).map_err(pdf_error)?;
+    if content.operations.first().map(|op| op.operator.as_str()) == Some("BDC") {
+        content.operations.remove(0);
+    }
+    if content.operations.last().map(|op| op.operator.as_str()) == Some("EMC") {
+        content.operations.pop();
+    }
+
+    let mut resources = match stamp.get_page_resources(page_id) {
+        (Some(resources), _) => resources.clone(),
+        (None, ids) => match ids.first() {
+            Some(&id) => stamp.get_dictionary(id).map_err(pdf_error)?.clone(),
+            None => Dictionary::new(),
+        },
+    };
+    resources.remove(b"Properties");
+    let resources = import_object(stamp, &resources.into(), target, &mut BTreeMap::new())?;
+
+    let size = stamp
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"MediaBox"))
+        .and_then(Object::as_array)
+        .map_err(pdf_error)?
+        .clone();
+    let mut form = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => size,
+            "Matrix" => vec![1.into(), 0.into(), 0.into(), 1.into(), origin[0].into(), origin[1].into()],
+            "Resources" => resources,
+        },
+        content.encode().map_err(pdf_error)?,
+    );
+    form.compress().map_err(pdf_error)?;
+    Ok(target.add_object(form))
+}
+
+/// Copies an object into `target`, giving referenced objects new numbers
+fn import_object(
+    source: &Document,
+    object: &Object,
+    target: &mut Document,
+    imported: &mut BTreeMap<ObjectId, ObjectId>,
+) -> Result<Object> {
+    Ok(match object {
+        Object::Reference(id) => {
+            if let Some(&new_id) = imported.get(id) {
+                return Ok(Object::Reference(new_id));
+            }
+            let new_id = target.new_object_id();
+            imported.insert(*id, new_id);
+            let referenced = source
+                .get_object(*id)
+                .map_err(|e| PayBySquareError::PdfError(e.to_string()))?;
+            let copy = import_object(source, referenced, target, imported)?;
+            target.set_object(new_id, copy);
+            Object::Reference(new_id)
+        }
+        Object::Array(items) => Object::Array(
+            items
+                .iter()
+                .map(|item| import_object(source, item, target, imported))
+                .collect::<Result<_>>()?,
+        ),
+        Object::Dictionary(dict) => {
+            Object::Dictionary(import_dictionary(source, dict, target, imported)?)
+        }
+        Object::Stream(stream) => {
+            let dict = import_dictionary(source, &stream.dict, target, imported)?;
+            Object::Stream(Stream::new(dict, stream.content.clone()))
+        }
+        other => other.clone(),
+    })
+}
+
+fn import_dictionary(
+    source: &Document,
+    dict: &Dictionary,
+    target: &mut Document,
+    imported: &mut BTreeMap<ObjectId, ObjectId>,
+) -> Result<Dictionary> {
+    let mut copy = Dictionary::new();
+    for (key, value) in dict.iter() {
+        copy.set(key.clone(), import_object(source, value, target, imported)?);
+    }
+    Ok(copy)
+}
+
+/// Draws a form XObject over the existing content of a page
+///
+/// The page gets its own copy of its resources, so resources shared with other
+/// pages are left alone. The existing content is wrapped in `q`/`Q` so graphics
+/// state it leaves behind doesn't move the stamp.
+fn add_to_page(doc: &mut IncrementalDocument, page_id: ObjectId, form: ObjectId) -> Result<()> {
+    let previous = doc.get_prev_documents();
+    let resolve = |object: &Object| -> Result<Object> {
+        previous
+            .dereference(object)
+            .map(|(_, object)| object.clone())
+            .map_err(invalid_pdf)
+    };
+
+    let mut resources = match inherited(previous, page_id, b"Resources") {
+        Some(resources) => resolve(resources)?.as_dict().map_err(invalid_pdf)?.clone(),
+        None => Dictionary::new(),
+    };
+    let mut xobjects = match resources.get(b"XObject") {
+        Ok(xobjects) => resolve(xobjects)?.as_dict().map_err(invalid_pdf)?.clone(),
+        Err(_) => Dictionary::new(),
+    };
+    let name = (1..)
+        .map(|n| match n {
+            1 => XOBJECT_NAME.to_string(),
+            n => format!("{}{}", XOBJECT_NAME, n),
+        })
+        .find(|name| !xobjects.has(name.as_bytes()))
+        .expect("unused resource name");
+    xobjects.set(name.clone(), Object::Reference(form));
+    resources.set("XObject", xobjects);
+
+    let mut contents = match previous
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Contents"))
+    {
+        Ok(Object::Reference(id)) => match previous.get_object(*id) {
+            Ok(Object::Array(items)) => items.clone(),
+            _ => vec![Object::Reference(*id)],
+        },
+        Ok(Object::Array(items)) => items.clone(),
+        _ => Vec::new(),
+    };
+
+    let new_document = &mut doc.new_document;
+    let save = new_document.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec()));
+    let draw = format!("Q\nq\n/{} Do\nQ\n", name);
+    let draw = new_document.add_object(Stream::new(Dictionary::new(), draw.into_bytes()));
+    contents.insert(0, Object::Reference(save));
+    contents.push(Object::Reference(draw));
+
+    doc.opt_clone_object_to_new_document(page_id)
+        .map_err(invalid_pdf)?;
+    let page = doc
+        .new_document
+        .get_dictionary_mut(page_id)
+        .map_err(invalid_pdf)?;
+    page.set("Resources", resources);
+    page.set("Contents", contents);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StampPlacement;
+
+    fn payment() -> PaymentRequest {
+        serde_json::from_value(serde_json::json!({
+            "amount": 100.5,
+            "iban": "SK9611000000002918599669",
+            "variable_symbol": "1234567890"
+        }))
+        .unwrap()
+    }
+
+    /// Two A4 pages with text, sharing their resources
+    fn invoice() -> Vec<u8> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let kids: Vec<Object> = (1..=2)
+            .map(|n| {
+                let content = format!("BT /F1 12 Tf 72 770 Td (Invoice page {}) Tj ET", n);
+                let content_id = doc.add_object(Stream::new(Dictionary::new(), content.into()));
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                })
+                .into()
+            })
+            .collect();
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => 2,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+
+        let mut pdf = Vec::new();
+        doc.save_to(&mut pdf).unwrap();
+        pdf
+    }
+
+    fn placement(page: u32) -> StampPlacement {
+        StampPlacement {
+            page,
+            x_mm: 150.0,
+            y_mm: 230.0,
+            size_mm: 40.0,
+        }
+    }
+
+    #[test]
+    fn test_stamp_pdf() {
+        let invoice = invoice();
+        let stamped =
+            stamp_pdf(&invoice, &payment(), &QrOptions::default(), &placement(2)).unwrap();
+
+        // Incremental update keeps the original bytes
+        assert!(stamped.starts_with(&invoice));
+
+        let doc = Document::load_mem(&stamped).unwrap();
+        let pages = doc.get_pages();
+        assert_eq!(pages.len(), 2);
+
+        let content = String::from_utf8(doc.get_page_content(pages[&2]).unwrap()).unwrap();
+        assert!(content.starts_with("q\n"));
+        assert!(content.contains("(Invoice page 2) Tj"));
+        assert!(content.ends_with("Q\nq\n/PayBySquare Do\nQ\n"));
+
+        let (resources, _) = doc.get_page_resources(pages[&2]);
+        let resources = resources.unwrap();
+        assert!(resources.get(b"Font").is_ok());
+        let form_id = resources
+            .get(b"XObject")
+            .and_then(Object::as_dict)
+            .and_then(|xobjects| xobjects.get(b"PayBySquare"))
+            .and_then(Object::as_reference)
+            .unwrap();
+        let form = doc.get_object(form_id).and_then(Object::as_stream).unwrap();
+        assert_eq!(
+            form.dict.get(b"Subtype").unwrap().as_name_str().unwrap(),
+            "Form"
+        );
+
+        // Top-left corner 150 mm from the left and 230 mm from the top of the page
+        let matrix: Vec<f32> = form
+            .dict
+            .get(b"Matrix")
+            .and_then(Object::as_array)
+            .unwrap()
+            .iter()
+            .map(|value| value.as_float().unwrap())
+            .collect();
+        assert!((matrix[4] - 150.0 * PT_PER_MM).abs() < 0.01);
+        assert!((matrix[5] - (842.0 - 270.0 * PT_PER_MM)).abs() < 0.01);
+
+        // Vector modules without the optional content wrapper
+        let drawing = String::from_utf8(form.decompressed_content().unwrap()).unwrap();
+        assert!(drawing.contains(" re"));
+        assert!(!drawing.contains("BDC"));
+
+        // The other page is untouched
+        let content = doc.get_page_content(pages[&1]).unwrap();
+        assert_eq!(content, b"BT /F1 12 Tf 72 770 Td (Invoice page 1) Tj ET");
+        assert!(doc.get_page_resources(pages[&1]).0.is_none());
+    }
+
+    #[test]
+    fn test_stamp_pdf_errors() {
+        let invoice = invoice();
+        let opts = QrOptions::default();
+        let err = stamp_pdf(b"not a pdf", &payment(), &opts, &placement(1)).unwrap_err();
+        assert!(matches!(
+            err,
+            PayBySquareError::InvalidPdf {
+                key: "pdf.unreadable"
+            }
+        ));
+        // Parser details stay out of the localized message
+        assert_eq!(
+            err.localized(crate::i18n::Locale::Sk),
+            "Neplatný PDF dokument: dokument sa nedá prečítať"
+        );
+        assert!(matches!(
+            stamp_pdf(&invoice, &payment(), &opts, &placement(3)),
+            Err(PayBySquareError::OptionOutOfRange { option, max, .. }) if option == "page" && max == 2.0
+        ));
+
+        let outside = StampPlacement {
+            x_mm: 180.0,
+            ..placement(1)
+        };
+        assert!(matches!(
+            stamp_pdf(&invoice, &payment(), &opts, &outside),
+            Err(PayBySquareError::OptionOutOfRange { option, .. }) if option == "x_mm"
+        ));
+    }
+}
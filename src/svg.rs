@@ -0,0 +1,252 @@
+use crate::errors::Result;
+use crate::frame::{Contour, FrameLayout, Segment};
+use crate::logo::{LogoArea, PlacedLogo};
+use crate::models::{QrOptions, RgbaColor};
+use crate::qr::{build_symbol, dark_runs};
+use qrcode::QrCode;
+use std::fmt::Write;
+
+/// Generates a QR code as SVG, optionally inside the "by square" frame
+///
+/// The image is `opts.qr_size` units wide; `opts.quiet_zone` modules surround the symbol.
+/// Translucent colours are written with `fill-opacity`; a logo is embedded as a data URI.
+pub fn generate_qr_svg(code: &str, opts: &QrOptions) -> Result<String> {
+    let (qr, logo) = build_symbol(code, opts)?;
+    Ok(render_svg(&qr, logo.as_ref(), opts))
+}
+
+/// Renders an encoded symbol as SVG, see [`generate_qr_svg`]
+pub(crate) fn render_svg(qr: &QrCode, logo: Option<&PlacedLogo>, opts: &QrOptions) -> String {
+    let size = opts.qr_size;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{size}" height="{size}" viewBox="0 0 {size} {size}">"#
+    )
+    .unwrap();
+
+    if opts.with_frame {
+        let layout = FrameLayout::new(size as f32, opts.frame_variant);
+        writeln!(
+            svg,
+            r#"<rect width="{size}" height="{size}" {}/>"#,
+            paint(opts.background)
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<path {} d="{}"/>"#,
+            paint(opts.frame_color),
+            contour_path(&layout.contours)
+        )
+        .unwrap();
+        write_symbol(
+            &mut svg,
+            qr,
+            opts,
+            logo,
+            (layout.qr_x, layout.qr_y, layout.qr_size),
+        );
+    } else {
+        write_symbol(&mut svg, qr, opts, logo, (0.0, 0.0, size as f32));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Writes the symbol as a nested SVG in module coordinates
+fn write_symbol(
+    svg: &mut String,
+    qr: &QrCode,
+    opts: &QrOptions,
+    logo: Option<&PlacedLogo>,
+    (x, y, size): (f32, f32, f32),
+) {
+    let quiet_zone = opts.quiet_zone as usize;
+    let dimension = qr.width() + 2 * quiet_zone;
+    writeln!(
+        svg,
+        r#"<svg x="{}" y="{}" width="{}" height="{}" viewBox="0 0 {dimension} {dimension}" shape-rendering="crispEdges">"#,
+        num(x),
+        num(y),
+        num(size),
+        num(size)
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<rect width="{dimension}" height="{dimension}" {}/>"#,
+        paint(opts.background)
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<path {} d="{}"/>"#,
+        paint(opts.foreground),
+        module_path(qr, quiet_zone, logo.map(|logo| &logo.area))
+    )
+    .unwrap();
+    if let (Some(placed), Some(logo)) = (logo, opts.logo.clone()) {
+        let (x, y, width, height) = placed.bounds();
+        writeln!(
+            svg,
+            r#"<image x="{}" y="{}" width="{}" height="{}" href="{}"/>"#,
+            num(x + quiet_zone as f32),
+            num(y + quiet_zone as f32),
+            num(width),
+            num(height),
+            String::from(logo)
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+}
+
+/// Converts frame contours to path data
+fn contour_path(contours: &[Contour]) -> String {
+    let mut path = String::new();
+    for contour in contours {
+        write!(path, "M{} {}", num(contour.start.x), num(contour.start.y)).unwrap();
+        for segment in &contour.segments {
+            match *segment {
+                Segment::Line(p) => write!(path, "L{} {}", num(p.x), num(p.y)),
+                Segment::Quad(c, p) => {
+                    write!(path, "Q{} {} {} {}", num(c.x), num(c.y), num(p.x), num(p.y))
+                }
+                Segment::Cubic(c0, c1, p) => write!(
+                    path,
+                    "C{} {} {} {} {} {}",
+                    num(c0.x),
+                    num(c0.y),
+                    num(c1.x),
+                    num(c1.y),
+                    num(p.x),
+                    num(p.y)
+                ),
+            }
+            .unwrap();
+        }
+        path.push('Z');
+    }
+    path
+}
+
+/// Formats fill attributes for a colour
+fn paint(color: RgbaColor) -> String {
+    match color.0[3] {
+        0xFF => format!(r#"fill="{}""#, color.rgb_hex()),
+        _ => format!(
+            r#"fill="{}" fill-opacity="{}""#,
+            color.rgb_hex(),
+            num(color.opacity())
+        ),
+    }
+}
+
+/// Formats a coordinate with at most two decimals
+fn num(value: f32) -> String {
+    let formatted = format!("{:.2}", value);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+/// Builds a single path with horizontal runs of dark modules merged into rectangles
+fn module_path(qr: &QrCode, quiet_zone: usize, cleared: Option<&LogoArea>) -> String {
+    let mut path = String::new();
+    for (x, y, length) in dark_runs(qr, cleared) {
+        write!(
+            path,
+            "M{} {}h{}v1h-{}z",
+            x + quiet_zone,
+            y + quiet_zone,
+            length,
+            length
+        )
+        .unwrap();
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{FrameVariant, Logo};
+    use crate::qr::build_qr_code;
+
+    fn options(with_frame: bool) -> QrOptions {
+        QrOptions {
+            with_frame,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_generate_qr_svg() {
+        let svg = generate_qr_svg("TEST", &options(false)).unwrap();
+        insta::assert_snapshot!(svg);
+    }
+
+    #[test]
+    fn test_generate_qr_svg_with_frame() {
+        let svg = generate_qr_svg("TEST", &options(true)).unwrap();
+        insta::assert_snapshot!(svg);
+    }
+
+    #[test]
+    fn test_module_path_merges_runs() {
+        let qr = build_qr_code("TEST").unwrap();
+        // The top row starts with a 7-module finder pattern
+        assert!(module_path(&qr, 4, None).starts_with("M4 4h7v1h-7z"));
+        assert!(module_path(&qr, 0, None).starts_with("M0 0h7v1h-7z"));
+    }
+
+    #[test]
+    fn test_generate_qr_svg_invoice_frame() {
+        let pay = generate_qr_svg("TEST", &options(true)).unwrap();
+        let invoice = QrOptions {
+            frame_variant: FrameVariant::Invoice,
+            ..options(true)
+        };
+        let invoice = generate_qr_svg("TEST", &invoice).unwrap();
+        assert_ne!(pay, invoice);
+        assert!(invoice.contains(r##"fill="#0066CC""##));
+    }
+
+    #[test]
+    fn test_generate_qr_svg_colors() {
+        let opts = QrOptions {
+            foreground: "#003366".parse().unwrap(),
+            background: "#FFFFFF00".parse().unwrap(),
+            ..options(false)
+        };
+        let svg = generate_qr_svg("TEST", &opts).unwrap();
+        assert!(svg.contains(r##"fill="#FFFFFF" fill-opacity="0""##));
+        assert!(svg.contains(r##"<path fill="#003366" d="##));
+    }
+
+    #[test]
+    fn test_generate_qr_svg_logo() {
+        let logo = Logo::Image(
+            std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/frames/classic.png")).unwrap(),
+        );
+        let opts = QrOptions {
+            logo: Some(logo.clone()),
+            ..options(false)
+        };
+        let svg = generate_qr_svg("TEST", &opts).unwrap();
+        assert!(svg.contains(&format!(r#"href="{}""#, String::from(logo))));
+    }
+
+    #[test]
+    fn test_num() {
+        assert_eq!(num(300.0), "300");
+        assert_eq!(num(12.5), "12.5");
+        assert_eq!(num(1.0 / 3.0), "0.33");
+        assert_eq!(num(-0.001), "0");
+    }
+}
@@ -0,0 +1,438 @@
+use crate::errors::{PayBySquareError, Result};
+use crate::frame::{caption_contours, fill_contours};
+use crate::logo::PlacedLogo;
+use crate::models::QrOptions;
+use crate::qr::{build_symbol, encode_image, render_qr};
+use image::{imageops, RgbaImage};
+use qrcode::QrCode;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Share of the shorter template side taken by the symbol without configured placement
+const DEFAULT_QR_RATIO: f32 = 0.85;
+
+/// Template used when a request doesn't name one
+pub const DEFAULT_TEMPLATE: &str = "default";
+
+/// Square placement of the symbol in template pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Placement {
+    pub x: u32,
+    pub y: u32,
+    pub size: u32,
+}
+
+/// Rectangle in template pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Area {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// How a template is sized for a request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateScale {
+    /// Template is scaled so that the symbol is `qr_size` pixels wide
+    #[default]
+    Fit,
+    /// Template keeps its pixel size and the symbol fills the placement
+    Fixed,
+}
+
+/// Template metadata, read from `<name>.toml` in the template directory
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateMetadata {
+    /// Image file relative to the template directory (default: `<name>.png`)
+    pub image: Option<String>,
+    /// Symbol placement (default: centered at 85% of the shorter side)
+    pub qr: Option<Placement>,
+    #[serde(default)]
+    pub scale: TemplateScale,
+    /// Area where the "PAY by square" caption is drawn (default: none)
+    pub caption: Option<Area>,
+}
+
+/// Raster frame image with symbol placement
+#[derive(Debug, Clone)]
+pub struct FrameTemplate {
+    pub name: String,
+    image: RgbaImage,
+    qr: Placement,
+    scale: TemplateScale,
+    caption: Option<Area>,
+}
+
+impl FrameTemplate {
+    /// Creates a template from an encoded image and its metadata
+    pub fn new(name: &str, image_data: &[u8], metadata: TemplateMetadata) -> Result<Self> {
+        let image = image::load_from_memory(image_data)
+            .map_err(|e| {
+                PayBySquareError::ImageError(format!("Failed to load frame image: {}", e))
+            })?
+            .to_rgba8();
+        let (width, height) = image.dimensions();
+
+        let qr = metadata.qr.unwrap_or_else(|| {
+            let size = (width.min(height) as f32 * DEFAULT_QR_RATIO) as u32;
+            Placement {
+                x: (width - size) / 2,
+                y: (height - size) / 2,
+                size,
+            }
+        });
+        let areas = [
+            Some((qr.x, qr.y, qr.size, qr.size)),
+            metadata.caption.map(|a| (a.x, a.y, a.width, a.height)),
+        ];
+        for (x, y, w, h) in areas.into_iter().flatten() {
+            // Checked sums, so huge values from the metadata can't wrap around
+            let outside = |start: u32, length: u32, limit: u32| {
+                start.checked_add(length).is_none_or(|end| end > limit)
+            };
+            if w == 0 || h == 0 || outside(x, w, width) || outside(y, h, height) {
+                return Err(PayBySquareError::ConfigError(format!(
+                    "frame template '{}': area {}x{} at ({}, {}) does not fit the {}x{} image",
+                    name, w, h, x, y, width, height
+                )));
+            }
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            image,
+            qr,
+            scale: metadata.scale,
+            caption: metadata.caption,
+        })
+    }
+
+    /// Creates a template of fixed size with the symbol centered at 85%
+    pub fn from_image(name: &str, image_data: &[u8]) -> Result<Self> {
+        Self::new(
+            name,
+            image_data,
+            TemplateMetadata {
+                scale: TemplateScale::Fixed,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Renders a code into the template as PNG
+    ///
+    /// See [`FrameTemplate::render_image`].
+    pub fn render(&self, code: &str, opts: &QrOptions) -> Result<Vec<u8>> {
+        encode_image(&self.render_image(code, opts)?, image::ImageFormat::Png)
+    }
+
+    /// Renders a code into the template
+    ///
+    /// The symbol is rendered with whole-pixel modules into the placement. A
+    /// template scaled to `qr_size` grows when the placement is too small for
+    /// one pixel per module; a fixed template returns an error instead.
+    pub fn render_image(&self, code: &str, opts: &QrOptions) -> Result<RgbaImage> {
+        let (qr, logo) = build_symbol(code, opts)?;
+        self.render_symbol(&qr, logo.as_ref(), opts)
+    }
+
+    /// Renders an encoded symbol into the template, see [`FrameTemplate::render_image`]
+    pub(crate) fn render_symbol(
+        &self,
+        qr: &QrCode,
+        logo: Option<&PlacedLogo>,
+        opts: &QrOptions,
+    ) -> Result<RgbaImage> {
+        let dimension = qr.width() as u32 + 2 * opts.quiet_zone;
+        let factor = match self.scale {
+            TemplateScale::Fit => opts.qr_size.max(dimension) as f32 / self.qr.size as f32,
+            TemplateScale::Fixed => 1.0,
+        };
+        let qr_img = render_qr(qr, scale(self.qr.size, factor), opts, logo);
+        self.compose(&qr_img, factor, opts)
+    }
+
+    /// Width of the symbol placement when the template isn't scaled to `qr_size`
+    pub(crate) fn fixed_symbol_size(&self) -> Option<u32> {
+        match self.scale {
+            TemplateScale::Fit => None,
+            TemplateScale::Fixed => Some(self.qr.size),
+        }
+    }
+
+    /// Places a rendered symbol into the template scaled by `factor`
+    ///
+    /// The symbol is centred in the placement as it is, never resampled; one
+    /// larger than the placement is an error. The caption uses the frame
+    /// variant and colour of `opts`.
+    pub(crate) fn compose(
+        &self,
+        qr_img: &RgbaImage,
+        factor: f32,
+        opts: &QrOptions,
+    ) -> Result<RgbaImage> {
+        let qr_size = scale(self.qr.size, factor);
+        let symbol_size = qr_img.width().max(qr_img.height());
+        if symbol_size > qr_size {
+            return Err(PayBySquareError::SymbolTooLarge {
+                pixels: symbol_size,
+                max: qr_size,
+            });
+        }
+
+        let (width, height) = self.image.dimensions();
+        let mut result = if factor == 1.0 {
+            self.image.clone()
+        } else {
+            imageops::resize(
+                &self.image,
+                scale(width, factor),
+                scale(height, factor),
+                imageops::FilterType::Lanczos3,
+            )
+        };
+
+        // Smaller symbols (external images) are centred in the placement
+        let x = scale(self.qr.x, factor) + (qr_size - qr_img.width()) / 2;
+        let y = scale(self.qr.y, factor) + (qr_size - qr_img.height()) / 2;
+        imageops::overlay(&mut result, qr_img, x as i64, y as i64);
+
+        if let Some(area) = self.caption {
+            let contours = caption_contours(
+                opts.frame_variant,
+                area.x as f32 * factor,
+                area.y as f32 * factor,
+                area.width as f32 * factor,
+                area.height as f32 * factor,
+            );
+            fill_contours(&mut result, &contours, opts.frame_color);
+        }
+        Ok(result)
+    }
+}
+
+fn scale(value: u32, factor: f32) -> u32 {
+    (value as f32 * factor).round() as u32
+}
+
+/// Frame templates available to requests
+#[derive(Debug, Clone, Default)]
+pub struct FrameTemplateRegistry {
+    templates: HashMap<String, FrameTemplate>,
+}
+
+impl FrameTemplateRegistry {
+    /// Loads templates from a directory
+    ///
+    /// Every `<name>.toml` describes a template; a `<name>.png` without
+    /// metadata is loaded with the default placement.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut paths = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<PathBuf>>>()?;
+        paths.sort();
+
+        let mut registry = Self::default();
+
+        for path in paths.iter().filter(|p| has_extension(p, "toml")) {
+            let name = file_stem(path);
+            let metadata: TemplateMetadata =
+                toml::from_str(&fs::read_to_string(path)?).map_err(|e| {
+                    PayBySquareError::ConfigError(format!("frame template '{}': {}", name, e))
+                })?;
+            let image_path = match metadata.image {
+                Some(ref image) => dir.join(image),
+                None => dir.join(format!("{}.png", name)),
+            };
+            registry.insert(FrameTemplate::new(&name, &fs::read(image_path)?, metadata)?);
+        }
+
+        for path in paths.iter().filter(|p| has_extension(p, "png")) {
+            let name = file_stem(path);
+            if !registry.templates.contains_key(&name) {
+                let template = FrameTemplate::new(&name, &fs::read(path)?, Default::default())?;
+                registry.insert(template);
+            }
+        }
+
+        Ok(registry)
+    }
+
+    /// Adds or replaces a template
+    pub fn insert(&mut self, template: FrameTemplate) {
+        self.templates.insert(template.name.clone(), template);
+    }
+
+    /// Returns a template by name
+    pub fn get(&self, name: &str) -> Result<&FrameTemplate> {
+        self.templates
+            .get(name)
+            .ok_or_else(|| PayBySquareError::UnknownFrameTemplate(name.to_string()))
+    }
+
+    /// Selects the template for a request: explicit name, then the `default` template
+    ///
+    /// Returns `None` when the drawn frame should be used.
+    pub fn resolve(&self, requested: Option<&str>) -> Result<Option<&FrameTemplate>> {
+        match requested {
+            Some(name) => self.get(name).map(Some),
+            None => Ok(self.templates.get(DEFAULT_TEMPLATE)),
+        }
+    }
+}
+
+pub(crate) fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|e| e == extension)
+}
+
+pub(crate) fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RgbaColor;
+    use image::Rgba;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_pixel(width, height, Rgba([200, 200, 200, 255]));
+        let mut data = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut data),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+        data
+    }
+
+    fn options(qr_size: u32) -> QrOptions {
+        QrOptions {
+            qr_size,
+            ..Default::default()
+        }
+    }
+
+    fn decode(data: &[u8]) -> RgbaImage {
+        image::load_from_memory(data).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn test_render_with_placement_and_fit_scale() {
+        let metadata = TemplateMetadata {
+            qr: Some(Placement {
+                x: 10,
+                y: 10,
+                size: 80,
+            }),
+            caption: Some(Area {
+                x: 10,
+                y: 95,
+                width: 80,
+                height: 20,
+            }),
+            ..Default::default()
+        };
+        let template = FrameTemplate::new("test", &png(100, 120), metadata).unwrap();
+        let img = decode(&template.render("TEST", &options(160)).unwrap());
+
+        // Template doubled so that the symbol is 160 px wide
+        assert_eq!(img.dimensions(), (200, 240));
+        // Quiet zone starts at the placement, finder pattern inside it
+        assert_eq!(img.get_pixel(21, 21).0, [255, 255, 255, 255]);
+        assert_eq!(img.get_pixel(20 + 41, 20 + 41).0, [0, 0, 0, 255]);
+        // Caption drawn in the caption area
+        let caption_pixels = (20..180)
+            .flat_map(|x| (190..230).map(move |y| (x, y)))
+            .filter(|&(x, y)| img.get_pixel(x, y).0 == RgbaColor::frame().0)
+            .count();
+        assert!(caption_pixels > 100);
+    }
+
+    #[test]
+    fn test_from_image_centers_symbol() {
+        let template = FrameTemplate::from_image("frame", &png(200, 200)).unwrap();
+        let img = decode(&template.render("TEST", &options(300)).unwrap());
+        assert_eq!(img.dimensions(), (200, 200));
+        assert_eq!(img.get_pixel(14, 100).0, [200, 200, 200, 255]);
+        assert_eq!(img.get_pixel(16, 100).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_dense_symbol_is_not_resampled() {
+        // 1000 characters need more than one pixel per module of 100 px
+        let code = "0123456789ABCDEFGHIJKLMNOPQRSTUV".repeat(32)[..1000].to_string();
+        let (qr, _) = build_symbol(&code, &options(100)).unwrap();
+        let dimension = qr.width() as u32 + 8;
+        assert!(dimension > 100);
+
+        // A template scaled to fit grows so that every module is one pixel
+        let template = FrameTemplate::new("fit", &png(100, 100), Default::default()).unwrap();
+        let img = template.render_image(&code, &options(100)).unwrap();
+        assert!(img.width() > 100);
+        assert_eq!(
+            crate::read_qr(&image::DynamicImage::ImageRgba8(img)).as_deref(),
+            Some(code.as_str())
+        );
+
+        // A fixed template can't hold it
+        let template = FrameTemplate::from_image("fixed", &png(100, 100)).unwrap();
+        assert!(matches!(
+            template.render_image(&code, &options(100)),
+            Err(PayBySquareError::SymbolTooLarge { pixels, max: 85 }) if pixels == dimension
+        ));
+    }
+
+    #[test]
+    fn test_placement_outside_image() {
+        let metadata = TemplateMetadata {
+            qr: Some(Placement {
+                x: 50,
+                y: 0,
+                size: 80,
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            FrameTemplate::new("test", &png(100, 100), metadata),
+            Err(PayBySquareError::ConfigError(_))
+        ));
+
+        // Coordinates whose sum overflows u32
+        let metadata = TemplateMetadata {
+            qr: Some(Placement {
+                x: u32::MAX - 10,
+                y: 0,
+                size: 80,
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            FrameTemplate::new("test", &png(100, 100), metadata),
+            Err(PayBySquareError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_directory() {
+        let registry =
+            FrameTemplateRegistry::load(concat!(env!("CARGO_MANIFEST_DIR"), "/frames")).unwrap();
+        let classic = registry.get("classic").unwrap();
+        assert_eq!(classic.qr.size, 184);
+
+        assert!(registry.resolve(None).unwrap().is_none());
+        assert!(registry.resolve(Some("classic")).unwrap().is_some());
+        assert!(matches!(
+            registry.resolve(Some("missing")),
+            Err(PayBySquareError::UnknownFrameTemplate(_))
+        ));
+    }
+}
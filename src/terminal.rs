@@ -0,0 +1,179 @@
+use crate::errors::Result;
+use crate::qr::{build_qr_code, QUIET_ZONE};
+use qrcode::{Color, QrCode};
+
+/// Characters used to draw modules as text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextStyle {
+    /// Unicode half blocks, two module rows per line
+    #[default]
+    HalfBlocks,
+    /// `##` per module, one module row per line
+    Ascii,
+}
+
+/// Options of the text renderer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextOptions {
+    pub style: TextStyle,
+    /// Quiet zone around the symbol in modules
+    pub quiet_zone: u32,
+    /// Draw light modules instead of dark ones, for light text on a dark terminal
+    pub invert: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            style: TextStyle::default(),
+            quiet_zone: QUIET_ZONE as u32,
+            invert: false,
+        }
+    }
+}
+
+/// Generates a QR code as text for terminals and logs
+///
+/// Drawn characters stand for dark modules, which suits dark text on a light
+/// background; `opts.invert` draws light modules and the quiet zone instead.
+/// Every line ends with `\n`.
+pub fn generate_qr_text(code: &str, opts: &TextOptions) -> Result<String> {
+    Ok(render_text(&build_qr_code(code)?, opts))
+}
+
+/// Renders a symbol as text
+fn render_text(qr: &QrCode, opts: &TextOptions) -> String {
+    let quiet_zone = opts.quiet_zone as usize;
+    let width = qr.width();
+    let dimension = width + 2 * quiet_zone;
+    let colors = qr.to_colors();
+
+    // Modules outside the symbol are light
+    let drawn = |x: usize, y: usize| {
+        let dark = (quiet_zone..quiet_zone + width).contains(&x)
+            && (quiet_zone..quiet_zone + width).contains(&y)
+            && colors[(y - quiet_zone) * width + x - quiet_zone] == Color::Dark;
+        dark != opts.invert
+    };
+
+    let mut text = String::new();
+    match opts.style {
+        TextStyle::HalfBlocks => {
+            for y in (0..dimension).step_by(2) {
+                for x in 0..dimension {
+                    let bottom = if y + 1 < dimension {
+                        drawn(x, y + 1)
+                    } else {
+                        opts.invert
+                    };
+                    text.push(match (drawn(x, y), bottom) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    });
+                }
+                text.push('\n');
+            }
+        }
+        TextStyle::Ascii => {
+            for y in 0..dimension {
+                for x in 0..dimension {
+                    text.push_str(if drawn(x, y) { "##" } else { "  " });
+                }
+                text.push('\n');
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "0004A00090IFU3P5R8G9E0N4FD1K5U";
+
+    /// Reads the module grid back from half-block text
+    fn parse_half_blocks(text: &str, dimension: usize) -> Vec<bool> {
+        let mut modules = vec![false; dimension * dimension];
+        for (line, row) in text.lines().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let (top, bottom) = match c {
+                    '█' => (true, true),
+                    '▀' => (true, false),
+                    '▄' => (false, true),
+                    _ => (false, false),
+                };
+                modules[2 * line * dimension + x] = top;
+                if 2 * line + 1 < dimension {
+                    modules[(2 * line + 1) * dimension + x] = bottom;
+                }
+            }
+        }
+        modules
+    }
+
+    #[test]
+    fn test_half_blocks_match_symbol() {
+        let qr = build_qr_code(CODE).unwrap();
+        let text = generate_qr_text(CODE, &TextOptions::default()).unwrap();
+        let dimension = qr.width() + 8;
+
+        assert_eq!(text.lines().count(), dimension.div_ceil(2));
+        assert!(text.lines().all(|line| line.chars().count() == dimension));
+        // Quiet zone is blank
+        assert!(text.lines().next().unwrap().chars().all(|c| c == ' '));
+
+        let modules = parse_half_blocks(&text, dimension);
+        let colors = qr.to_colors();
+        for y in 0..qr.width() {
+            for x in 0..qr.width() {
+                assert_eq!(
+                    modules[(y + 4) * dimension + x + 4],
+                    colors[y * qr.width() + x] == Color::Dark
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_inverted_text() {
+        let opts = TextOptions {
+            invert: true,
+            ..Default::default()
+        };
+        let text = generate_qr_text(CODE, &opts).unwrap();
+        let normal = generate_qr_text(CODE, &TextOptions::default()).unwrap();
+        assert!(text.lines().next().unwrap().chars().all(|c| c == '█'));
+        assert!(text.lines().last().unwrap().chars().all(|c| c == '█'));
+
+        let swapped: String = normal
+            .chars()
+            .map(|c| match c {
+                '█' => ' ',
+                ' ' => '█',
+                '▀' => '▄',
+                '▄' => '▀',
+                c => c,
+            })
+            .collect();
+        assert_eq!(text, swapped);
+    }
+
+    #[test]
+    fn test_ascii_text() {
+        let qr = build_qr_code(CODE).unwrap();
+        let opts = TextOptions {
+            style: TextStyle::Ascii,
+            quiet_zone: 0,
+            invert: false,
+        };
+        let text = generate_qr_text(CODE, &opts).unwrap();
+        assert_eq!(text.lines().count(), qr.width());
+        assert!(text.lines().all(|line| line.len() == 2 * qr.width()));
+        // Finder pattern in the top-left corner
+        assert!(text.starts_with(&"#".repeat(14)));
+        assert!(text.lines().nth(1).unwrap().starts_with("##          ##"));
+    }
+}
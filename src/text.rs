@@ -0,0 +1,145 @@
+use crate::frame::{Contour, Segment};
+use ab_glyph::{point, Font, FontRef, OutlineCurve, Point, PxScale, ScaleFont};
+
+/// Weight of the embedded Noto Sans font
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Weight {
+    Regular,
+    Bold,
+}
+
+impl Weight {
+    fn font(self) -> FontRef<'static> {
+        let data = match self {
+            Weight::Regular => notosans::REGULAR_TTF,
+            Weight::Bold => notosans::BOLD_TTF,
+        };
+        FontRef::try_from_slice(data).expect("embedded Noto Sans font is valid")
+    }
+}
+
+/// Piece of text set in a single weight
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TextRun<'a> {
+    pub weight: Weight,
+    pub text: &'a str,
+}
+
+impl<'a> TextRun<'a> {
+    pub fn regular(text: &'a str) -> Self {
+        Self {
+            weight: Weight::Regular,
+            text,
+        }
+    }
+
+    pub fn bold(text: &'a str) -> Self {
+        Self {
+            weight: Weight::Bold,
+            text,
+        }
+    }
+}
+
+/// Glyph outlines of a line of text starting at the origin on the baseline
+///
+/// Coordinates grow downwards, so `top` is negative and `bottom` is the depth of
+/// the descenders.
+#[derive(Debug, Clone)]
+pub(crate) struct TextOutline {
+    pub contours: Vec<Contour>,
+    pub width: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl TextOutline {
+    /// Height of the inked area
+    pub fn height(&self) -> f32 {
+        self.bottom - self.top
+    }
+}
+
+/// Lays out runs of text at the given em size and converts the glyphs to contours
+pub(crate) fn outline_text(runs: &[TextRun], em: f32) -> TextOutline {
+    let mut contours: Vec<Contour> = Vec::new();
+    let mut caret = 0.0;
+    let mut top: f32 = 0.0;
+    let mut bottom: f32 = 0.0;
+
+    for run in runs {
+        let font = run.weight.font();
+        let units_per_em = font.units_per_em().unwrap_or(1000.0);
+        let scaled = font.as_scaled(PxScale::from(em * font.height_unscaled() / units_per_em));
+        let (h_scale, v_scale) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+
+        let mut previous = None;
+        for c in run.text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            previous = Some(id);
+
+            if let Some(outline) = font.outline(id) {
+                // Font units grow upwards
+                let map = |p: Point| point(caret + p.x * h_scale, -p.y * v_scale);
+                for curve in &outline.curves {
+                    let (start, segment) = match *curve {
+                        OutlineCurve::Line(p0, p1) => (p0, Segment::Line(map(p1))),
+                        OutlineCurve::Quad(p0, c, p1) => (p0, Segment::Quad(map(c), map(p1))),
+                        OutlineCurve::Cubic(p0, c0, c1, p1) => {
+                            (p0, Segment::Cubic(map(c0), map(c1), map(p1)))
+                        }
+                    };
+                    let start = map(start);
+                    let end = segment.end();
+                    top = top.min(start.y).min(end.y);
+                    bottom = bottom.max(start.y).max(end.y);
+                    match contours.last_mut() {
+                        Some(contour) if contour.end() == start => contour.segments.push(segment),
+                        _ => contours.push(Contour {
+                            start,
+                            segments: vec![segment],
+                        }),
+                    }
+                }
+            }
+
+            caret += scaled.h_advance(id);
+        }
+    }
+
+    TextOutline {
+        contours,
+        width: caret,
+        top,
+        bottom,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outline_text() {
+        let text = outline_text(
+            &[TextRun::bold("PAY"), TextRun::regular(" by square")],
+            100.0,
+        );
+        assert!(!text.contours.is_empty());
+        assert!(text.width > 500.0 && text.width < 800.0);
+        // Ascender of "b" above the baseline, descenders of "y" and "q" below it
+        assert!(text.top < -70.0);
+        assert!(text.bottom > 15.0);
+    }
+
+    #[test]
+    fn test_outline_text_scales_linearly() {
+        let small = outline_text(&[TextRun::regular("square")], 10.0);
+        let large = outline_text(&[TextRun::regular("square")], 20.0);
+        assert!((large.width - 2.0 * small.width).abs() < 0.01);
+        assert!((large.height() - 2.0 * small.height()).abs() < 0.01);
+    }
+}
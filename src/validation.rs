@@ -1,39 +1,167 @@
 use crate::errors::{PayBySquareError, Result};
-use crate::models::PaymentRequest;
+use crate::i18n::Locale;
+use crate::models::{
+    LabelSheetOptions, PaymentRequest, QrOptions, RgbaColor, StampPlacement, Warning,
+};
+use crate::profiles::{RuleLevel, ValidationProfile};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Minimum luminance contrast ratio between foreground and background
+pub const MIN_CONTRAST_RATIO: f32 = 4.0;
+
+/// Validation rules that can be configured per profile
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// Amount must be greater than 0
+    Amount,
+    /// Either `iban` or `bank_accounts` must be provided
+    BankAccount,
+    /// IBAN format
+    Iban,
+    /// SWIFT/BIC format
+    Swift,
+    /// Maximum field lengths
+    FieldLength,
+    /// Payment due date must be set and not in the past
+    DueDate,
+    /// Variable, constant and specific symbols must be numeric
+    NumericSymbols,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 7] = [
+        Rule::Amount,
+        Rule::BankAccount,
+        Rule::Iban,
+        Rule::Swift,
+        Rule::FieldLength,
+        Rule::DueDate,
+        Rule::NumericSymbols,
+    ];
+
+    /// Returns the rule name as used in profiles and responses
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::Amount => "amount",
+            Rule::BankAccount => "bank_account",
+            Rule::Iban => "iban",
+            Rule::Swift => "swift",
+            Rule::FieldLength => "field_length",
+            Rule::DueDate => "due_date",
+            Rule::NumericSymbols => "numeric_symbols",
+        }
+    }
+}
+
+/// A rule violation downgraded to a warning by the validation profile
+#[derive(Debug)]
+pub struct ValidationWarning {
+    pub rule: Rule,
+    pub error: PayBySquareError,
+}
+
+impl ValidationWarning {
+    /// Converts the warning into its API representation
+    pub fn to_warning(&self, locale: Locale) -> Warning {
+        Warning {
+            rule: self.rule,
+            message: self.error.localized(locale),
+        }
+    }
+}
 
 /// Validates a payment request
 pub fn validate_payment_request(payment: &PaymentRequest) -> Result<()> {
-    // Validate amount
+    validate_payment_request_with_profile(payment, &ValidationProfile::standard()).map(|_| ())
+}
+
+/// Validates a payment request according to a validation profile
+///
+/// Violations of rules at `error` level fail the validation, violations of
+/// rules at `warning` level are returned.
+pub fn validate_payment_request_with_profile(
+    payment: &PaymentRequest,
+    profile: &ValidationProfile,
+) -> Result<Vec<ValidationWarning>> {
+    let mut warnings = Vec::new();
+
+    for rule in Rule::ALL {
+        let level = profile.level(rule);
+        if level == RuleLevel::Off {
+            continue;
+        }
+
+        if let Err(error) = check_rule(rule, payment) {
+            if level == RuleLevel::Error {
+                return Err(error);
+            }
+            warnings.push(ValidationWarning { rule, error });
+        }
+    }
+
+    Ok(warnings)
+}
+
+fn check_rule(rule: Rule, payment: &PaymentRequest) -> Result<()> {
+    match rule {
+        Rule::Amount => check_amount(payment),
+        Rule::BankAccount => check_bank_account(payment),
+        Rule::Iban => check_ibans(payment),
+        Rule::Swift => check_swifts(payment),
+        Rule::FieldLength => check_field_lengths(payment),
+        Rule::DueDate => check_due_date(payment),
+        Rule::NumericSymbols => check_numeric_symbols(payment),
+    }
+}
+
+fn check_amount(payment: &PaymentRequest) -> Result<()> {
     if payment.amount <= 0.0 {
         return Err(PayBySquareError::InvalidAmount);
     }
+    Ok(())
+}
 
-    // Validate that either iban or bank_accounts is provided
+fn check_bank_account(payment: &PaymentRequest) -> Result<()> {
+    // Either iban or bank_accounts must be provided
     if payment.iban.is_none() && payment.bank_accounts.is_none() {
         return Err(PayBySquareError::MissingBankAccount);
     }
+    Ok(())
+}
 
-    // Validate IBAN format
+fn check_ibans(payment: &PaymentRequest) -> Result<()> {
     if let Some(ref iban) = payment.iban {
         validate_iban(iban)?;
     }
 
-    // Validate bank accounts
     if let Some(ref accounts) = payment.bank_accounts {
         for account in accounts {
             validate_iban(&account.iban)?;
-            if let Some(ref swift) = account.swift {
-                validate_swift(swift)?;
-            }
         }
     }
 
-    // Validate SWIFT if provided
+    Ok(())
+}
+
+fn check_swifts(payment: &PaymentRequest) -> Result<()> {
     if let Some(ref swift) = payment.swift {
         validate_swift(swift)?;
     }
 
-    // Validate field lengths
+    if let Some(ref accounts) = payment.bank_accounts {
+        for swift in accounts.iter().filter_map(|a| a.swift.as_ref()) {
+            validate_swift(swift)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_field_lengths(payment: &PaymentRequest) -> Result<()> {
     if let Some(ref invoice_id) = payment.invoice_id {
         validate_length("invoice_id", invoice_id, 10)?;
     }
@@ -73,22 +201,131 @@ pub fn validate_payment_request(payment: &PaymentRequest) -> Result<()> {
     Ok(())
 }
 
+fn check_due_date(payment: &PaymentRequest) -> Result<()> {
+    let due_date = payment
+        .payment_due_date
+        .ok_or(PayBySquareError::ValidationError {
+            key: "validation.due_date_missing",
+        })?;
+
+    if due_date < chrono::Local::now().date_naive() {
+        return Err(PayBySquareError::ValidationError {
+            key: "validation.due_date_past",
+        });
+    }
+
+    Ok(())
+}
+
+fn check_numeric_symbols(payment: &PaymentRequest) -> Result<()> {
+    let symbols = [
+        (
+            &payment.variable_symbol,
+            "validation.variable_symbol_numeric",
+        ),
+        (
+            &payment.constant_symbol,
+            "validation.constant_symbol_numeric",
+        ),
+        (
+            &payment.specific_symbol,
+            "validation.specific_symbol_numeric",
+        ),
+    ];
+
+    for (symbol, key) in symbols {
+        if let Some(value) = symbol {
+            if !value.chars().all(|c| c.is_ascii_digit()) {
+                return Err(PayBySquareError::ValidationError { key });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates QR rendering options
+pub fn validate_qr_options(opts: &QrOptions) -> Result<()> {
+    validate_range("qr_size", opts.qr_size as f32, 100.0, 2000.0)?;
+    validate_range("quiet_zone", opts.quiet_zone as f32, 0.0, 16.0)?;
+    validate_range("max_version", opts.max_version as f32, 1.0, 40.0)?;
+
+    if let Some(size_mm) = opts.size_mm {
+        validate_range("size_mm", size_mm, 10.0, 500.0)?;
+    }
+    if let Some(dpi) = opts.dpi {
+        // Also keeps the resolution adjusted for `size_mm` within the 16 bits of JFIF density
+        validate_range("dpi", dpi as f32, 72.0, 2400.0)?;
+    }
+
+    validate_colors(opts.foreground, opts.background)
+}
+
+/// Validates a print resolution written into image metadata
+///
+/// JFIF stores the density in whole dots per inch as 16 bits, so the
+/// resolution must round to 1-65535. PNG output is checked the same way so
+/// both formats accept the same resolutions.
+pub fn validate_print_resolution(dpi: f32) -> Result<()> {
+    validate_range("dpi", dpi.round(), 1.0, u16::MAX as f32)
+}
+
+/// Validates label sheet options
+pub fn validate_label_sheet_options(sheet: &LabelSheetOptions) -> Result<()> {
+    if let Some(margin_mm) = sheet.margin_mm {
+        validate_range("margin_mm", margin_mm, 0.0, 50.0)?;
+    }
+    validate_range("padding_mm", sheet.padding_mm, 0.0, 20.0)
+}
+
+/// Validates the size of a stamped code
+///
+/// The page number and position depend on the document and are checked when stamping.
+pub fn validate_stamp_placement(placement: &StampPlacement) -> Result<()> {
+    validate_range("size_mm", placement.size_mm, 10.0, 500.0)
+}
+
+/// Validates that dark modules stand out against the background
+///
+/// Translucent colours are judged as printed on white paper. Scanners expect
+/// dark modules on a light background, so inverted combinations are rejected
+/// regardless of their contrast.
+pub fn validate_colors(foreground: RgbaColor, background: RgbaColor) -> Result<()> {
+    let background = background.over(RgbaColor::white());
+    let foreground = foreground.over(background);
+
+    let dark = foreground.relative_luminance();
+    let light = background.relative_luminance();
+    if dark > light {
+        return Err(PayBySquareError::InvertedColors);
+    }
+
+    let ratio = (light + 0.05) / (dark + 0.05);
+    if ratio < MIN_CONTRAST_RATIO {
+        return Err(PayBySquareError::LowContrast {
+            ratio,
+            min: MIN_CONTRAST_RATIO,
+        });
+    }
+    Ok(())
+}
+
 /// Validates IBAN format (basic validation)
 fn validate_iban(iban: &str) -> Result<()> {
     let iban_clean = iban.replace(' ', "");
 
     // IBAN must be 15-34 characters
     if iban_clean.len() < 15 || iban_clean.len() > 34 {
-        return Err(PayBySquareError::InvalidIban(
-            "IBAN must be between 15 and 34 characters".to_string(),
-        ));
+        return Err(PayBySquareError::InvalidIban {
+            key: "validation.iban_length",
+        });
     }
 
     // IBAN must start with 2 letters (country code)
     if !iban_clean.chars().take(2).all(|c| c.is_ascii_alphabetic()) {
-        return Err(PayBySquareError::InvalidIban(
-            "IBAN must start with a 2-letter country code".to_string(),
-        ));
+        return Err(PayBySquareError::InvalidIban {
+            key: "validation.iban_country_code",
+        });
     }
 
     // Next 2 characters must be digits (check digits)
@@ -98,16 +335,16 @@ fn validate_iban(iban: &str) -> Result<()> {
         .take(2)
         .all(|c| c.is_ascii_digit())
     {
-        return Err(PayBySquareError::InvalidIban(
-            "IBAN check digits must be numeric".to_string(),
-        ));
+        return Err(PayBySquareError::InvalidIban {
+            key: "validation.iban_check_digits",
+        });
     }
 
     // Rest must be alphanumeric
     if !iban_clean.chars().all(|c| c.is_ascii_alphanumeric()) {
-        return Err(PayBySquareError::InvalidIban(
-            "IBAN contains invalid characters".to_string(),
-        ));
+        return Err(PayBySquareError::InvalidIban {
+            key: "validation.iban_characters",
+        });
     }
 
     Ok(())
@@ -119,21 +356,33 @@ fn validate_swift(swift: &str) -> Result<()> {
 
     // SWIFT must be 8 or 11 characters
     if swift_clean.len() != 8 && swift_clean.len() != 11 {
-        return Err(PayBySquareError::InvalidSwift(
-            "SWIFT/BIC must be 8 or 11 characters".to_string(),
-        ));
+        return Err(PayBySquareError::InvalidSwift {
+            key: "validation.swift_length",
+        });
     }
 
     // Must be alphanumeric
     if !swift_clean.chars().all(|c| c.is_ascii_alphanumeric()) {
-        return Err(PayBySquareError::InvalidSwift(
-            "SWIFT/BIC contains invalid characters".to_string(),
-        ));
+        return Err(PayBySquareError::InvalidSwift {
+            key: "validation.swift_characters",
+        });
     }
 
     Ok(())
 }
 
+/// Validates that an option lies within bounds
+fn validate_range(option: &str, value: f32, min: f32, max: f32) -> Result<()> {
+    if !(min..=max).contains(&value) {
+        return Err(PayBySquareError::OptionOutOfRange {
+            option: option.to_string(),
+            min,
+            max,
+        });
+    }
+    Ok(())
+}
+
 /// Validates string length
 fn validate_length(field: &str, value: &str, max: usize) -> Result<()> {
     if value.len() > max {
@@ -145,3 +394,88 @@ fn validate_length(field: &str, value: &str, max: usize) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment() -> PaymentRequest {
+        serde_json::from_value(serde_json::json!({
+            "amount": 10.0,
+            "iban": "SK9611000000002918599669",
+            "swift": "GIBASKBX12",
+            "variable_symbol": "INV-1"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_standard_profile_rejects_invalid_swift() {
+        let result = validate_payment_request(&payment());
+        assert!(matches!(result, Err(PayBySquareError::InvalidSwift { .. })));
+    }
+
+    #[test]
+    fn test_lenient_profile_returns_warnings() {
+        let warnings =
+            validate_payment_request_with_profile(&payment(), &ValidationProfile::lenient())
+                .unwrap();
+        let rules: Vec<_> = warnings.iter().map(|w| w.rule).collect();
+        assert_eq!(rules, [Rule::Swift]);
+    }
+
+    #[test]
+    fn test_validate_qr_options() {
+        assert!(validate_qr_options(&QrOptions::default()).is_ok());
+
+        let opts = QrOptions {
+            qr_size: 5000,
+            ..Default::default()
+        };
+        assert!(matches!(
+            validate_qr_options(&opts),
+            Err(PayBySquareError::OptionOutOfRange { .. })
+        ));
+
+        let opts = QrOptions {
+            dpi: Some(10),
+            ..Default::default()
+        };
+        assert!(matches!(
+            validate_qr_options(&opts),
+            Err(PayBySquareError::OptionOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_colors() {
+        let color = |s: &str| s.parse::<RgbaColor>().unwrap();
+
+        assert!(validate_colors(RgbaColor::black(), RgbaColor::white()).is_ok());
+        assert!(validate_colors(color("#0066CC"), color("#FFFFFF00")).is_ok());
+        assert!(matches!(
+            validate_colors(RgbaColor::white(), RgbaColor::black()),
+            Err(PayBySquareError::InvertedColors)
+        ));
+        assert!(matches!(
+            validate_colors(color("#999999"), RgbaColor::white()),
+            Err(PayBySquareError::LowContrast { .. })
+        ));
+        // Faded foreground loses contrast
+        assert!(matches!(
+            validate_colors(color("#00000040"), RgbaColor::white()),
+            Err(PayBySquareError::LowContrast { .. })
+        ));
+    }
+
+    #[test]
+    fn test_strict_profile_enforces_due_date() {
+        let mut payment = payment();
+        payment.swift = None;
+        let result = validate_payment_request_with_profile(&payment, &ValidationProfile::strict());
+        assert!(matches!(
+            result,
+            Err(PayBySquareError::ValidationError { .. })
+        ));
+    }
+}
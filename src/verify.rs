@@ -0,0 +1,222 @@
+use crate::errors::{PayBySquareError, Result};
+use crate::generator::decode_pay_by_square_data;
+use crate::models::PaymentRequest;
+use crate::reader::read_qr;
+use image::DynamicImage;
+
+/// Scans an encoded image and checks that it carries `code` for `payment`
+pub fn verify_image(image_data: &[u8], code: &str, payment: &PaymentRequest) -> Result<()> {
+    let img = image::load_from_memory(image_data)
+        .map_err(|e| PayBySquareError::ImageError(format!("Failed to load image: {}", e)))?;
+    verify_rendered(&img, code, payment)
+}
+
+/// Scans an image and checks that it carries `code` for `payment`
+pub fn verify_rendered(img: &DynamicImage, code: &str, payment: &PaymentRequest) -> Result<()> {
+    let decoded = read_qr(img).ok_or(PayBySquareError::VerificationFailed {
+        key: "verify.unreadable",
+    })?;
+    if decoded != code {
+        return Err(PayBySquareError::VerificationFailed {
+            key: "verify.text_differs",
+        });
+    }
+    verify_code(code, payment)
+}
+
+/// Checks that a code decodes back to the data of `payment`
+///
+/// The decoded fields are parsed and compared with the payment: amount,
+/// currency, bank accounts and the variable, constant and specific symbol.
+pub fn verify_code(code: &str, payment: &PaymentRequest) -> Result<()> {
+    let failed = |key| Err(PayBySquareError::VerificationFailed { key });
+    let Some(data) = decode_pay_by_square_data(code) else {
+        return failed("verify.undecodable");
+    };
+    let fields: Vec<&str> = data.split('\t').collect();
+    let field = |index: usize| fields.get(index).copied().unwrap_or_default();
+
+    // Amount is encoded with two decimals
+    let amount = field(1).parse::<f64>().ok();
+    if amount.is_none_or(|amount| (amount - payment.amount).abs() > 0.005) {
+        return failed("verify.amount");
+    }
+    if field(2) != payment.currency {
+        return failed("verify.currency");
+    }
+
+    let symbols = [
+        &payment.variable_symbol,
+        &payment.constant_symbol,
+        &payment.specific_symbol,
+    ];
+    if (4..7)
+        .zip(symbols)
+        .any(|(index, symbol)| field(index) != symbol.as_deref().unwrap_or_default())
+    {
+        return failed("verify.symbols");
+    }
+
+    // Accounts are "IBAN|SWIFT" or "IBAN", separated by commas
+    let decoded: Vec<(&str, Option<&str>)> = field(9)
+        .split(',')
+        .filter(|account| !account.is_empty())
+        .map(|account| match account.split_once('|') {
+            Some((iban, swift)) => (iban, Some(swift)),
+            None => (account, None),
+        })
+        .collect();
+    let expected: Vec<(&str, Option<&str>)> = match payment.bank_accounts {
+        Some(ref accounts) => accounts
+            .iter()
+            .map(|account| (account.iban.as_str(), account.swift.as_deref()))
+            .collect(),
+        None => payment
+            .iban
+            .iter()
+            .map(|iban| (iban.as_str(), payment.swift.as_deref()))
+            .collect(),
+    };
+    if decoded != expected {
+        return failed("verify.account");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::generate_pay_by_square_code;
+    use crate::models::{OutputFormat, QrOptions, RgbaColor};
+    use crate::profiles::ValidationProfile;
+    use crate::qr::generate_qr_image;
+    use crate::templates::FrameTemplate;
+
+    fn payment(amount: f64) -> PaymentRequest {
+        serde_json::from_value(serde_json::json!({
+            "amount": amount,
+            "iban": "SK9611000000002918599669",
+            "variable_symbol": "1234567890"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_image() {
+        let code = generate_pay_by_square_code(&payment(100.0)).unwrap();
+        let image_data = generate_qr_image(&code, 300).unwrap();
+        verify_image(&image_data, &code, &payment(100.0)).unwrap();
+
+        // Same image checked against another payment
+        let other = generate_pay_by_square_code(&payment(200.0)).unwrap();
+        assert!(matches!(
+            verify_image(&image_data, &other, &payment(200.0)),
+            Err(PayBySquareError::VerificationFailed { .. })
+        ));
+        assert!(matches!(
+            verify_code(&code, &payment(200.0)),
+            Err(PayBySquareError::VerificationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_code_compares_fields() {
+        let code = generate_pay_by_square_code(&payment(100.0)).unwrap();
+        verify_code(&code, &payment(100.0)).unwrap();
+
+        let key = |payment: PaymentRequest| match verify_code(&code, &payment) {
+            Err(PayBySquareError::VerificationFailed { key }) => key,
+            result => panic!("unexpected result {:?}", result),
+        };
+        assert_eq!(key(payment(100.01)), "verify.amount");
+        assert_eq!(
+            key(PaymentRequest {
+                currency: "CZK".to_string(),
+                ..payment(100.0)
+            }),
+            "verify.currency"
+        );
+        assert_eq!(
+            key(PaymentRequest {
+                constant_symbol: Some("0308".to_string()),
+                ..payment(100.0)
+            }),
+            "verify.symbols"
+        );
+        assert_eq!(
+            key(PaymentRequest {
+                swift: Some("TATRSKBX".to_string()),
+                ..payment(100.0)
+            }),
+            "verify.account"
+        );
+
+        assert_eq!(
+            PayBySquareError::VerificationFailed {
+                key: "verify.amount"
+            }
+            .localized(crate::i18n::Locale::Sk),
+            "Vygenerovaný kód neprešiel overením: kód obsahuje inú sumu"
+        );
+    }
+
+    #[test]
+    fn test_verify_unreadable_image() {
+        let code = generate_pay_by_square_code(&payment(100.0)).unwrap();
+        let img = image::load_from_memory(&generate_qr_image(&code, 300).unwrap()).unwrap();
+
+        // Scaled down below one pixel per module
+        let mut image_data = Vec::new();
+        img.resize(40, 40, image::imageops::FilterType::Triangle)
+            .write_to(
+                &mut std::io::Cursor::new(&mut image_data),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        assert!(matches!(
+            verify_image(&image_data, &code, &payment(100.0)),
+            Err(PayBySquareError::VerificationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_generate_with_verify() {
+        for format in [
+            OutputFormat::Png,
+            OutputFormat::Jpeg,
+            OutputFormat::Svg,
+            OutputFormat::Pdf,
+        ] {
+            let opts = QrOptions {
+                format,
+                verify: true,
+                ..Default::default()
+            };
+            crate::generate_pay_by_square_qr(&payment(100.0), opts, None).unwrap();
+        }
+
+        // Transparent light modules over a black template
+        let mut frame = Vec::new();
+        image::RgbaImage::from_pixel(300, 300, image::Rgba([0, 0, 0, 255]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut frame),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        let template = FrameTemplate::from_image("black", &frame).unwrap();
+        let opts = QrOptions {
+            verify: true,
+            background: RgbaColor([255, 255, 255, 0]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            crate::generate_pay_by_square_qr_with_profile(
+                &payment(100.0),
+                opts,
+                Some(&template),
+                &ValidationProfile::standard()
+            ),
+            Err(PayBySquareError::VerificationFailed { .. })
+        ));
+    }
+}
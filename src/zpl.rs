@@ -0,0 +1,306 @@
+use crate::errors::Result;
+use crate::logo::PlacedLogo;
+use crate::models::{QrOptions, RgbaColor};
+use crate::qr::{build_symbol, print_options, render_symbol};
+use crate::templates::FrameTemplate;
+use image::RgbaImage;
+use qrcode::{EcLevel, QrCode};
+use std::fmt::Write;
+
+/// Print resolution of ZPL output when `opts.dpi` is not set (8 dots/mm)
+pub const ZPL_DEFAULT_DPI: u32 = 203;
+
+/// Largest `^BQ` magnification, in dots per module
+const MAX_MAGNIFICATION: u32 = 10;
+
+/// Largest field data of a `^BQ` code; longer codes are sent as a graphic
+const MAX_NATIVE_DATA: usize = 3072 - 4;
+
+/// Relative luminance below which pixels print black, about that of mid-grey
+const DARK_LUMINANCE: f32 = 0.2;
+
+/// Caption font height in points
+const CAPTION_FONT_SIZE: f32 = 9.0;
+
+/// Caption line height relative to the font height
+const CAPTION_LINE_HEIGHT: f32 = 1.4;
+
+/// Generates a ZPL label with the code for Zebra printers
+///
+/// Sizes are in printer dots: `opts.qr_size`, or `opts.size_mm` at `opts.dpi`
+/// (default 203). The code is sent as the printer's own `^BQ` QR code when the
+/// symbol needs nothing the command can't express: no frame, no logo, a module
+/// of at most 10 dots and a code that fits one field. Otherwise the image that
+/// PNG output would contain is sent as a `^GF` graphic, with colours printed
+/// black when darker than mid-grey. `caption` lines are printed under the code
+/// in the printer's scalable font.
+pub fn generate_qr_zpl(
+    code: &str,
+    opts: &QrOptions,
+    caption: &[String],
+    template: Option<&FrameTemplate>,
+) -> Result<String> {
+    let (symbol, logo) = build_symbol(code, opts)?;
+    render_zpl(code, &symbol, logo.as_ref(), opts, caption, template)
+}
+
+/// Renders the encoded symbol of `code`, see [`generate_qr_zpl`]
+pub(crate) fn render_zpl(
+    code: &str,
+    symbol: &QrCode,
+    logo: Option<&PlacedLogo>,
+    opts: &QrOptions,
+    caption: &[String],
+    template: Option<&FrameTemplate>,
+) -> Result<String> {
+    let opts = QrOptions {
+        dpi: Some(opts.dpi.unwrap_or(ZPL_DEFAULT_DPI)),
+        ..opts.clone()
+    };
+    let (opts, _) = print_options(symbol, &opts, template)?;
+
+    // Module size and quiet zone offset in dots, placed as `render_qr` places them
+    let dimension = symbol.width() as u32 + 2 * opts.quiet_zone;
+    let module = (opts.qr_size / dimension).max(1);
+    let side = opts.qr_size.max(dimension * module);
+    let offset = (side - dimension * module) / 2 + opts.quiet_zone * module;
+
+    let native = !opts.with_frame
+        && logo.is_none()
+        && code.len() <= MAX_NATIVE_DATA
+        && module <= MAX_MAGNIFICATION;
+
+    let mut zpl = String::from("^XA\n^CI28\n");
+    let width = if native {
+        let _ = writeln!(
+            zpl,
+            "^FO{offset},{offset}^BQN,2,{module}^FD{}M,A{code}^FS",
+            ec_letter(symbol.error_correction_level()),
+        );
+        side
+    } else {
+        let img = render_symbol(symbol, logo, &opts, template)?;
+        let _ = writeln!(zpl, "^FO0,0{}^FS", graphic_field(&img));
+        img.width()
+    };
+
+    // Scalable font 0 at the caption size in dots
+    let dpi = opts.dpi.unwrap_or(ZPL_DEFAULT_DPI) as f32;
+    let font = (CAPTION_FONT_SIZE / 72.0 * dpi).round() as u32;
+    let line = (font as f32 * CAPTION_LINE_HEIGHT).round() as u32;
+    for (i, text) in caption.iter().enumerate() {
+        let _ = writeln!(
+            zpl,
+            "^FO{},{}^A0N,{font},{font}^FH^FD{}^FS",
+            offset,
+            width + i as u32 * line,
+            escape(text)
+        );
+    }
+
+    let height = width + caption.len() as u32 * line;
+    let _ = write!(zpl, "^PW{width}\n^LL{height}\n^XZ\n");
+    Ok(zpl)
+}
+
+fn ec_letter(level: EcLevel) -> char {
+    match level {
+        EcLevel::L => 'L',
+        EcLevel::M => 'M',
+        EcLevel::Q => 'Q',
+        EcLevel::H => 'H',
+    }
+}
+
+/// Escapes the ZPL control characters of field data for `^FH`
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '_' => "_5F".to_string(),
+            '^' => "_5E".to_string(),
+            '~' => "_7E".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Encodes an image as a compressed ASCII hex `^GF` graphic field
+fn graphic_field(img: &RgbaImage) -> String {
+    let bytes_per_row = img.width().div_ceil(8) as usize;
+    let total = bytes_per_row * img.height() as usize;
+
+    let mut data = String::new();
+    let mut previous: Option<String> = None;
+    for y in 0..img.height() {
+        let mut row = vec![0u8; bytes_per_row];
+        for x in 0..img.width() {
+            if is_dark(RgbaColor(img.get_pixel(x, y).0)) {
+                row[x as usize / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        let hex: String = row.iter().map(|byte| format!("{:02X}", byte)).collect();
+        if previous.as_ref() == Some(&hex) {
+            data.push(':');
+        } else {
+            data.push_str(&compress_row(&hex));
+        }
+        previous = Some(hex);
+    }
+    format!("^GFA,{total},{total},{bytes_per_row},{data}")
+}
+
+/// Returns whether a pixel prints black, judged as seen on white paper
+pub(crate) fn is_dark(color: RgbaColor) -> bool {
+    color.over(RgbaColor::white()).relative_luminance() < DARK_LUMINANCE
+}
+
+/// Compresses a row of hex digits with ZPL run lengths
+///
+/// Runs are prefixed with their length (`G`-`Y` for 1-19, `g`-`z` for
+/// multiples of 20 up to 400); trailing zeros become `,` and trailing `F`s `!`.
+fn compress_row(hex: &str) -> String {
+    let (body, fill) = match hex.trim_end_matches('0') {
+        body if hex.len() - body.len() >= 2 => (body, ","),
+        _ => match hex.trim_end_matches('F') {
+            body if hex.len() - body.len() >= 2 => (body, "!"),
+            _ => (hex, ""),
+        },
+    };
+
+    let mut compressed = String::new();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        let mut count = 1;
+        while chars.next_if_eq(&c).is_some() {
+            count += 1;
+        }
+        compressed.push_str(&repeat_count(count));
+        compressed.push(c);
+    }
+    compressed.push_str(fill);
+    compressed
+}
+
+/// Returns the ZPL repeat count prefix of a run, empty for a single character
+fn repeat_count(count: usize) -> String {
+    if count == 1 {
+        return String::new();
+    }
+    let mut prefix = "z".repeat(count / 400);
+    let twenties = count % 400 / 20;
+    if twenties > 0 {
+        prefix.push((b'g' + twenties as u8 - 1) as char);
+    }
+    let ones = count % 20;
+    if ones > 0 {
+        prefix.push((b'G' + ones as u8 - 1) as char);
+    }
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "0004A00090IFU3P5R8G9E0N4FD1K5U";
+
+    fn options() -> QrOptions {
+        QrOptions {
+            with_frame: false,
+            ..Default::default()
+        }
+    }
+
+    /// Expands a compressed `^GF` data string back into hex rows
+    fn decompress(data: &str, bytes_per_row: usize) -> Vec<String> {
+        let mut rows: Vec<String> = Vec::new();
+        let mut row = String::new();
+        let mut count = 0;
+        for c in data.chars() {
+            match c {
+                'G'..='Y' => count += (c as u8 - b'G' + 1) as usize,
+                'g'..='z' => count += (c as u8 - b'g' + 1) as usize * 20,
+                ':' => rows.push(rows.last().unwrap().clone()),
+                ',' | '!' => {
+                    let fill = if c == ',' { '0' } else { 'F' };
+                    while row.len() < bytes_per_row * 2 {
+                        row.push(fill);
+                    }
+                }
+                c => {
+                    row.push_str(&c.to_string().repeat(count.max(1)));
+                    count = 0;
+                }
+            }
+            if row.len() == bytes_per_row * 2 {
+                rows.push(std::mem::take(&mut row));
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn test_native_qr() {
+        let zpl = generate_qr_zpl(CODE, &options(), &[], None).unwrap();
+        // 33 modules with the quiet zone, 9 dots each in 300 dots
+        assert_eq!(
+            zpl,
+            format!("^XA\n^CI28\n^FO37,37^BQN,2,9^FDMM,A{CODE}^FS\n^PW300\n^LL300\n^XZ\n")
+        );
+    }
+
+    #[test]
+    fn test_graphic_field() {
+        let opts = QrOptions {
+            qr_size: 200,
+            ..Default::default()
+        };
+        let zpl = generate_qr_zpl(CODE, &opts, &[], None).unwrap();
+        assert!(!zpl.contains("^BQ"));
+
+        let field = zpl.lines().nth(2).unwrap();
+        let data = field
+            .strip_prefix("^FO0,0^GFA,5000,5000,25,")
+            .and_then(|data| data.strip_suffix("^FS"))
+            .unwrap();
+        let rows = decompress(data, 25);
+        assert_eq!(rows.len(), 200);
+
+        // Same pixels as the framed PNG output
+        let (qr, logo) = build_symbol(CODE, &opts).unwrap();
+        let img = render_symbol(&qr, logo.as_ref(), &opts, None).unwrap();
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..200u32 {
+                let byte = u8::from_str_radix(&row[x as usize / 8 * 2..][..2], 16).unwrap();
+                let dark = byte & (0x80 >> (x % 8)) != 0;
+                assert_eq!(dark, is_dark(RgbaColor(img.get_pixel(x, y as u32).0)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_compress_row() {
+        assert_eq!(compress_row("FFFFFFFF"), "!");
+        assert_eq!(compress_row("0000"), ",");
+        assert_eq!(compress_row("A0"), "A0");
+        assert_eq!(compress_row("ABBB00"), "AIB,");
+        assert_eq!(repeat_count(45), "hK");
+        assert_eq!(repeat_count(400), "z");
+        assert_eq!(repeat_count(421), "zgG");
+    }
+
+    #[test]
+    fn test_caption_and_size() {
+        let opts = QrOptions {
+            size_mm: Some(25.0),
+            ..options()
+        };
+        let caption = ["Suma: 10,00 EUR".to_string(), "VS_1^2~".to_string()];
+        let zpl = generate_qr_zpl(CODE, &opts, &caption, None).unwrap();
+        // 25 mm at 203 dpi over 25 modules: 8 dots per module
+        assert!(zpl.contains("^FO32,32^BQN,2,8^"));
+        assert!(zpl.contains("^FO32,264^A0N,25,25^FH^FDSuma: 10,00 EUR^FS"));
+        assert!(zpl.contains("^FDVS_5F1_5E2_7E^FS"));
+        assert!(zpl.ends_with("^PW264\n^LL334\n^XZ\n"));
+    }
+}